@@ -0,0 +1,54 @@
+//! Cost of a traced syscall as the number of handlers in the policy grows
+//!
+//! every policy traces `getpid` plus `n - 1` other syscalls, `getpid` being registered last
+//! (the worst case of a linear search). The handlers are looked up by syscall number so the
+//! cost per traced call should stay flat
+//!
+//! run with `cargo bench --bench dispatch`
+use std::time::Instant;
+
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+/// traced `getpid` calls measured per policy
+const CALLS: u32 = 2000;
+
+/// `n` syscalls other than `getpid`
+fn other_syscalls(n: usize) -> impl Iterator<Item = Syscall> {
+    (0..1024)
+        .filter_map(|nr| Syscall::try_from(nr).ok())
+        .filter(|syscall| *syscall != Syscall::Getpid)
+        .take(n)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    println!("{:>10} {:>16}", "handlers", "ns per call");
+    for handlers in [1, 10, 50, 100, 200, 300] {
+        let mut policy = Policy::allow_all().unwrap().with_context(0u64);
+        for syscall in other_syscalls(handlers - 1) {
+            policy.trace(syscall, |_| TraceAction::Continue);
+        }
+        policy.trace_with(Syscall::Getpid, |_, calls| {
+            *calls += 1;
+            TraceAction::Continue
+        });
+
+        let calls = policy
+            .run(move || {
+                let start = Instant::now();
+                for _ in 0..CALLS {
+                    // SAFETY: getpid can't fail, called directly to bypass any caching
+                    unsafe { libc::syscall(libc::SYS_getpid) };
+                }
+                let per_call = start.elapsed().as_nanos() / CALLS as u128;
+                println!("{handlers:>10} {per_call:>16}");
+            })
+            .unwrap();
+        assert_eq!(calls, CALLS as u64);
+    }
+}
@@ -0,0 +1,48 @@
+//! Overhead of the tracer on a syscall heavy workload
+//!
+//! the tracee makes a lot of cheap syscalls: untraced ones, traced ones handled at entry and
+//! traced ones with an exit interceptor, each compared to the same calls without any policy.
+//! Only the exit interceptor should need the extra syscall-exit stop
+//!
+//! run with `cargo bench --bench resume`
+use std::time::Instant;
+
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+/// syscalls made per measurement
+const CALLS: u32 = 20_000;
+
+/// average cost of `syscall(nr)` in ns
+fn measure(nr: libc::c_long) -> u128 {
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        // SAFETY: only argument-less syscalls that can't fail are measured
+        unsafe { libc::syscall(nr) };
+    }
+    start.elapsed().as_nanos() / CALLS as u128
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    println!("{:<36} {:>12}", "workload", "ns per call");
+    println!("{:<36} {:>12}", "no policy", measure(libc::SYS_getppid));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .trace(Syscall::Getpid, |_| TraceAction::Continue)
+        .exit_intercept(Syscall::Getuid, |_| TraceAction::Continue);
+    policy
+        .run(|| {
+            // the first traced syscall used to switch the tracee to a stop at every syscall
+            unsafe { libc::syscall(libc::SYS_getpid) };
+            println!("{:<36} {:>12}", "untraced", measure(libc::SYS_getppid));
+            println!("{:<36} {:>12}", "traced at entry", measure(libc::SYS_getpid));
+            println!("{:<36} {:>12}", "exit intercepted", measure(libc::SYS_getuid));
+        })
+        .unwrap();
+}
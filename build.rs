@@ -13,7 +13,7 @@ fn main() {
     let header_file = match arch.as_str() {
         "x86_64" => "unistd_64.h",
         "x86" | "i386" | "arm" => "unistd_32.h",
-        "aarch64" => "unistd.h",
+        "aarch64" | "riscv64" => "unistd.h",
         other => panic!("Unsupported architecture: {}", other),
     };
 
@@ -55,13 +55,25 @@ fn main() {
 }
 
 fn extract_syscalls(content: &str) -> Vec<(String, u32)> {
-    let re = Regex::new(r"#define\s+__NR_([A-Za-z0-9_]+)\s+(\d+)").unwrap();
+    let re = Regex::new(r"#define\s+__NR_([A-Za-z0-9_]+)\s+(\d+|__NR3264_[A-Za-z0-9_]+)").unwrap();
+    // asm-generic defines some numbers once for 32 and 64 bits (eg. `__NR_mmap __NR3264_mmap`)
+    let re_3264 = Regex::new(r"#define\s+(__NR3264_[A-Za-z0-9_]+)\s+(\d+)").unwrap();
+    let numbers_3264: HashMap<String, u32> = re_3264
+        .captures_iter(content)
+        .map(|cap| (cap[1].to_string(), cap[2].parse().unwrap()))
+        .collect();
 
     let mut map = HashMap::new();
 
     for cap in re.captures_iter(content) {
         let name = cap[1].to_string();
-        let num: u32 = cap[2].parse().unwrap();
+        let num: u32 = match cap[2].parse() {
+            Ok(num) => num,
+            Err(_) => match numbers_3264.get(&cap[2]) {
+                Some(num) => *num,
+                None => continue,
+            },
+        };
 
         // Insert only if the number is not already mapped
         // in aarch64 i found a syscall number with two aliases which causes issues
@@ -151,5 +163,39 @@ pub enum Syscall {
     }
 
     code.push_str("}\n");
+
+    code.push_str("\nimpl Syscall {\n");
+    code.push_str("    /// every syscall of the host architecture, ordered by number\n");
+    code.push_str("    pub const ALL: &'static [Syscall] = &[\n");
+    for (name, _num) in syscalls {
+        let variant = {
+            let mut chars = name.chars();
+            let first = chars.next().unwrap().to_uppercase().to_string();
+            first + chars.as_str()
+        };
+        code.push_str(&format!("        Syscall::{},\n", to_camel_case(&variant)));
+    }
+    code.push_str("    ];\n");
+
+    code.push_str(
+        r#"
+    /// the syscall name as spelled in the kernel headers (eg. `"openat"`)
+    pub fn name(&self) -> &'static str {
+        match self {
+"#,
+    );
+    for (name, _num) in syscalls {
+        let variant = {
+            let mut chars = name.chars();
+            let first = chars.next().unwrap().to_uppercase().to_string();
+            first + chars.as_str()
+        };
+        code.push_str(&format!(
+            "            Syscall::{} => \"{}\",\n",
+            to_camel_case(&variant),
+            name
+        ));
+    }
+    code.push_str("        }\n    }\n}\n");
     code
 }
@@ -6,8 +6,8 @@ fn main() -> Result<(), SeccompError> {
     let mut filter = Policy::allow_all()?;
 
     filter.entry_intercept(Syscall::Write, move |mut interceptor| {
-        if interceptor.registers.get("rdi").unwrap() == 1 as u64 {
-            interceptor.registers.set("rdx", 12).unwrap();
+        if interceptor.registers.arg(0) == 1 {
+            interceptor.registers.set_arg(2, 12);
             interceptor.commit_regs().unwrap();
         }
         TraceAction::Continue
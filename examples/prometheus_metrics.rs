@@ -2,29 +2,102 @@
 //!
 //! This Rust example demonstrates:
 //! 1. Installing a Prometheus metrics recorder to export application metrics.
-//! 2. Defining a seccomp policy that:
+//! 2. Running a sandboxed workload with `runtime_metrics` and scraping its per-syscall
+//!    latency histograms, verdict counters and live task gauge from another thread while it
+//!    runs.
+//! 3. Defining a seccomp policy that:
 //!    - Allows all syscalls by default.
 //!    - Denies dangerous operations (Munmap, ExitGroup).
 //!    - Traces and logs each invocation of the Openat syscall.
-//! 3. Applying the policy and then performing a file open to trigger the trace.
-//! 4. Rendering and printing the collected Prometheus metrics.
+//! 4. Applying the policy and then performing a file open to trigger the trace.
+//! 5. Rendering and printing the collected Prometheus metrics.
 //!
 //! Usage:
 //! ```bash
 //! cargo run --example prometheus_metrics --features metrics
 //! ```
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use restrict::{
     policy::{Policy, Syscall},
-    SeccompError, TraceAction,
+    Errno, SeccompError, TraceAction,
 };
-use std::fs;
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// print the runtime metrics of the sandbox, without the latency quantiles
+fn scrape(recorder: &PrometheusHandle) {
+    for line in recorder.render().lines() {
+        let runtime = line.starts_with("restrict_syscall") || line.starts_with("restrict_tasks");
+        if runtime && !line.contains("quantile=") {
+            println!("  {line}");
+        }
+    }
+}
+
 fn main() -> Result<(), SeccompError> {
     // Initialize Prometheus exporter
     let recorder = PrometheusBuilder::new()
         .install_recorder()
         .expect("Failed to install recorder");
 
+    // the sandbox: reads are allowed, /etc/shadow is denied
+    let started = Arc::new(AtomicBool::new(false));
+    let done = Arc::new(AtomicBool::new(false));
+    let mut sandbox = Policy::allow_all()?;
+    sandbox
+        .runtime_metrics()
+        .entry_intercept(Syscall::Openat, |i| {
+            match i.read_c_string(i.registers.arg(1), 4096) {
+                Ok(path) if path.as_bytes() == b"/etc/shadow" => {
+                    TraceAction::Deny(Errno(libc::EACCES))
+                }
+                _ => TraceAction::Continue,
+            }
+        })
+        // the first traced syscall of the workload, the sandbox is set up
+        .trace(Syscall::Getpid, {
+            let started = Arc::clone(&started);
+            move |_| {
+                started.store(true, Ordering::Relaxed);
+                TraceAction::Continue
+            }
+        });
+
+    // scrape while the sandbox runs, only once it started: the forked child still records
+    // metrics while setting up and must not fork while the scraper holds the registry
+    let scraper = thread::spawn({
+        let (recorder, started, done) = (recorder.clone(), Arc::clone(&started), Arc::clone(&done));
+        move || {
+            while !started.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(10));
+            }
+            while !done.load(Ordering::Relaxed) {
+                println!("scrape:");
+                scrape(&recorder);
+                thread::sleep(Duration::from_millis(250));
+            }
+        }
+    });
+    sandbox.run(|| {
+        let _ = std::process::id();
+        for _ in 0..5 {
+            let _ = fs::read_to_string("/etc/hostname");
+            let _ = fs::read_to_string("/etc/shadow");
+            thread::sleep(Duration::from_millis(100));
+        }
+    })?;
+    done.store(true, Ordering::Relaxed);
+    scraper.join().unwrap();
+    println!("after the sandbox exited:");
+    scrape(&recorder);
+
     // Same example from example/example_05_syscall_trace.rs
 
     let mut policy = Policy::allow_all()?;
@@ -0,0 +1,460 @@
+use std::{
+    fmt::{self, Write as _},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use libc::pid_t;
+
+use crate::{
+    error::Errno, format::SyscallEvent, interceptor::Interceptor, syscall::Syscall,
+    wrapper::TraceAction,
+};
+
+/// What the handlers of the policy did with a traced syscall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// every handler let the syscall run (they may still have changed its arguments)
+    Allowed,
+    /// a handler made the syscall fail with this errno
+    Denied(Errno),
+    /// a handler made the syscall return this value
+    Returned(i64),
+    /// a handler killed the process
+    Killed,
+}
+
+impl Verdict {
+    /// the verdict of the handler that returned `action`
+    pub(crate) fn of(action: &TraceAction) -> Self {
+        match action {
+            TraceAction::Continue | TraceAction::SkipExit => Verdict::Allowed,
+            TraceAction::Deny(errno) => Verdict::Denied(*errno),
+            TraceAction::Return(ret) => Verdict::Returned(*ret),
+            TraceAction::Kill => Verdict::Killed,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::Allowed => "allow",
+            Verdict::Denied(_) => "deny",
+            Verdict::Returned(_) => "return",
+            Verdict::Killed => "kill",
+        })
+    }
+}
+
+/// One traced syscall, written by `Policy::audit` to its `AuditSink`
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    /// when the tracer stopped the syscall at its entry
+    pub timestamp: SystemTime,
+    /// process id (thread group id) of the caller
+    pub pid: pid_t,
+    /// thread id of the caller
+    pub tid: pid_t,
+    /// the syscall
+    pub syscall: Syscall,
+    /// decoded arguments, the way `trace_all_pretty` prints them
+    pub args: Vec<String>,
+    /// what the handlers decided
+    pub verdict: Verdict,
+    /// the registration the deciding handler comes from: the `trace`, `*_intercept` and
+    /// `*_any` calls of the policy are numbered from 0 in the order they were made, the
+    /// policy features registering handlers (eg. `remap_path`) count too. `None` when every
+    /// handler continued
+    pub rule: Option<usize>,
+    /// raw return value, `None` when the tracer didn't see the syscall return (`exit_group`,
+    /// `SkipExit`, a killed process)
+    pub ret: Option<i64>,
+    /// time between the entry and exit stops, handlers included. Zero without an exit
+    pub duration: Duration,
+}
+
+impl AuditRecord {
+    /// the errno the syscall failed with, if it did
+    pub fn errno(&self) -> Option<Errno> {
+        self.ret.and_then(Errno::from_return_value)
+    }
+
+    /// the record as one JSON object without a trailing newline, eg.
+    /// `{"timestamp":"2025-01-01T00:00:00.000000000Z","pid":42,"tid":42,"syscall":"openat",...}`
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        let _ = write!(
+            json,
+            "\"timestamp\":\"{}\",\"pid\":{},\"tid\":{},\"syscall\":",
+            rfc3339(self.timestamp),
+            self.pid,
+            self.tid,
+        );
+        json_string(&mut json, self.syscall.name());
+        json.push_str(",\"args\":[");
+        for (n, arg) in self.args.iter().enumerate() {
+            if n > 0 {
+                json.push(',');
+            }
+            json_string(&mut json, arg);
+        }
+        let _ = write!(json, "],\"verdict\":\"{}\",\"rule\":", self.verdict);
+        match self.rule {
+            Some(rule) => {
+                let _ = write!(json, "{rule}");
+            }
+            None => json.push_str("null"),
+        }
+        json.push_str(",\"ret\":");
+        match self.ret {
+            Some(ret) => {
+                let _ = write!(json, "{ret}");
+            }
+            None => json.push_str("null"),
+        }
+        json.push_str(",\"errno\":");
+        match self.errno().and_then(Errno::name) {
+            Some(name) => json_string(&mut json, name),
+            None => json.push_str("null"),
+        }
+        let _ = write!(json, ",\"duration_ns\":{}}}", self.duration.as_nanos());
+        json
+    }
+}
+
+/// append `text` to `json` as a quoted JSON string
+fn json_string(json: &mut String, text: &str) {
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// `2025-01-01T00:00:00.000000000Z`, times before the epoch are printed as the epoch
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, day_secs) = ((secs / 86400) as i64, secs % 86400);
+    // days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:09}Z",
+        day_secs / 3600,
+        day_secs / 60 % 60,
+        day_secs % 60,
+        since_epoch.subsec_nanos()
+    )
+}
+
+/// Where `Policy::audit` writes its records, implement it to ship them somewhere else than
+/// a file (eg. a socket or an in-memory buffer)
+pub trait AuditSink {
+    /// write one record
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()>;
+
+    /// called once the restricted process exited
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// One JSON object per line written to any `Write`
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    /// write the records to `writer`
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> AuditSink for JsonLinesSink<W> {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        writeln!(self.writer, "{}", record.to_json())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// JSON lines written to a file that is rotated once it would grow past a size, `app.log`
+/// becomes `app.log.1`, `app.log.1` becomes `app.log.2`.. up to the number of files kept
+pub struct RotatingFileSink {
+    path: PathBuf,
+    file: File,
+    /// bytes in the current file
+    size: u64,
+    max_size: u64,
+    /// rotated files kept next to the current one
+    keep: usize,
+}
+
+impl RotatingFileSink {
+    /// append to `path`, rotating it before a record would make it larger than `max_size`
+    /// bytes and keeping `keep` rotated files (0 truncates it instead)
+    pub fn new(path: impl Into<PathBuf>, max_size: u64, keep: usize) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            keep,
+        })
+    }
+
+    /// `path` with the suffix of the `n`th rotated file
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        name.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep > 0 {
+            for n in (1..self.keep).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = open_truncated(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_truncated(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+}
+
+impl AuditSink for RotatingFileSink {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = record.to_json();
+        line.push('\n');
+        // a record larger than `max_size` still gets a file of its own
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// the syscall waiting for its exit stop
+struct Pending {
+    event: SyscallEvent,
+    timestamp: SystemTime,
+    start: Instant,
+    verdict: Verdict,
+    rule: Option<usize>,
+}
+
+/// `Policy::audit` state in the event loop: the syscall being audited and where it goes
+pub(crate) struct Auditor {
+    sink: Box<dyn AuditSink>,
+    /// the last thread seen and its thread group
+    thread_group: Option<(pid_t, pid_t)>,
+    pending: Option<Pending>,
+}
+
+impl Auditor {
+    pub(crate) fn new(sink: Box<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            thread_group: None,
+            pending: None,
+        }
+    }
+
+    /// the seccomp stop of a syscall, before its handlers run
+    pub(crate) fn entry(&mut self, interceptor: &Interceptor) -> io::Result<()> {
+        // the previous syscall had no exit stop
+        self.write_pending()?;
+        self.pending = Some(Pending {
+            event: SyscallEvent::entry(interceptor),
+            timestamp: SystemTime::now(),
+            start: Instant::now(),
+            verdict: Verdict::Allowed,
+            rule: None,
+        });
+        Ok(())
+    }
+
+    /// the handler registered by `rule` decided `verdict` for the pending syscall
+    pub(crate) fn decided(&mut self, verdict: Verdict, rule: Option<usize>) {
+        if let Some(pending) = self.pending.as_mut() {
+            pending.verdict = verdict;
+            pending.rule = rule;
+        }
+    }
+
+    /// the exit stop of the pending syscall, once its result is final
+    pub(crate) fn exit(&mut self, interceptor: &Interceptor) -> io::Result<()> {
+        match self.pending.take() {
+            Some(mut pending) => {
+                pending.event.finish(interceptor);
+                self.write(pending)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// the restricted process exited, the syscall it was in never returns
+    pub(crate) fn exited(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.sink.flush()
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        match self.pending.take() {
+            Some(pending) => self.write(pending),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, pending: Pending) -> io::Result<()> {
+        let tid = pending.event.pid;
+        let pid = match self.thread_group {
+            Some((thread, group)) if thread == tid => group,
+            _ => {
+                let group = thread_group(tid);
+                self.thread_group = Some((tid, group));
+                group
+            }
+        };
+        let ret = pending.event.ret;
+        let record = AuditRecord {
+            timestamp: pending.timestamp,
+            pid,
+            tid,
+            syscall: pending.event.syscall,
+            args: pending.event.decoded_args().to_vec(),
+            verdict: pending.verdict,
+            rule: pending.rule,
+            ret,
+            duration: match ret {
+                Some(_) => pending.start.elapsed(),
+                None => Duration::ZERO,
+            },
+        };
+        self.sink.record(&record)
+    }
+}
+
+/// the `Tgid` of `/proc/<tid>/status`, `tid` itself when it can't be read
+fn thread_group(tid: pid_t) -> pid_t {
+    fs::read_to_string(format!("/proc/{tid}/status"))
+        .ok()
+        .and_then(|status| {
+            let tgid = status.lines().find_map(|line| line.strip_prefix("Tgid:"))?;
+            tgid.trim().parse().ok()
+        })
+        .unwrap_or(tid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> AuditRecord {
+        AuditRecord {
+            timestamp: UNIX_EPOCH + Duration::new(1_700_000_000, 5),
+            pid: 42,
+            tid: 43,
+            syscall: Syscall::Openat,
+            args: vec![
+                "AT_FDCWD".to_string(),
+                "\"/etc/pass\\\"wd\"".to_string(),
+                "O_RDONLY".to_string(),
+            ],
+            verdict: Verdict::Denied(Errno(libc::EACCES)),
+            rule: Some(3),
+            ret: Some(-libc::EACCES as i64),
+            duration: Duration::from_micros(12),
+        }
+    }
+
+    #[test]
+    fn record_as_json() {
+        assert_eq!(
+            record().to_json(),
+            r#"{"timestamp":"2023-11-14T22:13:20.000000005Z","pid":42,"tid":43,"syscall":"openat","args":["AT_FDCWD","\"/etc/pass\\\"wd\"","O_RDONLY"],"verdict":"deny","rule":3,"ret":-13,"errno":"EACCES","duration_ns":12000}"#
+        );
+        let unfinished = AuditRecord {
+            verdict: Verdict::Killed,
+            rule: None,
+            ret: None,
+            ..record()
+        };
+        assert!(unfinished.to_json().ends_with(
+            r#""verdict":"kill","rule":null,"ret":null,"errno":null,"duration_ns":12000}"#
+        ));
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000000000Z");
+        // leap day
+        let leap = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(rfc3339(leap), "2000-02-29T01:01:01.000000000Z");
+    }
+
+    #[test]
+    fn rotate_files() {
+        let dir = std::env::temp_dir().join(format!("restrict-audit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let line = record().to_json().len() as u64 + 1;
+
+        // two records per file, two rotated files kept
+        let mut sink = RotatingFileSink::new(&path, line * 2, 2).unwrap();
+        for _ in 0..7 {
+            sink.record(&record()).unwrap();
+        }
+        let lines = |path: PathBuf| fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(path.clone()), 1);
+        assert_eq!(lines(dir.join("audit.log.1")), 2);
+        assert_eq!(lines(dir.join("audit.log.2")), 2);
+        assert!(!dir.join("audit.log.3").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
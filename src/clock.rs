@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use libc::clockid_t;
+
+use crate::{interceptor::Interceptor, syscall::Syscall, Errno, SeccompError, TraceAction};
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// syscalls rewritten at their entry
+pub(crate) const ENTRY_SYSCALLS: &[Syscall] = &[Syscall::ClockNanosleep];
+
+/// syscalls rewritten at their exit
+#[cfg(target_arch = "x86_64")]
+pub(crate) const EXIT_SYSCALLS: &[Syscall] = &[
+    Syscall::ClockGettime,
+    Syscall::Gettimeofday,
+    Syscall::ClockNanosleep,
+    Syscall::Time,
+];
+#[cfg(not(target_arch = "x86_64"))]
+pub(crate) const EXIT_SYSCALLS: &[Syscall] = &[
+    Syscall::ClockGettime,
+    Syscall::Gettimeofday,
+    Syscall::ClockNanosleep,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// nanoseconds added to the wall clocks
+    Offset(i64),
+    /// wall clock time in nanoseconds since the epoch
+    Frozen(i64),
+    /// speed of every clock
+    Scaled(f64),
+}
+
+/// which clocks a mode applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// time since the epoch (`CLOCK_REALTIME`..)
+    Wall,
+    /// time since an unspecified point (`CLOCK_MONOTONIC`..)
+    Steady,
+}
+
+fn kind(clock: clockid_t) -> Option<Kind> {
+    match clock {
+        libc::CLOCK_REALTIME
+        | libc::CLOCK_REALTIME_COARSE
+        | libc::CLOCK_REALTIME_ALARM
+        | libc::CLOCK_TAI => Some(Kind::Wall),
+        libc::CLOCK_MONOTONIC
+        | libc::CLOCK_MONOTONIC_RAW
+        | libc::CLOCK_MONOTONIC_COARSE
+        | libc::CLOCK_BOOTTIME
+        | libc::CLOCK_BOOTTIME_ALARM => Some(Kind::Steady),
+        // CPU time clocks are left alone
+        _ => None,
+    }
+}
+
+/// The time seen by the restricted process, installed with `Policy::virtual_clock`
+///
+/// `time`, `clock_gettime`, `gettimeofday` and `clock_nanosleep` are rewritten, CPU time
+/// clocks are left alone. Programs exec'd by the restricted process don't get the vDSO, so
+/// libc makes real syscalls for them. Code running before the exec still reads the clocks
+/// through the vDSO of the parent and only sees the virtual time with raw syscalls
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    mode: Mode,
+    /// real reading of each clock the first time it was seen, where frozen and scaled steady
+    /// clocks start from
+    bases: HashMap<clockid_t, i64>,
+}
+
+impl VirtualClock {
+    fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            bases: HashMap::new(),
+        }
+    }
+
+    /// the wall clocks run `secs` seconds ahead of the real time (behind when negative)
+    pub fn offset(secs: i64) -> Self {
+        Self::new(Mode::Offset(secs * NANOS_PER_SEC))
+    }
+
+    /// every clock stands still, the wall clocks at `at`
+    pub fn frozen(at: SystemTime) -> Self {
+        let nanos = match at.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as i64,
+            Err(before) => -(before.duration().as_nanos() as i64),
+        };
+        Self::new(Mode::Frozen(nanos))
+    }
+
+    /// every clock runs `factor` times as fast as the real one from the first time it is read,
+    /// sleeps are shortened to match
+    pub fn scaled(factor: f64) -> Self {
+        assert!(factor > 0.0, "the clock can't run at {factor}x");
+        Self::new(Mode::Scaled(factor))
+    }
+
+    /// virtual seconds per real second
+    fn rate(&self) -> f64 {
+        match self.mode {
+            Mode::Scaled(factor) => factor,
+            _ => 1.0,
+        }
+    }
+
+    /// the virtual reading of `clock` when it really reads `real` nanoseconds
+    fn virtual_time(&mut self, clock: clockid_t, real: i64) -> i64 {
+        let Some(kind) = kind(clock) else {
+            return real;
+        };
+        let base = *self.bases.entry(clock).or_insert(real);
+        match (self.mode, kind) {
+            (Mode::Offset(offset), Kind::Wall) => real + offset,
+            (Mode::Offset(_), Kind::Steady) => real,
+            (Mode::Frozen(at), Kind::Wall) => at,
+            (Mode::Frozen(_), Kind::Steady) => base,
+            (Mode::Scaled(factor), _) => base + ((real - base) as f64 * factor) as i64,
+        }
+    }
+
+    /// the real reading of `clock` at which an absolute sleep until the virtual `deadline`
+    /// ends, when the clock now really reads `now`
+    ///
+    /// a frozen clock never reaches a later deadline, the sleep then lasts the time left as
+    /// if the clock ran
+    fn real_deadline(&mut self, clock: clockid_t, deadline: i64, now: i64) -> i64 {
+        let left = deadline - self.virtual_time(clock, now);
+        now + (left as f64 / self.rate()) as i64
+    }
+
+    /// rewrite the arguments of a syscall at its entry
+    pub(crate) fn entry(&mut self, i: &mut Interceptor) -> TraceAction {
+        // a syscall we can't rewrite is left to fail on its own
+        let _ = match i.syscall {
+            Syscall::ClockNanosleep => self.sleep_entry(i),
+            _ => Ok(()),
+        };
+        TraceAction::Continue
+    }
+
+    /// rewrite the results of a syscall at its exit
+    pub(crate) fn exit(&mut self, i: &mut Interceptor) -> TraceAction {
+        if Errno::from_return_value(i.registers.return_value() as i64).is_some() {
+            return self.sleep_interrupted(i);
+        }
+        let _ = match i.syscall {
+            Syscall::ClockGettime => self.timespec(i, i.arg(0) as clockid_t, 1),
+            Syscall::Gettimeofday => self.timeval(i),
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Time => self.time(i),
+            _ => Ok(()),
+        };
+        TraceAction::Continue
+    }
+
+    /// replace the `timespec` at argument `arg` with its virtual value
+    fn timespec(
+        &mut self,
+        i: &Interceptor,
+        clock: clockid_t,
+        arg: usize,
+    ) -> Result<(), SeccompError> {
+        let addr = i.arg(arg);
+        if addr == 0 || kind(clock).is_none() {
+            return Ok(());
+        }
+        let ts: libc::timespec = i.read_struct(addr)?;
+        let now = self.virtual_time(clock, timespec_nanos(&ts));
+        i.write_struct(addr, &nanos_timespec(now))
+    }
+
+    /// `gettimeofday` reads `CLOCK_REALTIME` in microseconds
+    fn timeval(&mut self, i: &Interceptor) -> Result<(), SeccompError> {
+        let addr = i.arg(0);
+        if addr == 0 {
+            return Ok(());
+        }
+        let tv: libc::timeval = i.read_struct(addr)?;
+        let real = tv.tv_sec * NANOS_PER_SEC + tv.tv_usec * 1000;
+        let now = self.virtual_time(libc::CLOCK_REALTIME, real);
+        let tv = libc::timeval {
+            tv_sec: now.div_euclid(NANOS_PER_SEC),
+            tv_usec: now.rem_euclid(NANOS_PER_SEC) / 1000,
+        };
+        i.write_struct(addr, &tv)
+    }
+
+    /// `time` returns the seconds of `CLOCK_REALTIME` and also stores them in its argument
+    #[cfg(target_arch = "x86_64")]
+    fn time(&mut self, i: &mut Interceptor) -> Result<(), SeccompError> {
+        let real = i.registers.return_value() as i64 * NANOS_PER_SEC;
+        let secs = self
+            .virtual_time(libc::CLOCK_REALTIME, real)
+            .div_euclid(NANOS_PER_SEC);
+        i.registers.set_return_value(secs as u64);
+        i.commit_regs()?;
+        match i.arg(0) {
+            0 => Ok(()),
+            tloc => i.write_struct(tloc, &secs),
+        }
+    }
+
+    /// point `clock_nanosleep` to a request in real time, its deadline is virtual
+    fn sleep_entry(&mut self, i: &mut Interceptor) -> Result<(), SeccompError> {
+        let clock = i.registers.arg(0) as clockid_t;
+        let absolute = i.registers.arg(1) as i32 & libc::TIMER_ABSTIME != 0;
+        let addr = i.registers.arg(2);
+        if addr == 0 || kind(clock).is_none() {
+            return Ok(());
+        }
+        let request = timespec_nanos(&i.read_struct(addr)?);
+        let real = if absolute {
+            self.real_deadline(clock, request, clock_now(clock)?)
+        } else {
+            (request as f64 / self.rate()) as i64
+        };
+        if real == request {
+            return Ok(());
+        }
+        let ts = nanos_timespec(real);
+        let scratch = i.write_scratch(crate::memory::as_bytes(&ts))?;
+        i.registers.set_arg(2, scratch);
+        i.commit_regs()
+    }
+
+    /// an interrupted relative `clock_nanosleep` reports the virtual time it had left
+    fn sleep_interrupted(&mut self, i: &mut Interceptor) -> TraceAction {
+        let interrupted = i.syscall == Syscall::ClockNanosleep
+            && i.registers.return_value() as i64 == -(libc::EINTR as i64)
+            && i.arg(1) as i32 & libc::TIMER_ABSTIME == 0;
+        let addr = i.arg(3);
+        if interrupted && addr != 0 && self.rate() != 1.0 {
+            let _ = i.read_struct(addr).and_then(|left: libc::timespec| {
+                let left = (timespec_nanos(&left) as f64 * self.rate()) as i64;
+                i.write_struct(addr, &nanos_timespec(left))
+            });
+        }
+        TraceAction::Continue
+    }
+}
+
+fn timespec_nanos(ts: &libc::timespec) -> i64 {
+    ts.tv_sec * NANOS_PER_SEC + ts.tv_nsec
+}
+
+fn nanos_timespec(nanos: i64) -> libc::timespec {
+    libc::timespec {
+        tv_sec: nanos.div_euclid(NANOS_PER_SEC),
+        tv_nsec: nanos.rem_euclid(NANOS_PER_SEC),
+    }
+}
+
+/// the real reading of `clock`, the tracer shares the clocks of the tracee
+fn clock_now(clock: clockid_t) -> Result<i64, SeccompError> {
+    let mut ts = nanos_timespec(0);
+    // SAFETY: `ts` is a valid timespec to fill
+    if unsafe { libc::clock_gettime(clock, &mut ts) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(timespec_nanos(&ts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const SEC: i64 = NANOS_PER_SEC;
+
+    #[test]
+    fn offset_and_frozen() {
+        let mut clock = VirtualClock::offset(-60);
+        assert_eq!(
+            clock.virtual_time(libc::CLOCK_REALTIME, 100 * SEC),
+            40 * SEC
+        );
+        assert_eq!(
+            clock.virtual_time(libc::CLOCK_MONOTONIC, 100 * SEC),
+            100 * SEC
+        );
+        assert_eq!(clock.virtual_time(libc::CLOCK_PROCESS_CPUTIME_ID, 5), 5);
+        assert_eq!(
+            clock.real_deadline(libc::CLOCK_REALTIME, 50 * SEC, 100 * SEC),
+            110 * SEC
+        );
+
+        let mut clock = VirtualClock::frozen(UNIX_EPOCH + Duration::from_secs(7));
+        assert_eq!(clock.virtual_time(libc::CLOCK_REALTIME, 100 * SEC), 7 * SEC);
+        assert_eq!(clock.virtual_time(libc::CLOCK_MONOTONIC, 3 * SEC), 3 * SEC);
+        assert_eq!(clock.virtual_time(libc::CLOCK_MONOTONIC, 9 * SEC), 3 * SEC);
+        // the time left is slept as if the clock ran
+        assert_eq!(
+            clock.real_deadline(libc::CLOCK_REALTIME, 10 * SEC, 100 * SEC),
+            103 * SEC
+        );
+    }
+
+    #[test]
+    fn scaled() {
+        let mut clock = VirtualClock::scaled(10.0);
+        assert_eq!(
+            clock.virtual_time(libc::CLOCK_MONOTONIC, 100 * SEC),
+            100 * SEC
+        );
+        assert_eq!(
+            clock.virtual_time(libc::CLOCK_MONOTONIC, 101 * SEC),
+            110 * SEC
+        );
+        // 20 virtual seconds from 110 take 2 real seconds
+        assert_eq!(
+            clock.real_deadline(libc::CLOCK_MONOTONIC, 130 * SEC, 101 * SEC),
+            103 * SEC
+        );
+        let ts = nanos_timespec(-SEC / 2);
+        assert_eq!((ts.tv_sec, ts.tv_nsec), (-1, SEC / 2));
+        assert_eq!(timespec_nanos(&ts), -SEC / 2);
+    }
+
+    #[test]
+    fn exit_reads_the_entry_arguments() {
+        let mut clock = VirtualClock::offset(-60);
+        let mut ts = nanos_timespec(100 * SEC);
+        let args = [
+            libc::CLOCK_MONOTONIC as u64,
+            &mut ts as *mut _ as u64,
+            0,
+            0,
+            0,
+            0,
+        ];
+        clock.exit(&mut Interceptor::exit_stop(Syscall::ClockGettime, args, 0));
+        // SAFETY: written through the kernel, read it back from memory
+        let ts = unsafe { std::ptr::read_volatile(&ts) };
+        // the returned 0 isn't CLOCK_REALTIME
+        assert_eq!(timespec_nanos(&ts), 100 * SEC);
+
+        let mut tv = libc::timeval {
+            tv_sec: 100,
+            tv_usec: 0,
+        };
+        let args = [&mut tv as *mut _ as u64, 0, 0, 0, 0, 0];
+        clock.exit(&mut Interceptor::exit_stop(Syscall::Gettimeofday, args, 0));
+        // SAFETY: written through the kernel, read it back from memory
+        let tv = unsafe { std::ptr::read_volatile(&tv) };
+        assert_eq!(tv.tv_sec, 40);
+    }
+}
@@ -1,9 +1,9 @@
-use std::io;
+use std::{io, path::PathBuf};
 
 use libc::pid_t;
 use thiserror::Error;
 
-use crate::syscall::Syscall;
+use crate::{syscall::Syscall, wrapper::Action};
 /// Set of errors enums returned from libseccomp wrapper and the public api
 #[derive(Debug, Error)]
 pub enum SeccompError {
@@ -11,18 +11,69 @@ pub enum SeccompError {
     #[error("Failed to initilize seccomp context")]
     InitFailed,
 
-    /// triggered when adding a libseccomp rule fails
-    #[error("Failed to initilize seccomp context {0:?}")]
-    AddRuleFailed(Syscall),
+    /// triggered when libseccomp fails to add a rule for any other reason, `errno` is the
+    /// negative errno it returned
+    #[error("Failed to add the {action:?} rule for {syscall:?}: {reason} ({})", code_name(*.errno))]
+    AddRuleFailed {
+        /// syscall of the rule
+        syscall: Syscall,
+        /// action of the rule
+        action: Action,
+        /// negative errno returned by libseccomp
+        errno: i32,
+        /// what the errno means for this call
+        reason: &'static str,
+    },
+
+    /// triggered when a rule conflicts with a rule already added for the same syscall
+    /// (libseccomp `-EEXIST`)
+    #[error("Duplicate rule for {syscall:?}: a conflicting rule already exists ({})", code_name(*.errno))]
+    DuplicateRule {
+        /// syscall of the rule
+        syscall: Syscall,
+        /// action of the rule
+        action: Action,
+        /// negative errno returned by libseccomp
+        errno: i32,
+    },
+
+    /// triggered when the action of a rule is the default action of the context
+    /// (libseccomp `-EACCES`), eg. `allow()` in an `allow_all()` policy
+    #[error("Redundant {action:?} rule for {syscall:?}: it is already the default action of the filter ({})", code_name(*.errno))]
+    RuleMatchesDefault {
+        /// syscall of the rule
+        syscall: Syscall,
+        /// action of the rule
+        action: Action,
+        /// negative errno returned by libseccomp
+        errno: i32,
+    },
+
+    /// triggered when libseccomp rejects a rule (`-EINVAL`, `-EDOM`, `-EOPNOTSUPP`)
+    #[error("Invalid {action:?} rule for {syscall:?}: {reason} ({})", code_name(*.errno))]
+    InvalidRule {
+        /// syscall of the rule
+        syscall: Syscall,
+        /// action of the rule
+        action: Action,
+        /// negative errno returned by libseccomp
+        errno: i32,
+        /// what the errno means for this call
+        reason: &'static str,
+    },
 
     /// triggered when we fail to resolve a syscall from a string
     #[error("Unsupported syscall name: {0:?}")]
     UnsupportedSyscall(String),
 
-    /// triggered when the low-level libseccomp returns an error
-    /// todo(x0rw) explicitly handle it especially for error num -13
-    #[error("libseccomp returned error code {0}")]
-    LibSeccompError(i32),
+    /// triggered when libseccomp itself fails while loading the filter (`-EFAULT`, `-ECANCELED`)
+    #[error("libseccomp failed to load the filter: {reason} ({})", code_name(*.errno))]
+    LibSeccompError {
+        /// negative errno returned by libseccomp
+        errno: i32,
+        /// what the errno means for this call
+        reason: &'static str,
+    },
 
     /// triggered when loading metadata fails
     #[error("Failed to load syscall metadata")]
@@ -40,9 +91,22 @@ pub enum SeccompError {
     #[error("Cannot find a context, please specify a context(.allow_all() or .deny_all())")]
     EmptyContext,
 
-    /// triggered when you encounter an error while loading the filter
-    #[error("Failed to load the context")]
-    LoadError,
+    /// triggered when the kernel refuses the filter (`-EACCES`): the process has neither
+    /// `PR_SET_NO_NEW_PRIVS` set nor `CAP_SYS_ADMIN`
+    #[error("Permission denied loading the filter: the process has neither NO_NEW_PRIVS set nor CAP_SYS_ADMIN ({})", code_name(*.errno))]
+    LoadPermissionDenied {
+        /// negative errno returned by libseccomp
+        errno: i32,
+    },
+
+    /// triggered when loading the filter fails for any other reason
+    #[error("Failed to load the filter: {reason} ({})", code_name(*.errno))]
+    LoadFailed {
+        /// negative errno returned by libseccomp
+        errno: i32,
+        /// what the errno means for this call
+        reason: &'static str,
+    },
 
     /// triggered when you deny() but the context is deny_all()
     #[error("Redundant deny rule for {0:?} when default is Deny all.")]
@@ -72,7 +136,342 @@ pub enum SeccompError {
     #[error("Unsupported syscall id {0}")]
     UnsupportedSyscallID(i32),
 
-    /// Unsupported syscall
-    #[error("failed to add rule to a seccomp filter")]
-    FailedToAddResultToSeccompFilter,
+    /// Triggered when the tracee memory at the given address is unmapped or not accessible
+    #[error("Bad address {1:#x} in the memory of process {0}")]
+    MemoryFault(pid_t, u64),
+
+    /// Triggered when reading or writing the tracee memory fails for any other reason
+    #[error("Failed to access the memory of process {0}: {1:?}")]
+    MemoryAccess(pid_t, io::Error),
+
+    /// Triggered when a C string in the tracee memory has no NUL terminator within the limit
+    #[error("Unterminated string at {0:#x} after {1} bytes")]
+    UnterminatedString(u64, usize),
+
+    /// Triggered when running a syscall on behalf of the tracee (eg. `mmap` for scratch memory) fails
+    #[error("Failed to inject {1:?} into process {0}: {2:?}")]
+    SyscallInjection(pid_t, Syscall, io::Error),
+
+    /// Triggered when `waitpid` fails while waiting for the tracee
+    #[error("Failed to wait for process {0}: {1:?}")]
+    Wait(pid_t, io::Error),
+
+    /// Triggered when ptrace fails to resume the tracee with `PTRACE_CONT`
+    #[error("Failed to resume process {0}: {1:?}")]
+    PtraceCont(pid_t, io::Error),
+
+    /// Triggered when ptrace fails to `PTRACE_DETACH` from the tracee
+    #[error("Failed to detach from process {0}: {1:?}")]
+    PtraceDetach(pid_t, io::Error),
+
+    /// Triggered when the watchdog of `Policy::time_limit` can't watch the tracee
+    /// (`pidfd_open` needs Linux 5.3)
+    #[error("Failed to watch the time limit of process {0}: {1:?}")]
+    Watchdog(pid_t, io::Error),
+
+    /// Triggered when ptrace fails to `PTRACE_LISTEN` on a tracee in group-stop
+    #[error("Failed to keep process {0} stopped: {1:?}")]
+    PtraceListen(pid_t, io::Error),
+
+    /// Triggered when parsing the CIDR block of a network policy (eg. `10.0.0.0/8`) fails
+    #[error("Invalid CIDR block {0:?}")]
+    InvalidCidr(String),
+
+    /// Triggered when a SHA-256 pin of an exec policy isn't 64 hex digits
+    #[error("Invalid SHA-256 digest {0:?}")]
+    InvalidSha256(String),
+
+    /// Triggered when an allowed path of an exec policy can't be resolved (eg. it doesn't exist)
+    #[error("Invalid exec path {0:?}: {1:?}")]
+    InvalidExecPath(PathBuf, io::Error),
+}
+
+/// `-EEXIST` for a negative errno returned by libseccomp
+fn code_name(code: i32) -> String {
+    match errno_name(-code) {
+        Some(name) => format!("-{name}"),
+        None => code.to_string(),
+    }
+}
+
+impl SeccompError {
+    /// the error of `seccomp_rule_add` returning `errno` for the rule `action` on `syscall`
+    pub(crate) fn add_rule(syscall: Syscall, action: Action, errno: i32) -> Self {
+        let invalid = |reason| SeccompError::InvalidRule {
+            syscall,
+            action,
+            errno,
+            reason,
+        };
+        let failed = |reason| SeccompError::AddRuleFailed {
+            syscall,
+            action,
+            errno,
+            reason,
+        };
+        match -errno {
+            libc::EEXIST => SeccompError::DuplicateRule {
+                syscall,
+                action,
+                errno,
+            },
+            libc::EACCES => SeccompError::RuleMatchesDefault {
+                syscall,
+                action,
+                errno,
+            },
+            libc::EINVAL => invalid("the action or the syscall is not valid for the filter"),
+            libc::EDOM => invalid("the syscall does not exist on the architecture of the filter"),
+            libc::EOPNOTSUPP => invalid("the action is not supported by this libseccomp"),
+            libc::ENOMEM => failed("libseccomp ran out of memory"),
+            libc::EFAULT => failed("internal libseccomp failure"),
+            _ => failed("unexpected libseccomp error"),
+        }
+    }
+
+    /// the error of `seccomp_load` returning `errno`
+    pub(crate) fn load(errno: i32) -> Self {
+        let failed = |reason| SeccompError::LoadFailed { errno, reason };
+        match -errno {
+            libc::EACCES => SeccompError::LoadPermissionDenied { errno },
+            libc::EFAULT => SeccompError::LibSeccompError {
+                errno,
+                reason: "internal libseccomp failure",
+            },
+            libc::ECANCELED => SeccompError::LibSeccompError {
+                errno,
+                reason: "the kernel rejected the filter, libseccomp hid the reason",
+            },
+            libc::EINVAL => {
+                failed("the kernel does not support seccomp filters or one of their flags")
+            }
+            libc::ENOMEM => failed("the filter is too large or the kernel ran out of memory"),
+            libc::ESRCH => failed("the filter could not be synchronized to the other threads"),
+            libc::EPERM => {
+                failed("seccomp is not permitted in this process (eg. a container profile)")
+            }
+            _ => failed("unexpected libseccomp error"),
+        }
+    }
+
+    /// the negative errno libseccomp returned, for the errors of adding a rule or loading
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            SeccompError::AddRuleFailed { errno, .. }
+            | SeccompError::DuplicateRule { errno, .. }
+            | SeccompError::RuleMatchesDefault { errno, .. }
+            | SeccompError::InvalidRule { errno, .. }
+            | SeccompError::LibSeccompError { errno, .. }
+            | SeccompError::LoadPermissionDenied { errno }
+            | SeccompError::LoadFailed { errno, .. } => Some(Errno(-errno)),
+            _ => None,
+        }
+    }
+
+    /// whether the error comes from a tracee that is gone (`ESRCH`), eg. killed from outside
+    /// while the tracer was handling one of its stops
+    pub(crate) fn is_tracee_gone(&self) -> bool {
+        let err = match self {
+            SeccompError::IO(err)
+            | SeccompError::PtraceSyscall(_, err)
+            | SeccompError::PtraceCont(_, err)
+            | SeccompError::PtraceDetach(_, err)
+            | SeccompError::PtraceListen(_, err)
+            | SeccompError::MemoryAccess(_, err)
+            | SeccompError::SyscallInjection(_, _, err) => err,
+            _ => return false,
+        };
+        err.raw_os_error() == Some(libc::ESRCH)
+    }
+}
+
+/// An errno value (eg. `Errno(libc::EACCES)`) a syscall fails with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Errno(pub i32);
+
+impl Errno {
+    /// the raw syscall return value for this errno, `-errno`
+    pub fn as_return_value(self) -> i64 {
+        -(self.0 as i64)
+    }
+}
+
+impl From<i32> for Errno {
+    fn from(errno: i32) -> Self {
+        Errno(errno)
+    }
+}
+
+macro_rules! errno_names {
+    ($($name:ident),+ $(,)?) => {
+        /// the symbolic name of an errno value (eg. `"ENOENT"`)
+        fn errno_name(errno: i32) -> Option<&'static str> {
+            match errno {
+                $(libc::$name => Some(stringify!($name)),)+
+                _ => None,
+            }
+        }
+    };
+}
+
+// aliases (EWOULDBLOCK, EDEADLOCK, ENOTSUP) share their value with the names below
+errno_names!(
+    EPERM,
+    ENOENT,
+    ESRCH,
+    EINTR,
+    EIO,
+    ENXIO,
+    E2BIG,
+    ENOEXEC,
+    EBADF,
+    ECHILD,
+    EAGAIN,
+    ENOMEM,
+    EACCES,
+    EFAULT,
+    ENOTBLK,
+    EBUSY,
+    EEXIST,
+    EXDEV,
+    ENODEV,
+    ENOTDIR,
+    EISDIR,
+    EINVAL,
+    ENFILE,
+    EMFILE,
+    ENOTTY,
+    ETXTBSY,
+    EFBIG,
+    ENOSPC,
+    ESPIPE,
+    EROFS,
+    EMLINK,
+    EPIPE,
+    EDOM,
+    ERANGE,
+    EDEADLK,
+    ENAMETOOLONG,
+    ENOLCK,
+    ENOSYS,
+    ENOTEMPTY,
+    ELOOP,
+    ENOMSG,
+    EIDRM,
+    ECHRNG,
+    EL2NSYNC,
+    EL3HLT,
+    EL3RST,
+    ELNRNG,
+    EUNATCH,
+    ENOCSI,
+    EL2HLT,
+    EBADE,
+    EBADR,
+    EXFULL,
+    ENOANO,
+    EBADRQC,
+    EBADSLT,
+    EBFONT,
+    ENOSTR,
+    ENODATA,
+    ETIME,
+    ENOSR,
+    ENONET,
+    ENOPKG,
+    EREMOTE,
+    ENOLINK,
+    EADV,
+    ESRMNT,
+    ECOMM,
+    EPROTO,
+    EMULTIHOP,
+    EDOTDOT,
+    EBADMSG,
+    EOVERFLOW,
+    ENOTUNIQ,
+    EBADFD,
+    EREMCHG,
+    ELIBACC,
+    ELIBBAD,
+    ELIBSCN,
+    ELIBMAX,
+    ELIBEXEC,
+    EILSEQ,
+    ERESTART,
+    ESTRPIPE,
+    EUSERS,
+    ENOTSOCK,
+    EDESTADDRREQ,
+    EMSGSIZE,
+    EPROTOTYPE,
+    ENOPROTOOPT,
+    EPROTONOSUPPORT,
+    ESOCKTNOSUPPORT,
+    EOPNOTSUPP,
+    EPFNOSUPPORT,
+    EAFNOSUPPORT,
+    EADDRINUSE,
+    EADDRNOTAVAIL,
+    ENETDOWN,
+    ENETUNREACH,
+    ENETRESET,
+    ECONNABORTED,
+    ECONNRESET,
+    ENOBUFS,
+    EISCONN,
+    ENOTCONN,
+    ESHUTDOWN,
+    ETOOMANYREFS,
+    ETIMEDOUT,
+    ECONNREFUSED,
+    EHOSTDOWN,
+    EHOSTUNREACH,
+    EALREADY,
+    EINPROGRESS,
+    ESTALE,
+    EUCLEAN,
+    ENOTNAM,
+    ENAVAIL,
+    EISNAM,
+    EREMOTEIO,
+    EDQUOT,
+    ENOMEDIUM,
+    EMEDIUMTYPE,
+    ECANCELED,
+    ENOKEY,
+    EKEYEXPIRED,
+    EKEYREVOKED,
+    EKEYREJECTED,
+    EOWNERDEAD,
+    ENOTRECOVERABLE,
+    ERFKILL,
+    EHWPOISON,
+);
+
+impl Errno {
+    /// the symbolic name of the errno (eg. `"ENOENT"`), `None` for unknown values
+    pub fn name(self) -> Option<&'static str> {
+        errno_name(self.0)
+    }
+
+    /// the errno of a raw syscall return value, if it is an error (`-4095..0`)
+    pub fn from_return_value(ret: i64) -> Option<Self> {
+        (-4095..0).contains(&ret).then(|| Errno(-ret as i32))
+    }
+}
+
+/// `ENOENT (No such file or directory)`, the way strace prints failed syscalls
+impl std::fmt::Display for Errno {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = io::Error::from_raw_os_error(self.0).to_string();
+        // drop the " (os error N)" suffix std adds to the libc message
+        let message = message
+            .rsplit_once(" (os error")
+            .map_or(message.as_str(), |(msg, _)| msg);
+        match self.name() {
+            Some(name) => write!(f, "{name} ({message})"),
+            None => write!(f, "{} ({message})", self.0),
+        }
+    }
 }
@@ -0,0 +1,348 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use libc::pid_t;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    interceptor::Interceptor, restrict_counter, syscall::Syscall, Errno, SeccompError, TraceAction,
+};
+
+/// syscalls checked at their entry
+pub(crate) const SYSCALLS: &[Syscall] = &[Syscall::Execve, Syscall::Execveat];
+
+/// longest argument or environment string the kernel takes (`MAX_ARG_STRLEN`)
+const MAX_ARG_STRLEN: usize = 32 * 4096;
+
+/// most argv or envp entries read whatever the stack limit: the kernel wants their pointers to
+/// fit in 3/4 of `_STK_LIM` (8 MiB) and refuses more with `E2BIG`
+const MAX_ARG_COUNT: usize = 8 * 1024 * 1024 / 4 * 3 / 8;
+
+/// the variables `strip_dangerous_env` removes, they make the dynamic loader run other code
+const DANGEROUS_ENV: &[&str] = &["LD_PRELOAD", "LD_LIBRARY_PATH", "LD_AUDIT"];
+
+/// most symlinks followed while resolving a binary, the kernel gives up with `ELOOP` too
+const MAX_SYMLINKS: usize = 40;
+
+/// Binaries the restricted process may exec, installed with `Policy::exec_policy`
+///
+/// a binary is allowed when its resolved path is under an allowed path or its content has an
+/// allowed SHA-256, every other exec fails with `EACCES`, including the ones whose binary can't
+/// be resolved. The binary is checked by the tracer right before the kernel opens it, a
+/// process racing to swap it in between isn't stopped
+#[derive(Debug, Default)]
+pub struct ExecPolicy {
+    paths: Vec<PathBuf>,
+    hashes: Vec<[u8; 32]>,
+    strip_env: Vec<String>,
+}
+
+impl ExecPolicy {
+    /// deny every exec until it is allowed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// allow the binary at `path`, or the binaries under it when it is a directory, symlinks
+    /// are resolved
+    ///
+    /// fails with `InvalidExecPath` when `path` can't be resolved (eg. it doesn't exist), the
+    /// resolved binaries could never match it
+    pub fn allow_path(mut self, path: impl Into<PathBuf>) -> Result<Self, SeccompError> {
+        let path = path.into();
+        match fs::canonicalize(&path) {
+            Ok(resolved) => self.paths.push(resolved),
+            Err(err) => return Err(SeccompError::InvalidExecPath(path, err)),
+        }
+        Ok(self)
+    }
+
+    /// allow the binaries whose content has the SHA-256 `hex`
+    pub fn allow_sha256(mut self, hex: &str) -> Result<Self, SeccompError> {
+        let invalid = || SeccompError::InvalidSha256(hex.to_string());
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut digest = [0; 32];
+        for (n, byte) in digest.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[n * 2..n * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        self.hashes.push(digest);
+        Ok(self)
+    }
+
+    /// remove the variable `name` from the environment of the allowed execs
+    pub fn strip_env(mut self, name: &str) -> Self {
+        self.strip_env.push(name.to_string());
+        self
+    }
+
+    /// remove `LD_PRELOAD`, `LD_LIBRARY_PATH` and `LD_AUDIT` from the environment of the
+    /// allowed execs
+    pub fn strip_dangerous_env(self) -> Self {
+        DANGEROUS_ENV
+            .iter()
+            .fold(self, |policy, name| policy.strip_env(name))
+    }
+
+    /// whether the binary at the resolved `path` may run, its content is read from `file`
+    fn allows(&self, path: &Path, file: &Path) -> bool {
+        if self.paths.iter().any(|allowed| path.starts_with(allowed)) {
+            return true;
+        }
+        !self.hashes.is_empty() && digest(file).is_ok_and(|digest| self.hashes.contains(&digest))
+    }
+
+    /// check an exec at its entry
+    pub(crate) fn check(&self, i: &mut Interceptor) -> TraceAction {
+        // argv and envp follow the path
+        let (binary, argv) = match i.syscall {
+            Syscall::Execveat => (self.binary(i, Some(0), 1), 2),
+            _ => (self.binary(i, None, 0), 1),
+        };
+        // a binary that can't be resolved can't be checked either
+        let Ok((path, file)) = binary else {
+            restrict_counter!("restrict.exec.denied", 1);
+            return TraceAction::Deny(Errno(libc::EACCES));
+        };
+        if !self.allows(&path, &file) {
+            #[cfg(feature = "logging")]
+            {
+                let argv = read_strings(i, i.registers.arg(argv)).unwrap_or_default();
+                crate::restrict_warn!("denied exec of {} with {:?}", path.display(), argv);
+            }
+            restrict_counter!("restrict.exec.denied", 1);
+            return TraceAction::Deny(Errno(libc::EACCES));
+        }
+        // the exec must not go ahead with the variables still set
+        if !self.strip_env.is_empty() && self.strip(i, argv + 1).is_err() {
+            return TraceAction::Deny(Errno(libc::EACCES));
+        }
+        TraceAction::Continue
+    }
+
+    /// the resolved path of the binary and a path its content can be read from, the path
+    /// at argument `arg` is relative to the directory fd at argument `dirfd` or the cwd
+    fn binary(
+        &self,
+        i: &Interceptor,
+        dirfd: Option<usize>,
+        arg: usize,
+    ) -> Result<(PathBuf, PathBuf), SeccompError> {
+        let pid = i.child_pid;
+        let path = i.read_c_string(i.registers.arg(arg), libc::PATH_MAX as usize)?;
+        let path = Path::new(OsStr::from_bytes(path.as_bytes()));
+        let dir = match dirfd.map(|n| i.registers.arg(n) as i32) {
+            Some(fd) if fd != libc::AT_FDCWD => format!("/proc/{pid}/fd/{fd}"),
+            _ => format!("/proc/{pid}/cwd"),
+        };
+        // `fexecve` runs the fd itself (`AT_EMPTY_PATH`), eg. a memfd without a real path
+        if path.as_os_str().is_empty() {
+            return Ok((fs::read_link(&dir)?, PathBuf::from(dir)));
+        }
+        let root = fs::read_link(format!("/proc/{pid}/root"))?;
+        let start = match path.is_absolute() {
+            true => root.clone(),
+            false => fs::read_link(&dir)?,
+        };
+        let resolved = resolve(pid, &root, start, path)?;
+        Ok((resolved.clone(), resolved))
+    }
+
+    /// point the envp at argument `arg` to a copy without the stripped variables
+    fn strip(&self, i: &mut Interceptor, arg: usize) -> Result<(), SeccompError> {
+        let pointers = read_pointers(i, i.registers.arg(arg))?;
+        let mut kept = Vec::with_capacity(pointers.len() + 1);
+        for pointer in &pointers {
+            let entry = i.read_c_string(*pointer, MAX_ARG_STRLEN)?;
+            let name = entry.as_bytes().split(|b| *b == b'=').next().unwrap_or(&[]);
+            if !self.strip_env.iter().any(|strip| strip.as_bytes() == name) {
+                kept.push(*pointer);
+            }
+        }
+        if kept.len() == pointers.len() {
+            return Ok(());
+        }
+        kept.push(0);
+        let bytes: Vec<u8> = kept.iter().flat_map(|p| p.to_ne_bytes()).collect();
+        let scratch = i.write_scratch(&bytes)?;
+        i.registers.set_arg(arg, scratch);
+        i.commit_regs()
+    }
+}
+
+/// resolve `path` from `start` the way the kernel does for the process `pid`: symlinks are
+/// followed, `/` and `..` stay in its `root` and `/proc/self` is `pid` and not the tracer
+fn resolve(pid: pid_t, root: &Path, start: PathBuf, path: &Path) -> io::Result<PathBuf> {
+    let proc_self = [root.join("proc/self"), root.join("proc/thread-self")];
+    let components = |path: &Path| -> Vec<OsString> {
+        path.components()
+            .rev()
+            .map(|c| c.as_os_str().to_owned())
+            .collect()
+    };
+    let mut resolved = start;
+    // the components left, the next one last
+    let mut pending = components(path);
+    let mut links = 0;
+    while let Some(name) = pending.pop() {
+        match name.as_bytes() {
+            b"/" => resolved = root.to_path_buf(),
+            b"." => {}
+            b".." if resolved != root => {
+                resolved.pop();
+            }
+            b".." => {}
+            _ => {
+                let candidate = resolved.join(&name);
+                if proc_self.contains(&candidate) {
+                    resolved = root.join(format!("proc/{pid}"));
+                } else if fs::symlink_metadata(&candidate)?.file_type().is_symlink() {
+                    links += 1;
+                    if links > MAX_SYMLINKS {
+                        return Err(io::Error::from_raw_os_error(libc::ELOOP));
+                    }
+                    // relative targets start from the directory of the link
+                    pending.extend(components(&fs::read_link(&candidate)?));
+                } else {
+                    resolved = candidate;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// the sha256 of the file at `path`, streamed so a large binary isn't read into memory
+fn digest(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// most argv or envp entries the tracee can pass, their pointers also have to fit in its
+/// `ARG_MAX`, a quarter of its stack limit
+fn max_arg_count(pid: pid_t) -> usize {
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: prlimit only writes the current limit into `rlim`
+    let arg_max =
+        match unsafe { libc::prlimit(pid, libc::RLIMIT_STACK, std::ptr::null(), &mut rlim) } {
+            0 => rlim.rlim_cur / 4,
+            // SAFETY: plain sysconf call, the tracee inherited the tracer limits
+            _ => unsafe { libc::sysconf(libc::_SC_ARG_MAX) }.max(0) as u64,
+        };
+    (arg_max / 8).min(MAX_ARG_COUNT as u64) as usize
+}
+
+/// the pointers of the NULL terminated array at `addr`, an array too long for the kernel
+/// fails with `E2BIG`
+fn read_pointers(i: &Interceptor, addr: u64) -> Result<Vec<u64>, SeccompError> {
+    let mut pointers = Vec::new();
+    if addr == 0 {
+        return Ok(pointers);
+    }
+    let max = max_arg_count(i.child_pid);
+    loop {
+        match i.read_struct::<u64>(addr + pointers.len() as u64 * 8)? {
+            0 => return Ok(pointers),
+            _ if pointers.len() == max => {
+                return Err(io::Error::from_raw_os_error(libc::E2BIG).into())
+            }
+            pointer => pointers.push(pointer),
+        }
+    }
+}
+
+/// the strings of the NULL terminated array at `addr` (argv, envp)
+#[cfg(feature = "logging")]
+fn read_strings(i: &Interceptor, addr: u64) -> Result<Vec<std::ffi::CString>, SeccompError> {
+    read_pointers(i, addr)?
+        .into_iter()
+        .map(|pointer| i.read_c_string(pointer, MAX_ARG_STRLEN))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_pins() {
+        let digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let policy = ExecPolicy::new().allow_sha256(digest).unwrap();
+        // the digest of no bytes at all
+        assert_eq!(policy.hashes, [<[u8; 32]>::from(Sha256::digest([]))]);
+
+        assert!(ExecPolicy::new().allow_sha256("e3b0").is_err());
+        assert!(ExecPolicy::new().allow_sha256(&"g".repeat(64)).is_err());
+
+        let exe = Path::new("/proc/self/exe");
+        let content = fs::read(exe).unwrap();
+        assert_eq!(
+            super::digest(exe).unwrap(),
+            <[u8; 32]>::from(Sha256::digest(content))
+        );
+        assert!(super::digest(Path::new("/nonexistent/binary")).is_err());
+    }
+
+    #[test]
+    fn arg_count_limit() {
+        // SAFETY: plain getpid/sysconf calls
+        let (pid, arg_max) = unsafe { (libc::getpid(), libc::sysconf(libc::_SC_ARG_MAX)) };
+        let max = max_arg_count(pid);
+        assert_eq!(max, (arg_max as usize / 8).min(MAX_ARG_COUNT));
+        assert!(max > 0);
+    }
+
+    #[test]
+    fn allowed_paths() {
+        let dir = fs::canonicalize(std::env::temp_dir()).unwrap();
+        let policy = ExecPolicy::new()
+            .allow_path(&dir)
+            .unwrap()
+            .strip_dangerous_env();
+        let allows = |path: &Path| policy.allows(path, path);
+        assert!(allows(&dir.join("tool")));
+        assert!(!allows(Path::new("/nonexistent/binary")));
+        assert_eq!(policy.strip_env, DANGEROUS_ENV);
+
+        match ExecPolicy::new().allow_path("/nonexistent/bin") {
+            Err(SeccompError::InvalidExecPath(path, _)) => {
+                assert_eq!(path, Path::new("/nonexistent/bin"))
+            }
+            other => panic!("expected InvalidExecPath, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn resolve_in_the_tracee() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as pid_t;
+        let sleep = fs::read_link(format!("/proc/{pid}/exe")).unwrap();
+        let root = Path::new("/");
+
+        // `/proc/self` is the tracee, not the tracer
+        let resolved = resolve(pid, root, root.into(), Path::new("/proc/self/exe"));
+        assert_eq!(resolved.unwrap(), sleep);
+        let resolved = resolve(pid, root, root.into(), Path::new("/proc/thread-self/exe"));
+        assert_eq!(resolved.unwrap(), sleep);
+        // `..` stops at the root
+        let resolved = resolve(pid, root, "/proc".into(), Path::new("../../proc/self/exe"));
+        assert_eq!(resolved.unwrap(), sleep);
+        // a missing binary isn't resolved
+        assert!(resolve(pid, root, root.into(), Path::new("/nonexistent/binary")).is_err());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+use std::{cell::RefCell, collections::HashSet, fmt, rc::Rc};
+
+use crate::{interceptor::Interceptor, syscall::Syscall, Errno, TraceAction};
+
+/// A call the fault injector tampered with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    /// the faulted syscall
+    pub syscall: Syscall,
+    /// which call of the syscall it was, from 1
+    pub call: u64,
+    /// what was done to it
+    pub kind: FaultKind,
+}
+
+/// What the fault injector did to a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// skipped and failed with this errno
+    Failed(Errno),
+    /// its byte count was cut from `requested` to `allowed`
+    Short {
+        /// count passed by the tracee
+        requested: u64,
+        /// count the syscall ran with
+        allowed: u64,
+    },
+}
+
+/// The faults injected so far, shared by the `FaultInjector` it comes from
+#[derive(Debug, Clone, Default)]
+pub struct FaultReport(Rc<RefCell<Vec<Fault>>>);
+
+impl FaultReport {
+    /// the faults in the order they were injected
+    pub fn faults(&self) -> Vec<Fault> {
+        self.0.borrow().clone()
+    }
+
+    fn push(&self, fault: Fault) {
+        self.0.borrow_mut().push(fault);
+    }
+}
+
+/// one fault per line, eg. `openat #3: EIO (Input/output error)`
+impl fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for fault in self.0.borrow().iter() {
+            write!(f, "{} #{}: ", fault.syscall.name(), fault.call)?;
+            match fault.kind {
+                FaultKind::Failed(errno) => writeln!(f, "{errno}")?,
+                FaultKind::Short { requested, allowed } => {
+                    writeln!(f, "short {requested} -> {allowed}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// splitmix64, the same seed and the same sequence of syscalls give the same faults
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// whether an event of `probability` happens
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    /// uniform in `0..n`, with Lemire's multiply-shift: the high half of `x * n` is in
+    /// `0..n` and the low halves below `2^64 % n` are rejected, they would favour some values
+    fn below(&mut self, n: u64) -> u64 {
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(n);
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+}
+
+enum Rule {
+    Fail {
+        probability: f64,
+        errnos: Vec<Errno>,
+    },
+    FailNth {
+        nth: u64,
+        errno: Errno,
+    },
+    ShortIo {
+        probability: f64,
+    },
+}
+
+/// Deterministic fault injection for chaos testing, installed with `Policy::inject_faults`
+///
+/// failed calls are skipped at their entry like `TraceAction::Deny`, the rules of a syscall are
+/// tried in the order they were added and the first one that fires wins
+pub struct FaultInjector {
+    seed: u64,
+    rules: Vec<(Syscall, Rule)>,
+    report: FaultReport,
+}
+
+impl FaultInjector {
+    /// no rule yet, `seed` drives every random choice so a run can be reproduced
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rules: Vec::new(),
+            report: FaultReport::default(),
+        }
+    }
+
+    /// fail each call of `syscall` with `probability` (0 to 1), with an errno picked from
+    /// `errnos`
+    pub fn fail(mut self, syscall: Syscall, probability: f64, errnos: &[Errno]) -> Self {
+        assert!(!errnos.is_empty(), "no errno to fail {syscall:?} with");
+        self.rules.push((
+            syscall,
+            Rule::Fail {
+                probability,
+                errnos: errnos.to_vec(),
+            },
+        ));
+        self
+    }
+
+    /// fail the `nth` call (from 1) of `syscall` with `errno`
+    pub fn fail_nth(mut self, syscall: Syscall, nth: u64, errno: Errno) -> Self {
+        self.rules.push((syscall, Rule::FailNth { nth, errno }));
+        self
+    }
+
+    /// with `probability`, cut the byte count of a call of `syscall` to a random smaller one
+    /// so it reads or writes less than asked
+    ///
+    /// only `read`, `write`, `pread64`, `pwrite64`, `recvfrom` and `sendto` have a count
+    pub fn short_io(mut self, syscall: Syscall, probability: f64) -> Self {
+        self.rules.push((syscall, Rule::ShortIo { probability }));
+        self
+    }
+
+    /// the report the faults are recorded in, keep it to look at them after `Policy::run`
+    pub fn report(&self) -> FaultReport {
+        self.report.clone()
+    }
+
+    /// one entry interceptor per syscall with rules, sharing the RNG
+    pub(crate) fn into_interceptors(
+        self,
+    ) -> Vec<(Syscall, impl FnMut(Interceptor) -> TraceAction)> {
+        let rng = Rc::new(RefCell::new(Rng(self.seed)));
+        // in the order of their first rule
+        let mut seen = HashSet::new();
+        let syscalls: Vec<Syscall> = self
+            .rules
+            .iter()
+            .map(|(sc, _)| *sc)
+            .filter(|sc| seen.insert(*sc))
+            .collect();
+
+        let mut rules = self.rules;
+        let mut interceptors = Vec::new();
+        for syscall in syscalls {
+            let (own, rest) = rules.into_iter().partition(|(sc, _)| *sc == syscall);
+            rules = rest;
+            let own: Vec<Rule> = own.into_iter().map(|(_, rule)| rule).collect();
+            let (rng, report) = (rng.clone(), self.report.clone());
+            let mut calls = 0;
+            interceptors.push((syscall, move |mut i: Interceptor| {
+                calls += 1;
+                let mut rng = rng.borrow_mut();
+                let kind = own.iter().find_map(|rule| match rule {
+                    Rule::FailNth { nth, errno } => {
+                        (*nth == calls).then_some(FaultKind::Failed(*errno))
+                    }
+                    Rule::Fail {
+                        probability,
+                        errnos,
+                    } => rng.chance(*probability).then(|| {
+                        FaultKind::Failed(errnos[rng.below(errnos.len() as u64) as usize])
+                    }),
+                    Rule::ShortIo { probability } => shorten(&mut i, &mut rng, *probability),
+                });
+                let Some(kind) = kind else {
+                    return TraceAction::Continue;
+                };
+                report.push(Fault {
+                    syscall,
+                    call: calls,
+                    kind,
+                });
+                match kind {
+                    FaultKind::Failed(errno) => TraceAction::Deny(errno),
+                    FaultKind::Short { .. } => TraceAction::Continue,
+                }
+            }));
+        }
+        interceptors
+    }
+}
+
+/// cut the count of the read or write `i` is stopped at, with `probability`
+fn shorten(i: &mut Interceptor, rng: &mut Rng, probability: f64) -> Option<FaultKind> {
+    let count = match i.syscall {
+        Syscall::Read
+        | Syscall::Write
+        | Syscall::Pread64
+        | Syscall::Pwrite64
+        | Syscall::Recvfrom
+        | Syscall::Sendto => 2,
+        _ => return None,
+    };
+    let requested = i.registers.arg(count);
+    // a single byte can't get any shorter
+    if requested < 2 || !rng.chance(probability) {
+        return None;
+    }
+    let allowed = 1 + rng.below(requested - 1);
+    i.registers.set_arg(count, allowed);
+    i.commit_regs().ok()?;
+    Some(FaultKind::Short { requested, allowed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible() {
+        let (mut a, mut b) = (Rng(7), Rng(7));
+        let run = |rng: &mut Rng| (0..64).map(|_| rng.below(1000)).collect::<Vec<_>>();
+        assert_eq!(run(&mut a), run(&mut b));
+        assert_ne!(run(&mut Rng(7)), run(&mut Rng(8)));
+
+        let mut rng = Rng(1);
+        assert!(!(0..100).any(|_| rng.chance(0.0)));
+        assert!((0..100).all(|_| rng.chance(1.0)));
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2000..3000).contains(&hits), "{hits}");
+
+        // 2^64 isn't a multiple of 3, no value is favoured anyway
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            counts[rng.below(3) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|n| (9000..11_000).contains(n)),
+            "{counts:?}"
+        );
+        assert_eq!(rng.below(1), 0);
+    }
+
+    #[test]
+    fn one_interceptor_per_syscall() {
+        let faults = FaultInjector::new(1)
+            .fail(Syscall::Read, 0.5, &[Errno(libc::EIO)])
+            .fail(Syscall::Write, 0.5, &[Errno(libc::EIO)])
+            .short_io(Syscall::Read, 0.5);
+        let syscalls: Vec<Syscall> = faults
+            .into_interceptors()
+            .into_iter()
+            .map(|(sc, _)| sc)
+            .collect();
+        assert_eq!(syscalls, [Syscall::Read, Syscall::Write]);
+    }
+
+    #[test]
+    fn report_lines() {
+        let report = FaultReport::default();
+        report.push(Fault {
+            syscall: Syscall::Openat,
+            call: 3,
+            kind: FaultKind::Failed(Errno(libc::EIO)),
+        });
+        report.push(Fault {
+            syscall: Syscall::Read,
+            call: 1,
+            kind: FaultKind::Short {
+                requested: 4096,
+                allowed: 17,
+            },
+        });
+        assert_eq!(
+            report.to_string(),
+            "openat #3: EIO (Input/output error)\nread #1: short 4096 -> 17\n"
+        );
+    }
+}
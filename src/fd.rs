@@ -0,0 +1,348 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use libc::{c_int, pid_t};
+
+use crate::{interceptor::Interceptor, net::SocketAddress, syscall::Syscall, Errno};
+
+/// syscalls that create, copy or close fds, the table is updated at their exit
+const SYSCALLS: &[Syscall] = &[
+    Syscall::Openat,
+    Syscall::Openat2,
+    Syscall::Socket,
+    Syscall::Socketpair,
+    Syscall::Accept,
+    Syscall::Accept4,
+    Syscall::Connect,
+    Syscall::Bind,
+    Syscall::Dup,
+    Syscall::Dup3,
+    Syscall::Fcntl,
+    Syscall::Close,
+    Syscall::CloseRange,
+    Syscall::Pipe2,
+];
+
+/// syscalls only x86_64 still has, newer architectures only have their `*at` variant
+#[cfg(target_arch = "x86_64")]
+const LEGACY_SYSCALLS: &[Syscall] = &[Syscall::Open, Syscall::Creat, Syscall::Dup2, Syscall::Pipe];
+#[cfg(not(target_arch = "x86_64"))]
+const LEGACY_SYSCALLS: &[Syscall] = &[];
+
+/// the syscalls the table follows
+pub(crate) fn syscalls() -> impl Iterator<Item = Syscall> {
+    SYSCALLS.iter().chain(LEGACY_SYSCALLS).copied()
+}
+
+/// What an fd of the traced process refers to, see `Interceptor::fd_info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FdKind {
+    /// a file or directory, at the path the kernel resolved
+    File(PathBuf),
+    /// a socket, with the address it connected to, was accepted from or bound to when known
+    Socket(Option<SocketAddress>),
+    /// one end of a pipe
+    Pipe,
+    /// anything else, as `/proc/<pid>/fd` shows it (eg. `anon_inode:[eventfd]`)
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    kind: FdKind,
+    cloexec: bool,
+}
+
+/// what `/proc/<pid>/fd/<fd>` says `fd` is, `None` when it isn't open
+pub(crate) fn proc_kind(pid: pid_t, fd: c_int) -> Option<FdKind> {
+    let target = fs::read_link(format!("/proc/{pid}/fd/{fd}")).ok()?;
+    let name = target.to_string_lossy();
+    Some(if name.starts_with("socket:[") {
+        FdKind::Socket(None)
+    } else if name.starts_with("pipe:[") {
+        FdKind::Pipe
+    } else if target.is_absolute() {
+        FdKind::File(target)
+    } else {
+        FdKind::Other(name.into_owned())
+    })
+}
+
+/// whether `fd` has `FD_CLOEXEC`, from the octal `flags` of `/proc/<pid>/fdinfo/<fd>`
+fn proc_cloexec(pid: pid_t, fd: c_int) -> bool {
+    fs::read_to_string(format!("/proc/{pid}/fdinfo/{fd}"))
+        .ok()
+        .and_then(|info| {
+            let flags = info.lines().find_map(|line| line.strip_prefix("flags:"))?;
+            i64::from_str_radix(flags.trim(), 8).ok()
+        })
+        .is_some_and(|flags| flags & libc::O_CLOEXEC as i64 != 0)
+}
+
+/// The fds of the traced process, kept by `Policy::track_fds` from the syscalls it makes and
+/// cross-checked against `/proc/<pid>/fd`
+#[derive(Debug, Default)]
+pub(crate) struct FdTable {
+    /// the process the table is about, 0 until its first syscall
+    pid: pid_t,
+    fds: HashMap<c_int, Entry>,
+}
+
+impl FdTable {
+    /// start from the fds `pid` already has (the inherited ones) the first time it is seen
+    fn attach(&mut self, pid: pid_t) {
+        if self.pid != pid {
+            self.pid = pid;
+            self.fds.clear();
+            self.sync();
+        }
+    }
+
+    /// reconcile the table with `/proc/<pid>/fd`: the fds opened or closed behind its back
+    /// (eg. by syscalls it doesn't follow) and the ones that changed kind
+    fn sync(&mut self) {
+        let Ok(dir) = fs::read_dir(format!("/proc/{}/fd", self.pid)) else {
+            return;
+        };
+        let open: HashMap<c_int, FdKind> = dir
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .filter_map(|fd| Some((fd, proc_kind(self.pid, fd)?)))
+            .collect();
+        self.fds.retain(|fd, _| open.contains_key(fd));
+        for (fd, kind) in open {
+            // /proc doesn't know the socket addresses the table remembers
+            let known = self
+                .fds
+                .get(&fd)
+                .is_some_and(|entry| match (&entry.kind, &kind) {
+                    (FdKind::Socket(_), FdKind::Socket(_)) => true,
+                    (tracked, kind) => tracked == kind,
+                });
+            if !known {
+                let cloexec = proc_cloexec(self.pid, fd);
+                self.fds.insert(fd, Entry { kind, cloexec });
+            }
+        }
+    }
+
+    /// what `fd` of `pid` refers to, looked up in `/proc` when the table doesn't know it
+    pub(crate) fn get(&mut self, pid: pid_t, fd: c_int) -> Option<FdKind> {
+        self.attach(pid);
+        if let Some(entry) = self.fds.get(&fd) {
+            return Some(entry.kind.clone());
+        }
+        let kind = proc_kind(pid, fd)?;
+        let cloexec = proc_cloexec(pid, fd);
+        self.insert(fd, kind.clone(), cloexec);
+        Some(kind)
+    }
+
+    fn insert(&mut self, fd: c_int, kind: FdKind, cloexec: bool) {
+        self.fds.insert(fd, Entry { kind, cloexec });
+    }
+
+    /// `new` is a copy of `old`
+    fn duplicate(&mut self, old: c_int, new: c_int, cloexec: bool) {
+        let kind = self
+            .fds
+            .get(&old)
+            .map(|entry| entry.kind.clone())
+            .or_else(|| proc_kind(self.pid, new));
+        match kind {
+            Some(kind) => self.insert(new, kind, cloexec),
+            None => {
+                self.fds.remove(&new);
+            }
+        }
+    }
+
+    /// a fresh fd, what it is comes from `/proc`
+    fn opened(&mut self, fd: c_int, cloexec: bool) {
+        match proc_kind(self.pid, fd) {
+            Some(kind) => self.insert(fd, kind, cloexec),
+            None => {
+                self.fds.remove(&fd);
+            }
+        }
+    }
+
+    /// the socket `fd` now has `address`
+    fn addressed(&mut self, fd: c_int, address: SocketAddress) {
+        if let Some(entry) = self.fds.get_mut(&fd) {
+            entry.kind = FdKind::Socket(Some(address));
+        }
+    }
+
+    /// the process exec'd: its `FD_CLOEXEC` fds are gone
+    pub(crate) fn exec(&mut self, pid: pid_t) {
+        self.attach(pid);
+        self.fds.retain(|_, entry| !entry.cloexec);
+        self.sync();
+    }
+
+    /// follow the syscall `i` is stopped at the exit of
+    pub(crate) fn syscall_exit(&mut self, i: &Interceptor) {
+        self.attach(i.child_pid);
+        let ret = i.registers.return_value() as i64;
+        let failed = Errno::from_return_value(ret);
+        // a non blocking connect goes on in the background
+        if failed
+            .is_some_and(|errno| !(i.syscall == Syscall::Connect && errno.0 == libc::EINPROGRESS))
+        {
+            return;
+        }
+        let arg = |n: usize| i.arg(n);
+        let fd = ret as c_int;
+        let has = |flags: u64, flag: c_int| flags as c_int & flag != 0;
+        match i.syscall {
+            Syscall::Openat => self.opened(fd, has(arg(2), libc::O_CLOEXEC)),
+            // `struct open_how` starts with the flags
+            Syscall::Openat2 => {
+                let flags = i.read_struct::<u64>(arg(2)).unwrap_or_default();
+                self.opened(fd, has(flags, libc::O_CLOEXEC));
+            }
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Open => self.opened(fd, has(arg(1), libc::O_CLOEXEC)),
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Creat => self.opened(fd, false),
+            Syscall::Socket => {
+                self.insert(fd, FdKind::Socket(None), has(arg(1), libc::SOCK_CLOEXEC))
+            }
+            Syscall::Socketpair => {
+                if let Ok(pair) = i.read_struct::<[c_int; 2]>(arg(3)) {
+                    let cloexec = has(arg(1), libc::SOCK_CLOEXEC);
+                    for fd in pair {
+                        self.insert(fd, FdKind::Socket(None), cloexec);
+                    }
+                }
+            }
+            Syscall::Accept | Syscall::Accept4 => {
+                let cloexec = i.syscall == Syscall::Accept4 && has(arg(3), libc::SOCK_CLOEXEC);
+                let peer = match (arg(1), arg(2)) {
+                    (0, _) | (_, 0) => None,
+                    (addr, len) => i
+                        .read_struct::<u32>(len)
+                        .and_then(|len| SocketAddress::read(i, addr, len as usize))
+                        .ok(),
+                };
+                self.insert(fd, FdKind::Socket(peer), cloexec);
+            }
+            Syscall::Connect | Syscall::Bind => {
+                if let Ok(address) = SocketAddress::read(i, arg(1), arg(2) as usize) {
+                    self.addressed(arg(0) as c_int, address);
+                }
+            }
+            Syscall::Dup => self.duplicate(arg(0) as c_int, fd, false),
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Dup2 => self.duplicate(arg(0) as c_int, fd, false),
+            Syscall::Dup3 => self.duplicate(arg(0) as c_int, fd, has(arg(2), libc::O_CLOEXEC)),
+            Syscall::Fcntl => match arg(1) as c_int {
+                libc::F_DUPFD => self.duplicate(arg(0) as c_int, fd, false),
+                libc::F_DUPFD_CLOEXEC => self.duplicate(arg(0) as c_int, fd, true),
+                libc::F_SETFD => {
+                    if let Some(entry) = self.fds.get_mut(&(arg(0) as c_int)) {
+                        entry.cloexec = has(arg(2), libc::FD_CLOEXEC);
+                    }
+                }
+                _ => {}
+            },
+            Syscall::Close => {
+                self.fds.remove(&(arg(0) as c_int));
+            }
+            Syscall::CloseRange => {
+                let (first, last) = (arg(0) as u32, arg(1) as u32);
+                let in_range = |fd: &c_int| (first..=last).contains(&(*fd as u32));
+                if has(arg(2), libc::CLOSE_RANGE_CLOEXEC as c_int) {
+                    for (_, entry) in self.fds.iter_mut().filter(|(fd, _)| in_range(fd)) {
+                        entry.cloexec = true;
+                    }
+                } else {
+                    self.fds.retain(|fd, _| !in_range(fd));
+                }
+            }
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Pipe => self.piped(i, false),
+            Syscall::Pipe2 => self.piped(i, has(arg(1), libc::O_CLOEXEC)),
+            _ => {}
+        }
+    }
+
+    /// both ends of the pipe `pipe`/`pipe2` filled in
+    fn piped(&mut self, i: &Interceptor, cloexec: bool) {
+        if let Ok(ends) = i.read_struct::<[c_int; 2]>(i.arg(0)) {
+            for fd in ends {
+                self.insert(fd, FdKind::Pipe, cloexec);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_follows_fds() {
+        // the inherited fds of the test process itself
+        let pid = std::process::id() as pid_t;
+        let mut table = FdTable::default();
+        assert!(table.get(pid, 0).is_some());
+        assert_eq!(table.get(pid, 4242), None);
+
+        let address = SocketAddress::Ip("127.0.0.1:80".parse().unwrap());
+        table.insert(100, FdKind::Socket(None), false);
+        table.addressed(100, address.clone());
+        table.duplicate(100, 101, true);
+        assert_eq!(table.get(pid, 101), Some(FdKind::Socket(Some(address))));
+
+        // closed at exec, and not open in /proc either
+        table.exec(pid);
+        assert_eq!(table.get(pid, 101), None);
+        assert_eq!(table.get(pid, 100), None);
+        assert!(table.get(pid, 0).is_some());
+    }
+
+    #[test]
+    fn exit_uses_the_entry_arguments() {
+        let pid = std::process::id() as pid_t;
+        let mut table = FdTable::default();
+        assert!(table.get(pid, 0).is_some());
+        let address = SocketAddress::Ip("127.0.0.1:80".parse().unwrap());
+        table.insert(100, FdKind::Socket(Some(address.clone())), false);
+
+        let cloexec = libc::O_CLOEXEC as u64;
+        table.syscall_exit(&Interceptor::exit_stop(
+            Syscall::Dup3,
+            [100, 101, cloexec, 0, 0, 0],
+            101,
+        ));
+        assert_eq!(table.get(pid, 101), Some(FdKind::Socket(Some(address))));
+
+        // `close` returns 0, it must not close stdin
+        table.syscall_exit(&Interceptor::exit_stop(
+            Syscall::Close,
+            [101, 0, 0, 0, 0, 0],
+            0,
+        ));
+        assert!(!table.fds.contains_key(&101));
+        assert!(table.fds.contains_key(&0));
+    }
+
+    #[test]
+    fn proc_kinds() {
+        let pid = std::process::id() as pid_t;
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        assert_eq!(proc_kind(pid, fds[0]), Some(FdKind::Pipe));
+        assert!(proc_cloexec(pid, fds[0]));
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        let file = fs::File::open("/proc/self/status").unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&file);
+        assert_eq!(
+            proc_kind(pid, fd),
+            Some(FdKind::File(format!("/proc/{pid}/status").into()))
+        );
+    }
+}
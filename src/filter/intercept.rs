@@ -5,12 +5,22 @@ use crate::{
     SeccompError,
 };
 
-use super::RestrictFilter;
+use super::{table::DispatchTable, RestrictFilter};
+
+/// boxed handler stored for every traced syscall, it gets the policy context `C` too
+pub(crate) type InterceptorCallback<C> = Box<dyn FnMut(Interceptor, &mut C) -> TraceAction>;
 
 /// seccomp fiters duh!
-pub(crate) struct InterceptorFilter {
+pub(crate) struct InterceptorFilter<C> {
     syscall: Syscall,
-    callback: Box<dyn Fn(Interceptor) -> TraceAction>,
+    callback: InterceptorCallback<C>,
+    /// registered by a `*_any` handler, explicit seccomp rules take precedence
+    wildcard: bool,
+    /// registered by a feature of the policy (`track_fds`, `remap_path`..), it only sees the
+    /// syscalls the seccomp rules allow
+    feature: bool,
+    /// id of the policy registration the filter comes from, see `AuditRecord::rule`
+    rule: usize,
 }
 
 // impl Debug for TracerFilter {
@@ -18,55 +28,100 @@ pub(crate) struct InterceptorFilter {
 //         write!(f, "TracerFilter({:?}), ", self.syscall)
 //     }
 // }
-impl InterceptorFilter {
+impl<C> InterceptorFilter<C> {
     /// declare a new filter
     pub fn new<F>(syscall: Syscall, callback: F) -> Self
     where
-        F: Fn(Interceptor) -> TraceAction + 'static,
+        F: FnMut(Interceptor, &mut C) -> TraceAction + 'static,
     {
         Self {
             syscall,
             callback: Box::new(callback),
+            wildcard: false,
+            feature: false,
+            rule: 0,
         }
     }
 
+    /// mark the filter as registered by a `*_any` handler
+    pub fn wildcard(mut self) -> Self {
+        self.wildcard = true;
+        self
+    }
+
+    /// mark the filter as registered by a feature of the policy
+    pub fn feature(mut self) -> Self {
+        self.feature = true;
+        self
+    }
+
+    /// set the id of the registration the filter comes from
+    pub fn rule(mut self, rule: usize) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.wildcard
+    }
+
+    pub fn is_feature(&self) -> bool {
+        self.feature
+    }
+
     pub fn syscall(&self) -> Syscall {
         self.syscall
     }
 
-    pub fn to_map(self) -> (Syscall, Box<dyn Fn(Interceptor) -> TraceAction>) {
-        (self.syscall, self.callback)
+    pub fn into_map(self) -> (Syscall, usize, InterceptorCallback<C>) {
+        (self.syscall, self.rule, self.callback)
+    }
+
+    /// wrap the handler, eg. for a policy with another context type
+    pub fn map_callback<D>(
+        self,
+        map: impl FnOnce(InterceptorCallback<C>) -> InterceptorCallback<D>,
+    ) -> InterceptorFilter<D> {
+        InterceptorFilter {
+            syscall: self.syscall,
+            callback: map(self.callback),
+            wildcard: self.wildcard,
+            feature: self.feature,
+            rule: self.rule,
+        }
     }
 }
-impl RestrictFilter for InterceptorFilter {
+impl<C> RestrictFilter for InterceptorFilter<C> {
     fn apply(&self, ctx: &mut SeccompWrapper) -> Result<(), SeccompError> {
         ctx.add_rule(Action::Trace, self.syscall)
     }
 }
-/// This is the struct that holds all the syscalls with their handlers
-/// it can be optimised to be more performant
-pub struct InterceptorMap(Vec<(Syscall, Box<dyn Fn(Interceptor) -> TraceAction>)>);
+/// This is the struct that holds all the syscalls with their handlers,
+/// indexed by syscall number
+pub struct InterceptorMap<C>(DispatchTable<InterceptorCallback<C>>);
 use core::fmt::Debug;
-impl Debug for InterceptorMap {
+impl<C> Debug for InterceptorMap<C> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "TracerMap({}), ", self.0.len())
+        write!(f, "InterceptorMap({}), ", self.0.len())
     }
 }
-impl InterceptorMap {
+impl<C> InterceptorMap<C> {
     /// Build this type from a vec of filters
-    pub(crate) fn from(intercepters_vec: Vec<InterceptorFilter>) -> Self {
-        InterceptorMap(
-            intercepters_vec
-                .into_iter()
-                .map(|x| x.to_map())
-                .collect::<Vec<_>>(),
-        )
+    pub(crate) fn from(intercepters_vec: Vec<InterceptorFilter<C>>) -> Self {
+        InterceptorMap(DispatchTable::new(
+            intercepters_vec.into_iter().map(|x| x.into_map()),
+        ))
+    }
+    /// the handlers of a syscall, in registration order
+    pub fn handlers(&mut self, syscall: Syscall) -> &mut [InterceptorCallback<C>] {
+        self.0.get_mut(syscall)
+    }
+    /// the rule id of the `n`th handler of `syscall`
+    pub fn rule(&self, syscall: Syscall, n: usize) -> Option<usize> {
+        self.0.rule(syscall, n)
     }
-    /// find a syscall
-    pub fn find_by_syscall(
-        &self,
-        syscall: Syscall,
-    ) -> Option<&Box<dyn Fn(Interceptor) -> TraceAction>> {
-        self.0.iter().find(|(s, _)| *s == syscall).map(|(_, cb)| cb)
+    /// whether `syscall` has a handler
+    pub fn contains(&self, syscall: Syscall) -> bool {
+        self.0.contains(syscall)
     }
 }
@@ -7,6 +7,8 @@ pub mod tracer;
 
 /// Interceptor that allows you to modify registers in entry and exit
 pub mod intercept;
+/// handlers indexed by syscall number
+pub(crate) mod table;
 /// define a Restrict filter trait
 pub(crate) trait RestrictFilter {
     /// this method defines the behavior of applying a filter on the context(eg, seccomp context)
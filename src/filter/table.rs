@@ -0,0 +1,91 @@
+use crate::syscall::Syscall;
+
+/// Handler chains indexed by syscall number, finding the handlers of a stop is a single index
+/// no matter how many syscalls the policy handles
+pub(crate) struct DispatchTable<H> {
+    slots: Vec<Vec<H>>,
+    /// rule id of every handler of `slots`
+    rules: Vec<Vec<usize>>,
+    len: usize,
+}
+
+impl<H> DispatchTable<H> {
+    /// build the table from `(syscall, rule id, handler)`, the handlers of a syscall keep their
+    /// registration order
+    pub(crate) fn new(handlers: impl IntoIterator<Item = (Syscall, usize, H)>) -> Self {
+        let mut table = Self {
+            slots: Vec::new(),
+            rules: Vec::new(),
+            len: 0,
+        };
+        for (syscall, rule, handler) in handlers {
+            let nr = syscall as usize;
+            if nr >= table.slots.len() {
+                table.slots.resize_with(nr + 1, Vec::new);
+                table.rules.resize_with(nr + 1, Vec::new);
+            }
+            table.slots[nr].push(handler);
+            table.rules[nr].push(rule);
+            table.len += 1;
+        }
+        table
+    }
+
+    /// the handler chain of `syscall`, empty if it has none
+    pub(crate) fn get_mut(&mut self, syscall: Syscall) -> &mut [H] {
+        match self.slots.get_mut(syscall as usize) {
+            Some(chain) => chain,
+            None => &mut [],
+        }
+    }
+
+    /// the rule id of the `n`th handler of the chain of `syscall`
+    pub(crate) fn rule(&self, syscall: Syscall, n: usize) -> Option<usize> {
+        self.rules.get(syscall as usize)?.get(n).copied()
+    }
+
+    /// whether `syscall` has a handler
+    pub(crate) fn contains(&self, syscall: Syscall) -> bool {
+        self.slots
+            .get(syscall as usize)
+            .is_some_and(|chain| !chain.is_empty())
+    }
+
+    /// number of handlers
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chains_keep_registration_order() {
+        let mut table = DispatchTable::new([
+            (Syscall::Write, 0, "first"),
+            (Syscall::Openat, 1, "openat"),
+            (Syscall::Write, 1, "second"),
+        ]);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get_mut(Syscall::Write), ["first", "second"]);
+        assert_eq!(table.get_mut(Syscall::Openat), ["openat"]);
+        assert!(table.get_mut(Syscall::Read).is_empty());
+        assert!(table.contains(Syscall::Openat));
+        assert!(!table.contains(Syscall::Read));
+        assert_eq!(table.rule(Syscall::Write, 1), Some(1));
+        assert_eq!(table.rule(Syscall::Read, 0), None);
+    }
+
+    #[test]
+    fn syscalls_past_the_table() {
+        let mut table = DispatchTable::new([(Syscall::Read, 0, ())]);
+        assert_eq!(table.get_mut(Syscall::Read), [()]);
+        assert!(table.get_mut(Syscall::Getpid).is_empty());
+
+        let mut empty = DispatchTable::<()>::new([]);
+        assert_eq!(empty.len(), 0);
+        assert!(empty.get_mut(Syscall::Read).is_empty());
+    }
+}
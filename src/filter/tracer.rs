@@ -4,12 +4,19 @@ use crate::{
     SeccompError,
 };
 
-use super::RestrictFilter;
+use super::{table::DispatchTable, RestrictFilter};
+
+/// boxed handler stored for every traced syscall, it gets the policy context `C` too
+pub(crate) type TracerCallback<C> = Box<dyn FnMut(Syscall, &mut C) -> TraceAction>;
 
 /// seccomp fiters duh!
-pub(crate) struct TracerFilter {
+pub(crate) struct TracerFilter<C> {
     syscall: Syscall,
-    callback: Box<dyn Fn(Syscall) -> TraceAction>,
+    callback: TracerCallback<C>,
+    /// registered by a `*_any` handler, explicit seccomp rules take precedence
+    wildcard: bool,
+    /// id of the policy registration the filter comes from, see `AuditRecord::rule`
+    rule: usize,
 }
 
 // impl Debug for TracerFilter {
@@ -17,55 +24,84 @@ pub(crate) struct TracerFilter {
 //         write!(f, "TracerFilter({:?}), ", self.syscall)
 //     }
 // }
-impl TracerFilter {
+impl<C> TracerFilter<C> {
     /// declare a new filter
     pub fn new<F>(syscall: Syscall, callback: F) -> Self
     where
-        F: Fn(Syscall) -> TraceAction + 'static,
+        F: FnMut(Syscall, &mut C) -> TraceAction + 'static,
     {
         Self {
             syscall,
             callback: Box::new(callback),
+            wildcard: false,
+            rule: 0,
         }
     }
 
+    /// mark the filter as registered by a `*_any` handler
+    pub fn wildcard(mut self) -> Self {
+        self.wildcard = true;
+        self
+    }
+
+    /// set the id of the registration the filter comes from
+    pub fn rule(mut self, rule: usize) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.wildcard
+    }
+
     pub fn syscall(&self) -> Syscall {
         self.syscall
     }
 
-    pub fn to_map(self) -> (Syscall, Box<dyn Fn(Syscall) -> TraceAction>) {
-        (self.syscall, self.callback)
+    pub fn into_map(self) -> (Syscall, usize, TracerCallback<C>) {
+        (self.syscall, self.rule, self.callback)
+    }
+
+    /// wrap the handler, eg. for a policy with another context type
+    pub fn map_callback<D>(
+        self,
+        map: impl FnOnce(TracerCallback<C>) -> TracerCallback<D>,
+    ) -> TracerFilter<D> {
+        TracerFilter {
+            syscall: self.syscall,
+            callback: map(self.callback),
+            wildcard: self.wildcard,
+            rule: self.rule,
+        }
     }
 }
-impl RestrictFilter for TracerFilter {
+impl<C> RestrictFilter for TracerFilter<C> {
     fn apply(&self, ctx: &mut SeccompWrapper) -> Result<(), SeccompError> {
         ctx.add_rule(Action::Trace, self.syscall)
     }
 }
-/// This is the struct that holds all the syscalls with their handlers
-/// it can be optimised to be more performant
-pub struct TracerMap(Vec<(Syscall, Box<dyn Fn(Syscall) -> TraceAction>)>);
+/// This is the struct that holds all the syscalls with their handlers,
+/// indexed by syscall number
+pub struct TracerMap<C>(DispatchTable<TracerCallback<C>>);
 use core::fmt::Debug;
-impl Debug for TracerMap {
+impl<C> Debug for TracerMap<C> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "TracerMap({}), ", self.0.len())
     }
 }
-impl TracerMap {
+impl<C> TracerMap<C> {
     /// Build this type from a vec of filters
-    pub(crate) fn from(tracers_vec: Vec<TracerFilter>) -> Self {
-        TracerMap(
-            tracers_vec
-                .into_iter()
-                .map(|x| x.to_map())
-                .collect::<Vec<_>>(),
-        )
+    pub(crate) fn from(tracers_vec: Vec<TracerFilter<C>>) -> Self {
+        TracerMap(DispatchTable::new(
+            tracers_vec.into_iter().map(|x| x.into_map()),
+        ))
+    }
+    /// the handlers of a syscall, in registration order
+    pub fn handlers(&mut self, syscall: Syscall) -> &mut [TracerCallback<C>] {
+        self.0.get_mut(syscall)
     }
-    /// find a syscall
-    pub fn find_by_syscall(
-        &self,
-        syscall: Syscall,
-    ) -> Option<&Box<dyn Fn(Syscall) -> TraceAction>> {
-        self.0.iter().find(|(s, _)| *s == syscall).map(|(_, cb)| cb)
+    /// the rule id of the `n`th handler of `syscall`
+    pub fn rule(&self, syscall: Syscall, n: usize) -> Option<usize> {
+        self.0.rule(syscall, n)
     }
 }
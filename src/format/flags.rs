@@ -0,0 +1,256 @@
+use libc::c_int;
+
+/// named values of a flags argument, in the order they are tested
+type Names = &'static [(c_int, &'static str)];
+
+macro_rules! names {
+    ($($name:ident),+ $(,)?) => {
+        &[$((libc::$name as c_int, stringify!($name))),+]
+    };
+}
+
+// O_TMPFILE and O_SYNC include other bits and must come before them
+const OPEN_FLAGS: Names = names!(
+    O_TMPFILE,
+    O_SYNC,
+    O_CREAT,
+    O_EXCL,
+    O_NOCTTY,
+    O_TRUNC,
+    O_APPEND,
+    O_NONBLOCK,
+    O_DSYNC,
+    O_ASYNC,
+    O_DIRECT,
+    O_LARGEFILE,
+    O_DIRECTORY,
+    O_NOFOLLOW,
+    O_NOATIME,
+    O_CLOEXEC,
+    O_PATH,
+);
+
+const PROT_FLAGS: Names = names!(PROT_READ, PROT_WRITE, PROT_EXEC);
+
+const MAP_TYPES: Names = names!(MAP_SHARED, MAP_PRIVATE, MAP_SHARED_VALIDATE);
+
+const MAP_FLAGS: Names = names!(
+    MAP_FIXED_NOREPLACE,
+    MAP_FIXED,
+    MAP_ANONYMOUS,
+    MAP_GROWSDOWN,
+    MAP_DENYWRITE,
+    MAP_EXECUTABLE,
+    MAP_LOCKED,
+    MAP_NORESERVE,
+    MAP_POPULATE,
+    MAP_NONBLOCK,
+    MAP_STACK,
+    MAP_HUGETLB,
+);
+
+const AT_FLAGS: Names = names!(
+    AT_SYMLINK_NOFOLLOW,
+    AT_REMOVEDIR,
+    AT_SYMLINK_FOLLOW,
+    AT_NO_AUTOMOUNT,
+    AT_EMPTY_PATH,
+);
+
+const ACCESS_MODES: Names = names!(R_OK, W_OK, X_OK);
+
+const WHENCE: Names = names!(SEEK_SET, SEEK_CUR, SEEK_END, SEEK_DATA, SEEK_HOLE);
+
+const SIGNALS: Names = names!(
+    SIGHUP, SIGINT, SIGQUIT, SIGILL, SIGTRAP, SIGABRT, SIGBUS, SIGFPE, SIGKILL, SIGUSR1, SIGSEGV,
+    SIGUSR2, SIGPIPE, SIGALRM, SIGTERM, SIGSTKFLT, SIGCHLD, SIGCONT, SIGSTOP, SIGTSTP, SIGTTIN,
+    SIGTTOU, SIGURG, SIGXCPU, SIGXFSZ, SIGVTALRM, SIGPROF, SIGWINCH, SIGIO, SIGPWR, SIGSYS,
+);
+
+const ADDRESS_FAMILIES: Names =
+    names!(AF_UNSPEC, AF_UNIX, AF_INET, AF_INET6, AF_NETLINK, AF_PACKET, AF_VSOCK);
+
+const SOCKET_TYPES: Names = names!(SOCK_STREAM, SOCK_DGRAM, SOCK_RAW, SOCK_SEQPACKET);
+
+const SOCKET_FLAGS: Names = names!(SOCK_NONBLOCK, SOCK_CLOEXEC);
+
+const FILE_TYPES: Names = names!(S_IFREG, S_IFDIR, S_IFLNK, S_IFCHR, S_IFBLK, S_IFIFO, S_IFSOCK);
+
+/// the name of the value `value` in `names`
+fn value(value: c_int, names: Names) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map(|(_, name)| *name)
+}
+
+/// `value` or its number when it has no name
+fn value_or_number(val: c_int, names: Names) -> String {
+    value(val, names).map_or_else(|| val.to_string(), str::to_string)
+}
+
+/// the names of the bits set in `flags`, unknown bits are printed in hex at the end
+fn bits(flags: c_int, names: Names) -> Vec<String> {
+    let mut rest = flags;
+    let mut set = Vec::new();
+    for (bit, name) in names {
+        if *bit != 0 && rest & bit == *bit {
+            set.push(name.to_string());
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        set.push(format!("{rest:#x}"));
+    }
+    set
+}
+
+/// `bits` joined with `|`, or `zero` when no bit is set
+fn bitmask(flags: c_int, names: Names, zero: &str) -> String {
+    let set = bits(flags, names);
+    if set.is_empty() {
+        zero.to_string()
+    } else {
+        set.join("|")
+    }
+}
+
+/// `O_RDONLY|O_CLOEXEC`, the access mode is a value and not a bit
+pub(crate) fn open_flags(flags: c_int) -> String {
+    let access = match flags & libc::O_ACCMODE {
+        libc::O_RDONLY => "O_RDONLY",
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_ACCMODE",
+    };
+    let mut set = vec![access.to_string()];
+    set.extend(bits(flags & !libc::O_ACCMODE, OPEN_FLAGS));
+    set.join("|")
+}
+
+/// whether `open_flags` creates a file, only then the mode argument is used
+pub(crate) fn creates_file(flags: c_int) -> bool {
+    flags & libc::O_CREAT != 0 || flags & libc::O_TMPFILE == libc::O_TMPFILE
+}
+
+/// `PROT_READ|PROT_WRITE`
+pub(crate) fn prot(prot: c_int) -> String {
+    bitmask(prot, PROT_FLAGS, "PROT_NONE")
+}
+
+/// `MAP_PRIVATE|MAP_ANONYMOUS`, the mapping type is a value and not a bit
+pub(crate) fn map_flags(flags: c_int) -> String {
+    let kind = flags & libc::MAP_SHARED_VALIDATE;
+    let mut set = vec![value_or_number(kind, MAP_TYPES)];
+    set.extend(bits(flags & !libc::MAP_SHARED_VALIDATE, MAP_FLAGS));
+    set.join("|")
+}
+
+/// `AT_SYMLINK_NOFOLLOW|AT_EMPTY_PATH`
+pub(crate) fn at_flags(flags: c_int) -> String {
+    bitmask(flags, AT_FLAGS, "0")
+}
+
+/// `R_OK|W_OK`
+pub(crate) fn access_mode(mode: c_int) -> String {
+    bitmask(mode, ACCESS_MODES, "F_OK")
+}
+
+/// `SEEK_SET`
+pub(crate) fn whence(whence: c_int) -> String {
+    value_or_number(whence, WHENCE)
+}
+
+/// `SIGTERM`, real time signals are printed as `SIGRT_<n>`
+pub(crate) fn signal(signal: c_int) -> String {
+    match value(signal, SIGNALS) {
+        Some(name) => name.to_string(),
+        None if (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&signal) => {
+            format!("SIGRT_{}", signal - libc::SIGRTMIN())
+        }
+        None => signal.to_string(),
+    }
+}
+
+/// `AF_INET`
+pub(crate) fn address_family(family: c_int) -> String {
+    value_or_number(family, ADDRESS_FAMILIES)
+}
+
+/// `SOCK_STREAM|SOCK_CLOEXEC`
+pub(crate) fn socket_type(kind: c_int) -> String {
+    let flags = libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+    let mut set = vec![value_or_number(kind & !flags, SOCKET_TYPES)];
+    set.extend(bits(kind & flags, SOCKET_FLAGS));
+    set.join("|")
+}
+
+/// `0644`
+pub(crate) fn mode(mode: u32) -> String {
+    format!("{:0>3}", format!("0{mode:o}"))
+}
+
+/// `S_IFREG|0644`
+pub(crate) fn file_mode(mode: u32) -> String {
+    let kind = (mode & libc::S_IFMT) as c_int;
+    match value(kind, FILE_TYPES) {
+        Some(name) => format!("{name}|{}", self::mode(mode & !libc::S_IFMT)),
+        None => self::mode(mode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_open_flags() {
+        assert_eq!(open_flags(libc::O_RDONLY), "O_RDONLY");
+        assert_eq!(
+            open_flags(libc::O_RDONLY | libc::O_CLOEXEC),
+            "O_RDONLY|O_CLOEXEC"
+        );
+        assert_eq!(
+            open_flags(libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC),
+            "O_WRONLY|O_CREAT|O_TRUNC"
+        );
+        // O_TMPFILE contains the O_DIRECTORY bit, it must not be printed twice
+        assert_eq!(
+            open_flags(libc::O_RDWR | libc::O_TMPFILE),
+            "O_RDWR|O_TMPFILE"
+        );
+        assert!(creates_file(libc::O_RDWR | libc::O_TMPFILE));
+        assert!(!creates_file(libc::O_DIRECTORY));
+    }
+
+    #[test]
+    fn decode_bitmasks_and_values() {
+        assert_eq!(
+            prot(libc::PROT_READ | libc::PROT_WRITE),
+            "PROT_READ|PROT_WRITE"
+        );
+        assert_eq!(prot(libc::PROT_NONE), "PROT_NONE");
+        assert_eq!(
+            map_flags(libc::MAP_PRIVATE | libc::MAP_ANONYMOUS),
+            "MAP_PRIVATE|MAP_ANONYMOUS"
+        );
+        assert_eq!(at_flags(0), "0");
+        assert_eq!(access_mode(libc::F_OK), "F_OK");
+        assert_eq!(whence(libc::SEEK_END), "SEEK_END");
+        assert_eq!(signal(libc::SIGKILL), "SIGKILL");
+        assert_eq!(signal(libc::SIGRTMIN() + 2), "SIGRT_2");
+        assert_eq!(
+            socket_type(libc::SOCK_STREAM | libc::SOCK_CLOEXEC),
+            "SOCK_STREAM|SOCK_CLOEXEC"
+        );
+        // unknown bits are kept in hex
+        assert_eq!(prot(libc::PROT_READ | 0x100), "PROT_READ|0x100");
+    }
+
+    #[test]
+    fn decode_modes() {
+        assert_eq!(mode(0o644), "0644");
+        assert_eq!(mode(0), "000");
+        assert_eq!(file_mode(libc::S_IFREG | 0o755), "S_IFREG|0755");
+    }
+}
@@ -0,0 +1,503 @@
+use std::{fmt, io::Write, mem::size_of, time::SystemTime};
+
+use libc::{c_int, pid_t};
+
+use crate::{error::Errno, interceptor::Interceptor, syscall::Syscall, SeccompError};
+
+use signature::Arg;
+
+/// flags and constants names
+mod flags;
+/// per syscall argument types
+mod signature;
+
+/// strings and buffers are cut after this many bytes, like strace does by default
+const STRING_LIMIT: usize = 32;
+
+/// paths are read up to `PATH_MAX`
+const PATH_LIMIT: usize = libc::PATH_MAX as usize;
+
+/// at most this many iovecs or argv strings are printed
+const ARRAY_LIMIT: usize = 16;
+
+/// A decoded syscall, printed the way strace does:
+/// `openat(AT_FDCWD, "/etc/passwd", O_RDONLY|O_CLOEXEC) = 3`
+///
+/// the inputs are decoded by `entry` while the tracee is stopped at the syscall entry, the
+/// buffers filled by the kernel (`read`, `fstat`..) and the return value by `finish` at exit
+#[derive(Debug, Clone)]
+pub struct SyscallEvent {
+    /// the traced process
+    pub pid: pid_t,
+    /// the thread stopped at the syscall, it equals `pid` until the tracer follows the
+    /// threads the process creates
+    pub tid: pid_t,
+    /// the syscall
+    pub syscall: Syscall,
+    /// the stop the event was last updated at
+    pub phase: Phase,
+    /// raw arguments at the syscall entry
+    pub args: [u64; 6],
+    /// raw return value, `None` until the syscall returned (`exit_group` never does)
+    pub ret: Option<i64>,
+    /// when the tracer saw the stop of `phase`
+    pub timestamp: SystemTime,
+    /// decoded arguments, outputs are filled at exit
+    decoded: Vec<String>,
+}
+
+/// The stop of the tracee a `SyscallEvent` comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// the syscall entry, the arguments are known
+    Entry,
+    /// the syscall exit, the return value and outputs are known too
+    Exit,
+}
+
+impl SyscallEvent {
+    /// decode the syscall `interceptor` is stopped at, at the syscall entry
+    pub fn entry(interceptor: &Interceptor) -> Self {
+        let regs = &interceptor.registers;
+        let args = std::array::from_fn(|n| regs.arg(n));
+        let syscall = interceptor.syscall;
+        let signature = signature::arguments(syscall);
+
+        let decoded = signature
+            .iter()
+            .enumerate()
+            .filter_map(|(n, arg)| match arg {
+                // the mode of `open` is garbage unless the file is created
+                Arg::CreateMode if !flags::creates_file(args[n - 1] as c_int) => None,
+                Arg::CreateMode => Some(flags::mode(args[n] as u32)),
+                arg if arg.is_output() => Some(hex(args[n])),
+                arg => Some(decode(interceptor, *arg, args[n], &args)),
+            })
+            .collect();
+
+        Self {
+            pid: interceptor.child_pid,
+            tid: interceptor.child_pid,
+            syscall,
+            phase: Phase::Entry,
+            args,
+            ret: None,
+            timestamp: SystemTime::now(),
+            decoded,
+        }
+    }
+
+    /// record the return value and decode the outputs, `interceptor` must be stopped at the
+    /// exit of the same syscall
+    pub fn finish(&mut self, interceptor: &Interceptor) {
+        let ret = interceptor.registers.return_value() as i64;
+        self.ret = Some(ret);
+        self.phase = Phase::Exit;
+        self.timestamp = SystemTime::now();
+        if Errno::from_return_value(ret).is_some() {
+            return;
+        }
+
+        let signature = signature::arguments(self.syscall);
+        // outputs are never behind a skipped `CreateMode` so the indexes still line up
+        for (n, arg) in signature.iter().enumerate() {
+            let addr = self.args[n];
+            let decoded = match arg {
+                Arg::OutBuf => buffer(interceptor, addr, ret as usize),
+                Arg::Stat => stat(interceptor, addr),
+                _ => continue,
+            };
+            if let Some(slot) = self.decoded.get_mut(n) {
+                *slot = decoded;
+            }
+        }
+    }
+
+    /// the decoded arguments (eg. `["AT_FDCWD", "\"/etc/passwd\"", "O_RDONLY"]`)
+    pub fn decoded_args(&self) -> &[String] {
+        &self.decoded
+    }
+
+    /// the errno the syscall failed with, if it did
+    pub fn errno(&self) -> Option<Errno> {
+        self.ret.and_then(Errno::from_return_value)
+    }
+}
+
+impl fmt::Display for SyscallEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({}) = ", self.syscall.name(), self.decoded.join(", "))?;
+        match (self.ret, self.errno()) {
+            (None, _) => write!(f, "?"),
+            (Some(_), Some(errno)) => write!(f, "-1 {errno}"),
+            (Some(ret), None) if signature::returns_address(self.syscall) => {
+                write!(f, "{ret:#x}")
+            }
+            (Some(ret), None) => write!(f, "{ret}"),
+        }
+    }
+}
+
+/// decode one input argument
+fn decode(interceptor: &Interceptor, arg: Arg, value: u64, args: &[u64; 6]) -> String {
+    match arg {
+        Arg::Int => (value as i64).to_string(),
+        Arg::Uint => value.to_string(),
+        Arg::Hex => hex(value),
+        Arg::Fd => (value as c_int).to_string(),
+        Arg::DirFd if value as c_int == libc::AT_FDCWD => "AT_FDCWD".to_string(),
+        Arg::DirFd => (value as c_int).to_string(),
+        Arg::Path => path(interceptor, value),
+        Arg::InBuf(len) => buffer(interceptor, value, args[len] as usize),
+        Arg::Iovec(count) => iovec(interceptor, value, args[count] as usize),
+        Arg::OpenFlags => flags::open_flags(value as c_int),
+        Arg::CreateMode | Arg::Mode => flags::mode(value as u32),
+        Arg::AtFlags => flags::at_flags(value as c_int),
+        Arg::AccessMode => flags::access_mode(value as c_int),
+        Arg::Prot => flags::prot(value as c_int),
+        Arg::MapFlags => flags::map_flags(value as c_int),
+        Arg::Whence => flags::whence(value as c_int),
+        Arg::Signal => flags::signal(value as c_int),
+        Arg::AddressFamily => flags::address_family(value as c_int),
+        Arg::SocketType => flags::socket_type(value as c_int),
+        Arg::Sockaddr(len) => sockaddr(interceptor, value, args[len] as usize),
+        Arg::Timespec => timespec(interceptor, value),
+        Arg::Argv => argv(interceptor, value),
+        Arg::OutBuf | Arg::Stat => hex(value),
+    }
+}
+
+/// `0x7ffd1234`
+fn hex(value: u64) -> String {
+    format!("{value:#x}")
+}
+
+/// a pointer that can't be decoded, `NULL` or its address
+fn pointer(addr: u64) -> String {
+    if addr == 0 {
+        "NULL".to_string()
+    } else {
+        hex(addr)
+    }
+}
+
+/// `"line\n"`, non printable bytes are escaped and `...` marks a truncated string
+fn quote(bytes: &[u8], truncated: bool) -> String {
+    let mut out = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(*byte as char),
+            _ => out.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+    out.push('"');
+    if truncated {
+        out.push_str("...");
+    }
+    out
+}
+
+fn path(interceptor: &Interceptor, addr: u64) -> String {
+    match interceptor.read_c_string(addr, PATH_LIMIT) {
+        Ok(path) if addr != 0 => quote(path.as_bytes(), false),
+        _ => pointer(addr),
+    }
+}
+
+/// the first `STRING_LIMIT` bytes of a `len` bytes long buffer
+fn buffer(interceptor: &Interceptor, addr: u64, len: usize) -> String {
+    match interceptor.read_bytes(addr, len.min(STRING_LIMIT)) {
+        Ok(bytes) if addr != 0 => quote(&bytes, len > STRING_LIMIT),
+        _ => pointer(addr),
+    }
+}
+
+/// `[{iov_base="hello", iov_len=5}]`
+fn iovec(interceptor: &Interceptor, addr: u64, count: usize) -> String {
+    let mut entries = Vec::new();
+    for i in 0..count.min(ARRAY_LIMIT) {
+        let entry_addr = addr + (i * size_of::<libc::iovec>()) as u64;
+        let Ok(iov) = interceptor.read_struct::<libc::iovec>(entry_addr) else {
+            return pointer(addr);
+        };
+        entries.push(format!(
+            "{{iov_base={}, iov_len={}}}",
+            buffer(interceptor, iov.iov_base as u64, iov.iov_len),
+            iov.iov_len
+        ));
+    }
+    if count > ARRAY_LIMIT {
+        entries.push("...".to_string());
+    }
+    format!("[{}]", entries.join(", "))
+}
+
+/// `["ls", "-l"]`
+fn argv(interceptor: &Interceptor, addr: u64) -> String {
+    let mut strings = Vec::new();
+    for i in 0..=ARRAY_LIMIT {
+        let Ok(ptr) = interceptor.read_struct::<u64>(addr + (i * size_of::<u64>()) as u64) else {
+            return pointer(addr);
+        };
+        if ptr == 0 {
+            return format!("[{}]", strings.join(", "));
+        }
+        if i == ARRAY_LIMIT {
+            break;
+        }
+        strings.push(match interceptor.read_c_string(ptr, PATH_LIMIT) {
+            Ok(string) => {
+                let bytes = string.as_bytes();
+                quote(
+                    &bytes[..bytes.len().min(STRING_LIMIT)],
+                    bytes.len() > STRING_LIMIT,
+                )
+            }
+            Err(_) => hex(ptr),
+        });
+    }
+    strings.push("...".to_string());
+    format!("[{}]", strings.join(", "))
+}
+
+/// `{tv_sec=1, tv_nsec=500}`
+fn timespec(interceptor: &Interceptor, addr: u64) -> String {
+    match interceptor.read_struct::<libc::timespec>(addr) {
+        Ok(ts) if addr != 0 => format!("{{tv_sec={}, tv_nsec={}}}", ts.tv_sec, ts.tv_nsec),
+        _ => pointer(addr),
+    }
+}
+
+/// `{st_mode=S_IFREG|0644, st_size=1234, ...}`
+fn stat(interceptor: &Interceptor, addr: u64) -> String {
+    match interceptor.read_struct::<libc::stat>(addr) {
+        Ok(st) if addr != 0 => format!(
+            "{{st_mode={}, st_size={}, ...}}",
+            flags::file_mode(st.st_mode),
+            st.st_size
+        ),
+        _ => pointer(addr),
+    }
+}
+
+/// `{sa_family=AF_INET, sin_port=htons(80), sin_addr=inet_addr("127.0.0.1")}`
+fn sockaddr(interceptor: &Interceptor, addr: u64, len: usize) -> String {
+    let len = len.min(size_of::<libc::sockaddr_storage>());
+    match interceptor.read_bytes(addr, len) {
+        Ok(bytes) if addr != 0 && len >= size_of::<libc::sa_family_t>() => sockaddr_bytes(&bytes),
+        _ => pointer(addr),
+    }
+}
+
+/// decode a socket address from its raw bytes
+fn sockaddr_bytes(bytes: &[u8]) -> String {
+    let mut storage = [0u8; size_of::<libc::sockaddr_storage>()];
+    storage[..bytes.len()].copy_from_slice(bytes);
+    let family: libc::sa_family_t = crate::memory::from_bytes(&storage);
+    let family_name = flags::address_family(family as c_int);
+
+    match family as c_int {
+        libc::AF_INET if bytes.len() >= size_of::<libc::sockaddr_in>() => {
+            let sin: libc::sockaddr_in = crate::memory::from_bytes(&storage);
+            let ip = std::net::Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+            format!(
+                "{{sa_family={family_name}, sin_port=htons({}), sin_addr=inet_addr(\"{ip}\")}}",
+                u16::from_be(sin.sin_port)
+            )
+        }
+        libc::AF_INET6 if bytes.len() >= size_of::<libc::sockaddr_in6>() => {
+            let sin6: libc::sockaddr_in6 = crate::memory::from_bytes(&storage);
+            let ip = std::net::Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+            format!(
+                "{{sa_family={family_name}, sin6_port=htons({}), sin6_addr=inet_pton(AF_INET6, \"{ip}\")}}",
+                u16::from_be(sin6.sin6_port)
+            )
+        }
+        libc::AF_UNIX => {
+            let path = &bytes[size_of::<libc::sa_family_t>()..];
+            // abstract sockets start with a NUL byte and aren't NUL terminated
+            let sun_path = match path.split_first() {
+                Some((0, name)) => format!("@{}", quote(name, false)),
+                _ => {
+                    let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+                    quote(&path[..end], false)
+                }
+            };
+            format!("{{sa_family={family_name}, sun_path={sun_path}}}")
+        }
+        _ => format!("{{sa_family={family_name}, ...}}"),
+    }
+}
+
+/// `Policy::trace_all_pretty` state: the syscall waiting for its exit stop and where to print it
+pub(crate) struct PrettyTracer {
+    writer: Box<dyn Write>,
+    pending: Option<SyscallEvent>,
+}
+
+impl PrettyTracer {
+    pub(crate) fn new<W: Write + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::new(writer),
+            pending: None,
+        }
+    }
+
+    /// decode the syscall at its entry, the seccomp stop following the entry stop of the same
+    /// syscall is ignored
+    pub(crate) fn entry(&mut self, interceptor: &Interceptor) {
+        if self.pending.is_none() {
+            self.pending = Some(SyscallEvent::entry(interceptor));
+        }
+    }
+
+    /// print the pending syscall with the return value found at its exit
+    pub(crate) fn exit(&mut self, interceptor: &Interceptor) -> Result<(), SeccompError> {
+        if let Some(mut event) = self.pending.take() {
+            event.finish(interceptor);
+            writeln!(self.writer, "{event}")?;
+        }
+        Ok(())
+    }
+
+    /// print the syscall that never returned (`exit_group`) and how the tracee ended
+    pub(crate) fn exited(&mut self, status: c_int) -> Result<(), SeccompError> {
+        if let Some(event) = self.pending.take() {
+            writeln!(self.writer, "{event}")?;
+        }
+        if libc::WIFEXITED(status) {
+            writeln!(
+                self.writer,
+                "+++ exited with {} +++",
+                libc::WEXITSTATUS(status)
+            )?;
+        } else if libc::WIFSIGNALED(status) {
+            let signal = flags::signal(libc::WTERMSIG(status));
+            writeln!(self.writer, "+++ killed by {signal} +++")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registers::Registers;
+
+    /// an interceptor for `syscall(args)` pointed at the current process
+    fn event(syscall: Syscall, args: [u64; 6]) -> SyscallEvent {
+        // SAFETY: user_regs_struct is plain integers
+        let raw = unsafe { std::mem::zeroed::<libc::user_regs_struct>() };
+        let mut regs = Registers::from_raw(raw);
+        for (n, arg) in args.into_iter().enumerate() {
+            regs.set_arg(n, arg);
+        }
+        let interceptor = Interceptor::new(syscall, regs, std::process::id() as pid_t);
+        SyscallEvent::entry(&interceptor)
+    }
+
+    #[test]
+    fn format_openat() {
+        let path = b"/etc/passwd\0";
+        let flags = (libc::O_RDONLY | libc::O_CLOEXEC) as u64;
+        let mut openat = event(
+            Syscall::Openat,
+            [libc::AT_FDCWD as u64, path.as_ptr() as u64, flags, 0, 0, 0],
+        );
+        assert_eq!(
+            openat.to_string(),
+            "openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY|O_CLOEXEC) = ?"
+        );
+
+        openat.ret = Some(3);
+        assert_eq!(
+            openat.to_string(),
+            "openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY|O_CLOEXEC) = 3"
+        );
+        openat.ret = Some(-libc::ENOENT as i64);
+        assert_eq!(
+            openat.to_string(),
+            "openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY|O_CLOEXEC) = -1 ENOENT (No such file or directory)"
+        );
+
+        let flags = (libc::O_WRONLY | libc::O_CREAT) as u64;
+        let creat = event(
+            Syscall::Openat,
+            [3, path.as_ptr() as u64, flags, 0o640, 0, 0],
+        );
+        assert_eq!(
+            creat.to_string(),
+            "openat(3, \"/etc/passwd\", O_WRONLY|O_CREAT, 0640) = ?"
+        );
+    }
+
+    #[test]
+    fn format_buffers() {
+        let data = b"hello\n\0world, this is a long buffer";
+        let write = event(
+            Syscall::Write,
+            [1, data.as_ptr() as u64, data.len() as u64, 0, 0, 0],
+        );
+        assert_eq!(
+            write.to_string(),
+            "write(1, \"hello\\n\\x00world, this is a long buf\"..., 35) = ?"
+        );
+        let null = event(Syscall::Write, [1, 0, 4, 0, 0, 0]);
+        assert_eq!(null.decoded_args()[1], "NULL");
+    }
+
+    #[test]
+    fn format_sockaddr() {
+        let sin = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: 8080u16.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        assert_eq!(
+            sockaddr_bytes(crate::memory::as_bytes(&sin)),
+            "{sa_family=AF_INET, sin_port=htons(8080), sin_addr=inet_addr(\"127.0.0.1\")}"
+        );
+
+        let mut unix = vec![0u8; 2];
+        unix[..2].copy_from_slice(&(libc::AF_UNIX as libc::sa_family_t).to_ne_bytes());
+        unix.extend_from_slice(b"/tmp/sock\0");
+        assert_eq!(
+            sockaddr_bytes(&unix),
+            "{sa_family=AF_UNIX, sun_path=\"/tmp/sock\"}"
+        );
+    }
+
+    #[test]
+    fn format_errno_and_address_returns() {
+        let mut mmap = event(
+            Syscall::Mmap,
+            [
+                0,
+                4096,
+                (libc::PROT_READ | libc::PROT_WRITE) as u64,
+                (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64,
+                -1i64 as u64,
+                0,
+            ],
+        );
+        mmap.ret = Some(0x7f00_0000_0000);
+        assert_eq!(
+            mmap.to_string(),
+            "mmap(0x0, 4096, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0x0) = 0x7f0000000000"
+        );
+        assert_eq!(
+            Errno(libc::EACCES).to_string(),
+            "EACCES (Permission denied)"
+        );
+        assert_eq!(Errno(4000).name(), None);
+    }
+}
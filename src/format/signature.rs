@@ -0,0 +1,168 @@
+use crate::syscall::Syscall;
+
+/// How a syscall argument is decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Arg {
+    /// signed integer
+    Int,
+    /// unsigned integer or size
+    Uint,
+    /// address or opaque value, printed in hex
+    Hex,
+    /// file descriptor
+    Fd,
+    /// directory file descriptor of the `*at` syscalls (`AT_FDCWD`)
+    DirFd,
+    /// NUL terminated path
+    Path,
+    /// buffer read by the kernel, its length is the given argument (`write`)
+    InBuf(usize),
+    /// buffer filled by the kernel, its length is the return value (`read`)
+    OutBuf,
+    /// iovec array read by the kernel, its length is the given argument (`writev`)
+    Iovec(usize),
+    /// `O_*` flags of `open`
+    OpenFlags,
+    /// permission bits, only printed when the previous `OpenFlags` argument creates a file
+    CreateMode,
+    /// permission bits
+    Mode,
+    /// `AT_*` flags
+    AtFlags,
+    /// `F_OK`/`R_OK`.. of `access`
+    AccessMode,
+    /// `PROT_*` flags
+    Prot,
+    /// `MAP_*` flags
+    MapFlags,
+    /// `SEEK_*` of `lseek`
+    Whence,
+    /// signal number
+    Signal,
+    /// `AF_*` address family
+    AddressFamily,
+    /// `SOCK_*` socket type and flags
+    SocketType,
+    /// socket address read by the kernel, its length is the given argument
+    Sockaddr(usize),
+    /// `struct timespec` read by the kernel
+    Timespec,
+    /// `struct stat` filled by the kernel
+    Stat,
+    /// NULL terminated array of strings (`execve` argv)
+    Argv,
+}
+
+impl Arg {
+    /// only known once the syscall returned
+    pub(crate) fn is_output(self) -> bool {
+        matches!(self, Arg::OutBuf | Arg::Stat)
+    }
+}
+
+use Arg::*;
+
+/// arguments of the syscalls without a known signature
+pub(crate) const UNKNOWN: &[Arg] = &[Hex, Hex, Hex, Hex, Hex, Hex];
+
+/// the arguments of `syscall`, `UNKNOWN` when the syscall has no signature yet
+pub(crate) fn arguments(syscall: Syscall) -> &'static [Arg] {
+    match syscall {
+        Syscall::Read => &[Fd, OutBuf, Uint],
+        Syscall::Getrandom => &[OutBuf, Uint, Hex],
+        Syscall::Pread64 => &[Fd, OutBuf, Uint, Int],
+        Syscall::Write => &[Fd, InBuf(2), Uint],
+        Syscall::Pwrite64 => &[Fd, InBuf(2), Uint, Int],
+        Syscall::Readv => &[Fd, Hex, Int],
+        Syscall::Writev => &[Fd, Iovec(2), Int],
+        Syscall::Close | Syscall::Dup | Syscall::Fchdir | Syscall::Fsync => &[Fd],
+        Syscall::Dup3 => &[Fd, Fd, OpenFlags],
+        Syscall::Openat => &[DirFd, Path, OpenFlags, CreateMode],
+        Syscall::Lseek => &[Fd, Int, Whence],
+        Syscall::Fstat => &[Fd, Stat],
+        Syscall::Newfstatat => &[DirFd, Path, Stat, AtFlags],
+        Syscall::Statx => &[DirFd, Path, AtFlags, Hex, Hex],
+        Syscall::Faccessat => &[DirFd, Path, AccessMode],
+        Syscall::Faccessat2 => &[DirFd, Path, AccessMode, AtFlags],
+        Syscall::Readlinkat => &[DirFd, Path, OutBuf, Uint],
+        Syscall::Getcwd => &[OutBuf, Uint],
+        Syscall::Chdir => &[Path],
+        Syscall::Mkdirat => &[DirFd, Path, Mode],
+        Syscall::Unlinkat => &[DirFd, Path, AtFlags],
+        Syscall::Renameat => &[DirFd, Path, DirFd, Path],
+        Syscall::Renameat2 => &[DirFd, Path, DirFd, Path, Uint],
+        Syscall::Fchmod => &[Fd, Mode],
+        Syscall::Fchmodat => &[DirFd, Path, Mode],
+        Syscall::Getdents64 => &[Fd, Hex, Uint],
+        Syscall::Ioctl | Syscall::Fcntl => &[Fd, Hex, Hex],
+        Syscall::Mmap => &[Hex, Uint, Prot, MapFlags, Fd, Hex],
+        Syscall::Mprotect => &[Hex, Uint, Prot],
+        Syscall::Munmap => &[Hex, Uint],
+        Syscall::Brk => &[Hex],
+        Syscall::Execve => &[Path, Argv, Hex],
+        Syscall::Execveat => &[DirFd, Path, Argv, Hex, AtFlags],
+        Syscall::Exit | Syscall::ExitGroup => &[Int],
+        Syscall::Kill | Syscall::Tkill => &[Int, Signal],
+        Syscall::Tgkill => &[Int, Int, Signal],
+        Syscall::RtSigaction => &[Signal, Hex, Hex, Uint],
+        Syscall::RtSigprocmask => &[Int, Hex, Hex, Uint],
+        Syscall::Socket => &[AddressFamily, SocketType, Int],
+        Syscall::Connect | Syscall::Bind => &[Fd, Sockaddr(2), Uint],
+        Syscall::Listen | Syscall::Shutdown => &[Fd, Int],
+        Syscall::Accept => &[Fd, Hex, Hex],
+        Syscall::Accept4 => &[Fd, Hex, Hex, SocketType],
+        Syscall::Sendto => &[Fd, InBuf(2), Uint, Hex, Sockaddr(5), Uint],
+        Syscall::Recvfrom => &[Fd, OutBuf, Uint, Hex, Hex, Hex],
+        Syscall::Nanosleep => &[Timespec, Hex],
+        Syscall::ClockNanosleep => &[Int, Int, Timespec, Hex],
+        Syscall::ClockGettime => &[Int, Hex],
+        Syscall::Gettimeofday => &[Hex, Hex],
+        Syscall::Uname | Syscall::SetTidAddress => &[Hex],
+        Syscall::Wait4 => &[Int, Hex, Int, Hex],
+        Syscall::Futex => &[Hex, Int, Uint, Hex, Hex, Uint],
+        Syscall::Prctl => &[Int, Hex, Hex, Hex, Hex],
+        Syscall::Seccomp => &[Uint, Uint, Hex],
+        Syscall::Getpid
+        | Syscall::Getppid
+        | Syscall::Gettid
+        | Syscall::Getuid
+        | Syscall::Geteuid
+        | Syscall::Getgid
+        | Syscall::Getegid
+        | Syscall::SchedYield
+        | Syscall::Sync => &[],
+        _ => legacy_arguments(syscall),
+    }
+}
+
+/// syscalls only x86_64 still has, newer architectures only have their `*at` variant
+#[cfg(target_arch = "x86_64")]
+fn legacy_arguments(syscall: Syscall) -> &'static [Arg] {
+    match syscall {
+        Syscall::Open => &[Path, OpenFlags, CreateMode],
+        Syscall::Creat => &[Path, Mode],
+        Syscall::Stat | Syscall::Lstat => &[Path, Stat],
+        Syscall::Access => &[Path, AccessMode],
+        Syscall::Readlink => &[Path, OutBuf, Uint],
+        Syscall::Mkdir | Syscall::Chmod => &[Path, Mode],
+        Syscall::Rmdir | Syscall::Unlink => &[Path],
+        Syscall::Rename => &[Path, Path],
+        Syscall::Dup2 => &[Fd, Fd],
+        Syscall::Pipe => &[Hex],
+        Syscall::Poll => &[Hex, Uint, Int],
+        Syscall::Time => &[Hex],
+        Syscall::ArchPrctl => &[Hex, Hex],
+        Syscall::Fork | Syscall::Vfork | Syscall::Pause => &[],
+        _ => UNKNOWN,
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn legacy_arguments(_syscall: Syscall) -> &'static [Arg] {
+    UNKNOWN
+}
+
+/// syscalls returning an address instead of a number
+pub(crate) fn returns_address(syscall: Syscall) -> bool {
+    matches!(syscall, Syscall::Mmap | Syscall::Mremap | Syscall::Brk)
+}
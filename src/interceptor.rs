@@ -1,6 +1,28 @@
-use libc::pid_t;
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CString,
+    mem::size_of,
+    rc::Rc,
+};
 
-use crate::{policy::Syscall, registers::Registers, SeccompError};
+use libc::{c_int, pid_t};
+
+use crate::{
+    fd::{self, FdKind, FdTable},
+    memory::{self, Plain},
+    policy::Syscall,
+    registers::Registers,
+    wrapper::PtraceWrapper,
+    SeccompError,
+};
+
+/// strings are read one chunk at a time without crossing this boundary, so a string that
+/// ends right before an unmapped page doesn't fault (pages are always a multiple of 4k)
+const READ_CHUNK: u64 = 4096;
+
+/// the ABI lets leaf functions keep data in these 128 bytes below the stack pointer,
+/// stack scratch memory starts right under them
+const STACK_RED_ZONE: u64 = 128;
 
 /// The Interceptor callback arguments
 pub struct Interceptor {
@@ -10,6 +32,15 @@ pub struct Interceptor {
     pub registers: Registers,
     /// child_pid
     pub child_pid: pid_t,
+    /// lowest stack address handed out by `alloc_scratch` (0 before the first one),
+    /// shared by the handlers chained on the same stop so they don't overwrite each other
+    scratch_top: Rc<Cell<u64>>,
+    /// intercepted at the syscall exit
+    at_exit: bool,
+    /// arguments the syscall was called with, saved at its entry for the exit handlers
+    entry_args: Option<[u64; 6]>,
+    /// fd table of `Policy::track_fds`, `/proc` is read instead without it
+    fds: Option<Rc<RefCell<FdTable>>>,
 }
 
 impl Interceptor {
@@ -18,7 +49,61 @@ impl Interceptor {
         Self {
             syscall: sc,
             registers: regs,
-            child_pid: child_pid,
+            child_pid,
+            scratch_top: Rc::default(),
+            at_exit: false,
+            entry_args: None,
+            fds: None,
+        }
+    }
+
+    /// hand out scratch memory below the one already given to the previous handlers of the
+    /// same stop
+    pub(crate) fn sharing_scratch(mut self, scratch_top: &Rc<Cell<u64>>) -> Self {
+        self.scratch_top = Rc::clone(scratch_top);
+        self
+    }
+
+    /// mark the interceptor as running at the syscall exit
+    pub(crate) fn at_exit(mut self) -> Self {
+        self.at_exit = true;
+        self
+    }
+
+    /// answer `arg` from the arguments saved at the syscall entry
+    pub(crate) fn with_entry_args(mut self, args: [u64; 6]) -> Self {
+        self.entry_args = Some(args);
+        self
+    }
+
+    /// the n-th argument the syscall was called with
+    ///
+    /// unlike `registers.arg` it is also valid at the syscall exit, where aarch64 and riscv64
+    /// have already replaced the first argument register with the return value
+    ///
+    /// # Panics
+    /// if `n` is greater than 5, syscalls take at most 6 arguments
+    pub fn arg(&self, n: usize) -> u64 {
+        match &self.entry_args {
+            Some(args) => args[n],
+            None => self.registers.arg(n),
+        }
+    }
+
+    /// answer `fd_info` from the tracked fd table
+    pub(crate) fn with_fds(mut self, fds: &Option<Rc<RefCell<FdTable>>>) -> Self {
+        self.fds = fds.clone();
+        self
+    }
+
+    /// what `fd` of the tracee refers to, `None` when it isn't open
+    ///
+    /// with `Policy::track_fds` the answer comes from the fd table, which also knows the
+    /// addresses of the sockets, otherwise from `/proc/<pid>/fd`
+    pub fn fd_info(&self, fd: c_int) -> Option<FdKind> {
+        match &self.fds {
+            Some(fds) => fds.borrow_mut().get(self.child_pid, fd),
+            None => fd::proc_kind(self.child_pid, fd),
         }
     }
     /// commit registers
@@ -26,4 +111,325 @@ impl Interceptor {
         self.registers.commit_regs(self.child_pid)?;
         Ok(())
     }
+
+    /// read `len` bytes at `addr` in the tracee memory
+    pub fn read_bytes(&self, addr: u64, len: usize) -> Result<Vec<u8>, SeccompError> {
+        let mut buf = vec![0; len];
+        PtraceWrapper::with_pid(self.child_pid).read_memory(addr, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// read a NUL terminated string at `addr` (eg. the path of `openat`),
+    /// giving up with `UnterminatedString` after `max` bytes
+    pub fn read_c_string(&self, addr: u64, max: usize) -> Result<CString, SeccompError> {
+        let mut string = Vec::new();
+        let mut cursor = addr;
+        while string.len() < max {
+            let to_boundary = (READ_CHUNK - cursor % READ_CHUNK) as usize;
+            let chunk = self.read_bytes(cursor, to_boundary.min(max - string.len()))?;
+            if let Some(nul) = chunk.iter().position(|b| *b == 0) {
+                string.extend_from_slice(&chunk[..nul]);
+                return Ok(CString::new(string).expect("the string stops at the first NUL"));
+            }
+            string.extend_from_slice(&chunk);
+            cursor += chunk.len() as u64;
+        }
+        Err(SeccompError::UnterminatedString(addr, max))
+    }
+
+    /// read a `T` at `addr` (eg. the `timespec` of `nanosleep`)
+    pub fn read_struct<T: Plain>(&self, addr: u64) -> Result<T, SeccompError> {
+        let bytes = self.read_bytes(addr, size_of::<T>())?;
+        Ok(memory::from_bytes(&bytes))
+    }
+
+    /// read the buffers of the `iovcnt` long iovec array at `addr` (readv, writev, sendmsg..)
+    /// and concatenate them, stopping after `max` bytes
+    pub fn read_iovec(
+        &self,
+        addr: u64,
+        iovcnt: usize,
+        max: usize,
+    ) -> Result<Vec<u8>, SeccompError> {
+        let mut data = Vec::new();
+        for i in 0..iovcnt {
+            if data.len() >= max {
+                break;
+            }
+            let iov_addr = addr + (i * size_of::<libc::iovec>()) as u64;
+            let iov: libc::iovec = self.read_struct(iov_addr)?;
+            let len = iov.iov_len.min(max - data.len());
+            data.extend(self.read_bytes(iov.iov_base as u64, len)?);
+        }
+        Ok(data)
+    }
+
+    /// write `data` at `addr` in the tracee memory
+    pub fn write_bytes(&self, addr: u64, data: &[u8]) -> Result<(), SeccompError> {
+        PtraceWrapper::with_pid(self.child_pid).write_memory(addr, data)
+    }
+
+    /// write `value` at `addr` (eg. fill the `utsname` of `uname` at exit)
+    pub fn write_struct<T: Plain>(&self, addr: u64, value: &T) -> Result<(), SeccompError> {
+        self.write_bytes(addr, memory::as_bytes(value))
+    }
+
+    /// reserve `len` bytes of scratch memory on the tracee stack and return their address
+    ///
+    /// the memory sits below the red zone, the tracee doesn't touch it while it is blocked in
+    /// the intercepted syscall, so it is only valid until that syscall returns. Fails with
+    /// `MemoryFault` when the stack pointer is too low to fit `len` bytes
+    pub fn alloc_scratch(&mut self, len: usize) -> Result<u64, SeccompError> {
+        let top = match self.scratch_top.get() {
+            0 => self.registers.stack_pointer().checked_sub(STACK_RED_ZONE),
+            top => Some(top),
+        };
+        let addr = top
+            .and_then(|top| top.checked_sub(len as u64))
+            .map(|addr| addr & !15)
+            // a null scratch address would read as NULL to the syscall
+            .filter(|&addr| addr != 0)
+            .ok_or(SeccompError::MemoryFault(
+                self.child_pid,
+                self.registers.stack_pointer(),
+            ))?;
+        self.scratch_top.set(addr);
+        Ok(addr)
+    }
+
+    /// map `len` bytes of fresh read/write memory in the tracee by running `mmap` on its behalf
+    ///
+    /// the mapping outlives the syscall and is never released, prefer `alloc_scratch` for
+    /// short lived data. Only possible in `entry_intercept` handlers
+    pub fn map_scratch(&mut self, len: usize) -> Result<u64, SeccompError> {
+        if self.at_exit {
+            return Err(SeccompError::SyscallInjection(
+                self.child_pid,
+                Syscall::Mmap,
+                std::io::Error::other("syscalls can only be injected at the syscall entry"),
+            ));
+        }
+        let prot = (libc::PROT_READ | libc::PROT_WRITE) as u64;
+        let flags = (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64;
+        let addr = PtraceWrapper::with_pid(self.child_pid).inject_syscall(
+            &self.registers,
+            Syscall::Mmap,
+            [0, len as u64, prot, flags, -1i64 as u64, 0],
+        )?;
+        Ok(addr as u64)
+    }
+
+    /// copy `data` into scratch memory and return its address (eg. a longer replacement path)
+    ///
+    /// uses the stack like `alloc_scratch` and falls back to `map_scratch` when the stack
+    /// can't take it
+    pub fn write_scratch(&mut self, data: &[u8]) -> Result<u64, SeccompError> {
+        let previous_top = self.scratch_top.get();
+        match self
+            .alloc_scratch(data.len())
+            .and_then(|addr| self.write_bytes(addr, data).map(|_| addr))
+        {
+            Err(SeccompError::MemoryFault(..)) => {
+                self.scratch_top.set(previous_top);
+                let addr = self.map_scratch(data.len())?;
+                self.write_bytes(addr, data)?;
+                Ok(addr)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Interceptor {
+    /// the exit stop of `syscall` of the current process returning `ret`, called with
+    /// `args`. The first argument register holds the return value like on aarch64 and riscv64
+    pub(crate) fn exit_stop(syscall: Syscall, args: [u64; 6], ret: u64) -> Self {
+        // SAFETY: user_regs_struct is plain integers
+        let mut regs = Registers::from_raw(unsafe { std::mem::zeroed() });
+        regs.set_arg(0, ret);
+        regs.set_return_value(ret);
+        Interceptor::new(syscall, regs, std::process::id() as pid_t)
+            .with_entry_args(args)
+            .at_exit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// an interceptor pointed at the current process, `process_vm_readv` on ourselves
+    /// doesn't need ptrace
+    fn self_interceptor() -> Interceptor {
+        // SAFETY: user_regs_struct is plain integers
+        let raw = unsafe { std::mem::zeroed::<libc::user_regs_struct>() };
+        Interceptor::new(
+            Syscall::Read,
+            Registers::from_raw(raw),
+            std::process::id() as pid_t,
+        )
+    }
+
+    /// map two pages and make the second one inaccessible
+    fn guarded_page() -> *mut u8 {
+        let page = READ_CHUNK as usize;
+        // SAFETY: anonymous private mapping, checked below
+        unsafe {
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                page * 2,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(ptr, libc::MAP_FAILED);
+            assert_eq!(libc::mprotect(ptr.add(page), page, libc::PROT_NONE), 0);
+            ptr as *mut u8
+        }
+    }
+
+    #[test]
+    fn read_bytes_and_struct() {
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let interceptor = self_interceptor();
+        let addr = data.as_ptr() as u64;
+
+        assert_eq!(interceptor.read_bytes(addr + 2, 3).unwrap(), vec![3, 4, 5]);
+        let word: u64 = interceptor.read_struct(addr).unwrap();
+        assert_eq!(word, u64::from_ne_bytes(data));
+    }
+
+    #[test]
+    fn read_c_string_before_unmapped_page() {
+        let page = READ_CHUNK as usize;
+        let base = guarded_page();
+        let text = b"/etc/passwd\0";
+        // SAFETY: the string fits at the end of the first (writable) page
+        let start = unsafe {
+            let start = base.add(page - text.len());
+            std::ptr::copy_nonoverlapping(text.as_ptr(), start, text.len());
+            start
+        };
+
+        let interceptor = self_interceptor();
+        let string = interceptor.read_c_string(start as u64, 4096).unwrap();
+        assert_eq!(string.as_bytes(), b"/etc/passwd");
+
+        match interceptor.read_c_string(start as u64, 4) {
+            Err(SeccompError::UnterminatedString(addr, 4)) => assert_eq!(addr, start as u64),
+            other => panic!("expected UnterminatedString, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_faulting_address() {
+        let page = READ_CHUNK;
+        let base = guarded_page() as u64;
+        let interceptor = self_interceptor();
+
+        match interceptor.read_bytes(base + page, 8) {
+            Err(SeccompError::MemoryFault(_, addr)) => assert_eq!(addr, base + page),
+            other => panic!("expected MemoryFault, got {other:?}"),
+        }
+        // a read crossing into the protected page reports where it stopped
+        match interceptor.read_bytes(base + page - 4, 8) {
+            Err(SeccompError::MemoryFault(_, addr)) => assert_eq!(addr, base + page),
+            other => panic!("expected MemoryFault, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn write_bytes_and_struct() {
+        let mut data = [0u8; 16];
+        let interceptor = self_interceptor();
+        let addr = data.as_mut_ptr() as u64;
+
+        interceptor.write_bytes(addr + 1, b"abc").unwrap();
+        interceptor.write_struct(addr + 8, &0x1122u16).unwrap();
+        // SAFETY: the writes above went through the kernel, read the buffer back from memory
+        let data = unsafe { std::ptr::read_volatile(&data) };
+        assert_eq!(&data[..4], b"\0abc");
+        assert_eq!(u16::from_ne_bytes([data[8], data[9]]), 0x1122);
+    }
+
+    #[test]
+    fn alloc_scratch_below_red_zone() {
+        let mut interceptor = self_interceptor();
+        let mut raw = interceptor.registers.clone().into_raw();
+        #[cfg(target_arch = "x86_64")]
+        {
+            raw.rsp = 0x7fff_0000_1008;
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            raw.sp = 0x7fff_0000_1008;
+        }
+        interceptor.registers = Registers::from_raw(raw);
+
+        let first = interceptor.alloc_scratch(10).unwrap();
+        let second = interceptor.alloc_scratch(32).unwrap();
+        assert_eq!(first % 16, 0);
+        assert!(first + 10 <= 0x7fff_0000_1008 - STACK_RED_ZONE);
+        assert!(second + 32 <= first);
+    }
+
+    #[test]
+    fn alloc_scratch_past_the_bottom_fails() {
+        let mut interceptor = self_interceptor();
+        let mut raw = interceptor.registers.clone().into_raw();
+        #[cfg(target_arch = "x86_64")]
+        {
+            raw.rsp = 0x100;
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            raw.sp = 0x100;
+        }
+        interceptor.registers = Registers::from_raw(raw);
+
+        assert!(matches!(
+            interceptor.alloc_scratch(0x1000),
+            Err(SeccompError::MemoryFault(_, 0x100))
+        ));
+        // a failed allocation doesn't move the scratch top
+        assert_eq!(interceptor.alloc_scratch(0x10).unwrap(), 0x70);
+        assert!(matches!(
+            interceptor.alloc_scratch(0x70),
+            Err(SeccompError::MemoryFault(..))
+        ));
+    }
+
+    #[test]
+    fn exit_args_come_from_the_entry() {
+        let mut interceptor = self_interceptor();
+        interceptor.registers.set_arg(0, 3);
+        assert_eq!(interceptor.arg(0), 3);
+
+        let exit = Interceptor::exit_stop(Syscall::Read, [3, 0, 0x1000, 0, 0, 0], 42);
+        assert_eq!(exit.registers.arg(0), 42);
+        assert_eq!(exit.arg(0), 3);
+        assert_eq!(exit.arg(2), 0x1000);
+    }
+
+    #[test]
+    fn read_iovec_concatenates_buffers() {
+        let (first, second) = (b"hello ".to_vec(), b"world".to_vec());
+        let iov = [
+            libc::iovec {
+                iov_base: first.as_ptr() as *mut _,
+                iov_len: first.len(),
+            },
+            libc::iovec {
+                iov_base: second.as_ptr() as *mut _,
+                iov_len: second.len(),
+            },
+        ];
+        let interceptor = self_interceptor();
+        let addr = iov.as_ptr() as u64;
+
+        assert_eq!(interceptor.read_iovec(addr, 2, 64).unwrap(), b"hello world");
+        assert_eq!(interceptor.read_iovec(addr, 2, 8).unwrap(), b"hello wo");
+    }
 }
@@ -25,26 +25,54 @@ SOFTWARE.
 //! Restrict
 #![deny(missing_docs)]
 
+/// Structured audit log of the traced syscalls
+pub mod audit;
+/// Virtual time seen by the restricted process
+pub mod clock;
 /// Error handling module
 pub mod error;
+/// Exec allow-listing by path and content hash
+pub mod exec;
+/// Deterministic fault injection for chaos testing
+pub mod fault;
+/// File descriptor table of the restricted process
+pub mod fd;
 /// filters
 mod filter;
+/// strace-like decoding of traced syscalls
+pub mod format;
 /// interceptors
 pub mod interceptor;
+/// time and CPU limits of the restricted process
+mod limits;
+/// Reading and writing the memory of the traced process
+pub mod memory;
+/// Network policy by socket address
+pub mod net;
 /// Modules for most common rules
 // this should be called profiles
 // pub mod modules;
 /// Safer, unsafe-free and ergonomic wrapper around wrapper module
 pub mod policy;
+/// syscall quotas and rate limits
+mod quota;
+/// seeded entropy for the restricted process
+mod random;
 /// registers
 pub mod registers;
+/// path remapping of the file syscalls
+mod remap;
+/// Syscall events consumed from another thread
+pub mod stream;
 /// Strongly tyoed system calls enum
 pub mod syscall;
 /// Tracer
 mod tracer;
 /// unsafe bindings
 mod wrapper;
-pub use error::SeccompError;
-pub use wrapper::TraceAction;
+pub use error::{Errno, SeccompError};
+pub use format::{Phase, SyscallEvent};
+pub use quota::OnExceeded;
+pub use wrapper::{OnError, Outcome, SignalAction, TraceAction};
 pub(crate) mod logging;
 pub(crate) mod metrics;
@@ -0,0 +1,196 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use libc::pid_t;
+
+use crate::SeccompError;
+
+/// Resource limits of the restricted process
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Limits {
+    /// wall-clock time, enforced by the tracer
+    pub(crate) time: Option<Duration>,
+    /// CPU time, enforced by the kernel with `RLIMIT_CPU`
+    pub(crate) cpu: Option<Duration>,
+}
+
+impl Limits {
+    /// whether the tracee used up its CPU time, `usage` is its `rusage` once it exited
+    pub(crate) fn cpu_exceeded(&self, usage: &libc::rusage) -> bool {
+        let used =
+            |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+        self.cpu
+            .is_some_and(|limit| used(usage.ru_utime) + used(usage.ru_stime) >= limit)
+    }
+}
+
+/// set `RLIMIT_CPU` of the calling process, it gets `SIGXCPU` once it used `limit`
+/// (rounded up to whole seconds) and `SIGKILL` one second later
+pub(crate) fn set_cpu_limit(limit: Duration) -> Result<(), SeccompError> {
+    let secs = (limit.as_secs() + u64::from(limit.subsec_nanos() > 0)).max(1);
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: plain getrlimit/setrlimit calls on a valid rlimit
+    if unsafe { libc::getrlimit(libc::RLIMIT_CPU, &mut rlim) } == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    // an unprivileged process can't raise its hard limit
+    rlim.rlim_max = rlim.rlim_max.min(secs + 1);
+    rlim.rlim_cur = rlim.rlim_max.min(secs);
+    if unsafe { libc::setrlimit(libc::RLIMIT_CPU, &rlim) } == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Kills the tracee with `SIGKILL` once its wall-clock time limit is reached
+///
+/// the event loop blocks in `wait4`, which has no timeout, and can't poll for the deadline
+/// first: a pidfd only becomes readable when the tracee exits, not when it stops, and
+/// interrupting `wait4` with a timer signal would need a process-wide handler in the host
+/// program. So the deadline is kept by a thread waiting on a pidfd of the tracee, the event
+/// loop then sees the kill as any other `SIGKILL` and asks [`Watchdog::fired`] for the outcome.
+/// The thread ends as soon as the tracee exits or the watchdog is dropped, which joins it,
+/// and the pidfd keeps the signal from reaching another process reusing the pid
+pub(crate) struct Watchdog {
+    fired: Arc<AtomicBool>,
+    /// eventfd waking the thread up when the watchdog is dropped before the tracee exited
+    stop: OwnedFd,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// start watching `pid`, which must not be reaped yet
+    pub(crate) fn start(pid: pid_t, limit: Duration) -> Result<Self, SeccompError> {
+        // SAFETY: pidfd_open only returns a new fd or -1
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+        if fd == -1 {
+            return Err(SeccompError::Watchdog(pid, io::Error::last_os_error()));
+        }
+        // SAFETY: the fd was just opened and is owned by nothing else
+        let pidfd = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+        // SAFETY: eventfd only returns a new fd or -1
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if fd == -1 {
+            return Err(SeccompError::Watchdog(pid, io::Error::last_os_error()));
+        }
+        // SAFETY: the fd was just opened and is owned by nothing else
+        let stop = unsafe { OwnedFd::from_raw_fd(fd) };
+        let stop_fd = stop.as_raw_fd();
+
+        let deadline = Instant::now() + limit;
+        let fired = Arc::new(AtomicBool::new(false));
+        let thread = thread::spawn({
+            let fired = fired.clone();
+            move || {
+                let mut poll = [pidfd.as_raw_fd(), stop_fd].map(|fd| libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+                loop {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        // SAFETY: the pidfd refers to the tracee until it is closed
+                        unsafe {
+                            libc::syscall(
+                                libc::SYS_pidfd_send_signal,
+                                pidfd.as_raw_fd(),
+                                libc::SIGKILL,
+                                std::ptr::null::<libc::siginfo_t>(),
+                                0,
+                            )
+                        };
+                        fired.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    // round up, a zero timeout would spin until the deadline
+                    let timeout = left.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+                    // SAFETY: `poll` is an array of valid pollfds, the eventfd outlives the
+                    // thread since the watchdog joins it before closing it
+                    match unsafe { libc::poll(poll.as_mut_ptr(), 2, timeout) } {
+                        0 => {}
+                        -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                        // the pidfd is readable once the tracee exited, the eventfd once the
+                        // watchdog was dropped
+                        _ => return,
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            fired,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// whether the watchdog killed the tracee, the tracee must have exited
+    pub(crate) fn fired(mut self) -> bool {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.fired.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let one = 1u64;
+            // SAFETY: writes the 8 bytes of `one` to our own eventfd
+            unsafe {
+                libc::write(
+                    self.stop.as_raw_fd(),
+                    (&one as *const u64).cast(),
+                    std::mem::size_of::<u64>(),
+                )
+            };
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_usage_against_the_limit() {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        usage.ru_utime.tv_sec = 1;
+        usage.ru_stime.tv_usec = 500_000;
+
+        let limits = |cpu| Limits { time: None, cpu };
+        assert!(limits(Some(Duration::from_millis(1500))).cpu_exceeded(&usage));
+        assert!(!limits(Some(Duration::from_secs(2))).cpu_exceeded(&usage));
+        assert!(!limits(None).cpu_exceeded(&usage));
+    }
+
+    #[test]
+    fn dropped_watchdog_stops_watching() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let watchdog = Watchdog::start(child.id() as pid_t, Duration::from_secs(5)).unwrap();
+
+        let start = Instant::now();
+        drop(watchdog);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // the child outlived its watchdog
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use std::mem::size_of;
+
+/// Types that can be rebuilt from raw bytes copied out of the tracee memory
+///
+/// # Safety
+/// every bit pattern must be a valid value of the type, so no references, `bool`, `char`
+/// or enums, and there must be no implicit padding since values are also written back as
+/// bytes. Plain C structs made of integers and raw pointers are fine
+pub unsafe trait Plain: Copy {}
+
+macro_rules! impl_plain {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            // SAFETY: integers and C structs made only of integers/raw pointers
+            unsafe impl Plain for $ty {}
+        )+
+    };
+}
+
+impl_plain!(
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    isize,
+    libc::iovec,
+    libc::timespec,
+    libc::timeval,
+    libc::stat,
+    libc::statfs,
+    libc::utsname,
+    libc::rlimit,
+    libc::pollfd,
+    libc::sockaddr,
+    libc::sockaddr_in,
+    libc::sockaddr_in6,
+    libc::sockaddr_un,
+    libc::sockaddr_storage,
+);
+
+// SAFETY: the general purpose registers of the supported architectures are all 64 bits
+#[cfg(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+))]
+unsafe impl Plain for libc::user_regs_struct {}
+
+// SAFETY: an array of plain values is plain
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+/// build a `T` from the first `size_of::<T>()` bytes of `bytes`
+pub(crate) fn from_bytes<T: Plain>(bytes: &[u8]) -> T {
+    assert!(
+        bytes.len() >= size_of::<T>(),
+        "not enough bytes for the type"
+    );
+    // SAFETY: the length is checked above and `T: Plain` accepts any bit pattern
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+/// view a plain value as its raw bytes
+pub(crate) fn as_bytes<T: Plain>(value: &T) -> &[u8] {
+    // SAFETY: `T: Plain` has no padding so all of its `size_of::<T>()` bytes are initialized
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
@@ -21,3 +21,192 @@ macro_rules! restrict_counter {
         }
     };
 }
+
+/// restrict metrics wrapper
+#[cfg(feature = "metrics")]
+#[macro_export]
+macro_rules! restrict_gauge {
+    ($name:expr, $value:expr $(, $label:expr => $value_expr:expr)*) => {
+        ::metrics::gauge!(
+            $name,
+            $value,
+            $($label => $value_expr,)*
+        )
+    };
+}
+
+/// restrict metrics wrapper
+#[cfg(not(feature = "metrics"))]
+#[macro_export]
+macro_rules! restrict_gauge {
+    ($name:expr, $value:expr $(, $label:expr => $value_expr:expr)*) => {
+        if false {
+            let _ = ($name, $value $(, $label, $value_expr)*);
+        }
+    };
+}
+
+/// restrict metrics wrapper
+#[cfg(feature = "metrics")]
+#[macro_export]
+macro_rules! restrict_histogram {
+    ($name:expr, $value:expr $(, $label:expr => $value_expr:expr)*) => {
+        ::metrics::histogram!(
+            $name,
+            $value,
+            $($label => $value_expr,)*
+        )
+    };
+}
+
+/// restrict metrics wrapper
+#[cfg(not(feature = "metrics"))]
+#[macro_export]
+macro_rules! restrict_histogram {
+    ($name:expr, $value:expr $(, $label:expr => $value_expr:expr)*) => {
+        if false {
+            let _ = ($name, $value $(, $label, $value_expr)*);
+        }
+    };
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) use runtime::RuntimeMetrics;
+
+/// `Policy::runtime_metrics`
+#[cfg(feature = "metrics")]
+mod runtime {
+    use std::{fs, time::Instant};
+
+    use libc::pid_t;
+
+    use crate::{audit::Verdict, interceptor::Interceptor, syscall::Syscall};
+
+    /// the syscall waiting for its exit stop
+    struct Pending {
+        syscall: Syscall,
+        start: Instant,
+        verdict: Verdict,
+        rule: Option<usize>,
+    }
+
+    /// the traced process, the labels of every metric
+    struct Task {
+        pid: String,
+        exe: String,
+    }
+
+    impl Task {
+        fn new(pid: pid_t) -> Self {
+            let exe = fs::read_link(format!("/proc/{pid}/exe"))
+                .map(|exe| exe.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Self {
+                pid: pid.to_string(),
+                exe,
+            }
+        }
+
+        /// `restrict.tasks.live`, 1 while the task is traced
+        fn live(&self, live: bool) {
+            crate::restrict_gauge!("restrict.tasks.live", if live { 1.0 } else { 0.0 },
+                "pid" => self.pid.clone(),
+                "exe" => self.exe.clone());
+        }
+    }
+
+    /// `Policy::runtime_metrics` state in the event loop: the traced task and the syscall
+    /// being measured
+    #[derive(Default)]
+    pub(crate) struct RuntimeMetrics {
+        task: Option<Task>,
+        pending: Option<Pending>,
+    }
+
+    impl RuntimeMetrics {
+        fn task(&mut self, pid: pid_t) -> &Task {
+            self.task.get_or_insert_with(|| {
+                let task = Task::new(pid);
+                task.live(true);
+                task
+            })
+        }
+
+        /// a syscall entry, the seccomp stop following the entry stop of the same syscall is
+        /// ignored
+        pub(crate) fn entry(&mut self, interceptor: &Interceptor) {
+            self.task(interceptor.child_pid);
+            if self.pending.is_none() {
+                self.pending = Some(Pending {
+                    syscall: interceptor.syscall,
+                    start: Instant::now(),
+                    verdict: Verdict::Allowed,
+                    rule: None,
+                });
+            }
+        }
+
+        /// the handler registered by `rule` decided `verdict` for the pending syscall
+        pub(crate) fn decided(&mut self, verdict: Verdict, rule: Option<usize>) {
+            if let Some(pending) = self.pending.as_mut() {
+                pending.verdict = verdict;
+                pending.rule = rule;
+            }
+        }
+
+        /// the exit stop of the pending syscall
+        pub(crate) fn exit(&mut self) {
+            if let Some(pending) = self.pending.take() {
+                self.record(&pending, true);
+            }
+        }
+
+        /// the pending syscall won't stop at its exit (`SkipExit`, killed process)
+        pub(crate) fn no_exit(&mut self) {
+            if let Some(pending) = self.pending.take() {
+                self.record(&pending, false);
+            }
+        }
+
+        /// the traced process exec'd, its executable changed
+        pub(crate) fn exec(&mut self, pid: pid_t) {
+            if let Some(task) = self.task.take() {
+                task.live(false);
+            }
+            self.task(pid);
+        }
+
+        /// the traced process exited
+        pub(crate) fn exited(&mut self) {
+            self.no_exit();
+            if let Some(task) = self.task.take() {
+                task.live(false);
+            }
+        }
+
+        /// `restrict.syscall.verdicts` and, when the exit was seen,
+        /// `restrict.syscall.latency_seconds`
+        fn record(&self, pending: &Pending, exited: bool) {
+            let Some(task) = &self.task else {
+                return;
+            };
+            let syscall = pending.syscall.name();
+            let rule = pending
+                .rule
+                .map_or_else(|| "none".to_string(), |rule| rule.to_string());
+            crate::restrict_counter!("restrict.syscall.verdicts", 1,
+                "syscall" => syscall,
+                "verdict" => pending.verdict.to_string(),
+                "rule" => rule,
+                "pid" => task.pid.clone(),
+                "exe" => task.exe.clone());
+            if exited {
+                crate::restrict_histogram!("restrict.syscall.latency_seconds",
+                    pending.start.elapsed().as_secs_f64(),
+                    "syscall" => syscall,
+                    "pid" => task.pid.clone(),
+                    "exe" => task.exe.clone());
+            }
+        }
+    }
+}
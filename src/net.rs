@@ -0,0 +1,395 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    mem::size_of,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use libc::c_int;
+
+use crate::{
+    interceptor::Interceptor, memory, remap, restrict_counter, restrict_warn, syscall::Syscall,
+    Errno, SeccompError, TraceAction,
+};
+
+/// syscalls whose socket address is checked at their entry
+pub(crate) const SYSCALLS: &[Syscall] = &[
+    Syscall::Connect,
+    Syscall::Bind,
+    Syscall::Sendto,
+    Syscall::Sendmsg,
+    Syscall::Sendmmsg,
+];
+
+/// most messages `sendmmsg` sends in one call (`UIO_MAXIOV`)
+const MAX_MMSG: u64 = 1024;
+
+/// A socket address read from the tracee memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// `sockaddr_in` or `sockaddr_in6`
+    Ip(SocketAddr),
+    /// `sockaddr_un` with a path, empty for an unnamed socket
+    Unix(PathBuf),
+    /// `sockaddr_un` in the abstract namespace, without its leading NUL
+    Abstract(Vec<u8>),
+    /// any other address family
+    Other(c_int),
+}
+
+impl SocketAddress {
+    /// decode the `len` bytes long socket address at `addr` in the tracee memory
+    pub fn read(i: &Interceptor, addr: u64, len: usize) -> Result<Self, SeccompError> {
+        let len = len.min(size_of::<libc::sockaddr_storage>());
+        Ok(Self::from_bytes(&i.read_bytes(addr, len)?))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut storage = [0u8; size_of::<libc::sockaddr_storage>()];
+        storage[..bytes.len()].copy_from_slice(bytes);
+        let family: libc::sa_family_t = memory::from_bytes(&storage);
+        match family as c_int {
+            libc::AF_INET if bytes.len() >= size_of::<libc::sockaddr_in>() => {
+                let sin: libc::sockaddr_in = memory::from_bytes(&storage);
+                let ip = Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+                Self::Ip(SocketAddr::new(ip.into(), u16::from_be(sin.sin_port)))
+            }
+            libc::AF_INET6 if bytes.len() >= size_of::<libc::sockaddr_in6>() => {
+                let sin6: libc::sockaddr_in6 = memory::from_bytes(&storage);
+                let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+                Self::Ip(SocketAddr::new(ip.into(), u16::from_be(sin6.sin6_port)))
+            }
+            libc::AF_UNIX => {
+                let path = bytes.get(size_of::<libc::sa_family_t>()..).unwrap_or(&[]);
+                match path.split_first() {
+                    Some((0, name)) => Self::Abstract(name.to_vec()),
+                    _ => {
+                        let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+                        Self::Unix(Path::new(OsStr::from_bytes(&path[..end])).to_path_buf())
+                    }
+                }
+            }
+            family => Self::Other(family),
+        }
+    }
+}
+
+/// `10.0.0.1:5432`, `/run/app.sock` or `@abstract`
+impl fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(addr) => write!(f, "{addr}"),
+            Self::Unix(path) => write!(f, "{}", path.display()),
+            Self::Abstract(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+            Self::Other(family) => write!(f, "address family {family}"),
+        }
+    }
+}
+
+/// An IP network, eg. `"10.0.0.0/8".parse::<Cidr>()`, a bare address is a single host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// whether `ip` is in the network, IPv4-mapped IPv6 addresses match IPv4 networks
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = SeccompError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SeccompError::InvalidCidr(s.to_string());
+        let (network, prefix) = s.split_once('/').map_or((s, None), |(n, p)| (n, Some(p)));
+        let network: IpAddr = network.parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+
+/// `path` without its `.` and `..`, a relative one is taken from the cwd of the tracer
+fn rule_path(path: PathBuf) -> PathBuf {
+    match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => remap::normalize(&cwd.join(path)),
+        _ => remap::normalize(&path),
+    }
+}
+
+enum Rule {
+    Ip(Cidr, Option<u16>),
+    Unix(PathBuf),
+    Abstract(Vec<u8>),
+}
+
+impl Rule {
+    fn matches(&self, address: &SocketAddress) -> bool {
+        match (self, address) {
+            (Rule::Ip(cidr, port), SocketAddress::Ip(addr)) => {
+                cidr.contains(addr.ip()) && port.map_or(true, |port| port == addr.port())
+            }
+            (Rule::Unix(path), SocketAddress::Unix(addr)) => addr.starts_with(path),
+            (Rule::Abstract(name), SocketAddress::Abstract(addr)) => addr == name,
+            _ => false,
+        }
+    }
+}
+
+/// Allowed and denied socket addresses of `connect`, `bind`, `sendto`, `sendmsg` and
+/// `sendmmsg`, installed with `Policy::network_policy`
+///
+/// the rules are tried in the order they were added and the first matching one decides, the
+/// addresses no rule matches (eg. other address families) get the default. Denied connections
+/// and datagrams fail with `ECONNREFUSED`, denied binds and unix sockets with `EACCES`, and so
+/// do the addresses that can't be read or decoded. The address is read by the tracer before
+/// the kernel copies it, another thread of the process racing to rewrite it in between isn't
+/// stopped
+pub struct NetworkPolicy {
+    allow_by_default: bool,
+    rules: Vec<(Rule, bool)>,
+}
+
+impl NetworkPolicy {
+    /// allow the addresses no rule matches
+    pub fn allow_all() -> Self {
+        Self {
+            allow_by_default: true,
+            rules: Vec::new(),
+        }
+    }
+
+    /// deny the addresses no rule matches
+    pub fn deny_all() -> Self {
+        Self {
+            allow_by_default: false,
+            rules: Vec::new(),
+        }
+    }
+
+    /// allow the addresses of `cidr`, on `port` or on every port with `None`
+    pub fn allow_ip(mut self, cidr: Cidr, port: Option<u16>) -> Self {
+        self.rules.push((Rule::Ip(cidr, port), true));
+        self
+    }
+
+    /// deny the addresses of `cidr`, on `port` or on every port with `None`
+    pub fn deny_ip(mut self, cidr: Cidr, port: Option<u16>) -> Self {
+        self.rules.push((Rule::Ip(cidr, port), false));
+        self
+    }
+
+    /// allow the unix sockets at `path` or under it
+    ///
+    /// the `.` and `..` of `path` and of the socket addresses are resolved before matching,
+    /// without following symlinks. A relative `path` is taken from the cwd of the tracer
+    pub fn allow_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rules.push((Rule::Unix(rule_path(path.into())), true));
+        self
+    }
+
+    /// deny the unix sockets at `path` or under it, see `allow_unix`
+    pub fn deny_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rules.push((Rule::Unix(rule_path(path.into())), false));
+        self
+    }
+
+    /// allow the unix socket named `name` in the abstract namespace (without its leading NUL)
+    pub fn allow_abstract(mut self, name: impl Into<Vec<u8>>) -> Self {
+        self.rules.push((Rule::Abstract(name.into()), true));
+        self
+    }
+
+    /// deny the unix socket named `name` in the abstract namespace (without its leading NUL)
+    pub fn deny_abstract(mut self, name: impl Into<Vec<u8>>) -> Self {
+        self.rules.push((Rule::Abstract(name.into()), false));
+        self
+    }
+
+    /// whether `address` is allowed
+    pub fn allows(&self, address: &SocketAddress) -> bool {
+        self.rules
+            .iter()
+            .find(|(rule, _)| rule.matches(address))
+            .map_or(self.allow_by_default, |(_, allow)| *allow)
+    }
+
+    /// check the socket addresses of a syscall at its entry
+    pub(crate) fn check(&self, i: &Interceptor) -> TraceAction {
+        // an address we can't read can't be checked either
+        let Ok(addresses) = addresses(i) else {
+            return self.deny(i, None);
+        };
+        for (addr, len) in addresses {
+            // a connected socket sends without an address, it was checked at `connect`
+            if addr == 0 {
+                continue;
+            }
+            let Ok(mut address) = SocketAddress::read(i, addr, len as usize) else {
+                return self.deny(i, None);
+            };
+            if let SocketAddress::Unix(path) = &address {
+                if !path.as_os_str().is_empty() {
+                    // `..` can't climb out of an allowed directory
+                    let absolute = match path.is_relative() {
+                        true => match remap::directory(i.child_pid, None) {
+                            Ok(cwd) => cwd.join(path),
+                            Err(_) => return self.deny(i, None),
+                        },
+                        false => path.clone(),
+                    };
+                    address = SocketAddress::Unix(remap::normalize(&absolute));
+                }
+            }
+            // an IP address too short to decode can't be matched by the IP rules
+            if matches!(
+                address,
+                SocketAddress::Other(libc::AF_INET | libc::AF_INET6)
+            ) {
+                return self.deny(i, None);
+            }
+            if !self.allows(&address) {
+                return self.deny(i, Some(&address));
+            }
+        }
+        TraceAction::Continue
+    }
+
+    /// deny a syscall to `address`, `None` when it couldn't be read or decoded
+    fn deny(&self, i: &Interceptor, address: Option<&SocketAddress>) -> TraceAction {
+        restrict_warn!(
+            "denied {:?} to {}",
+            i.syscall,
+            address.map_or_else(|| "an unreadable address".to_string(), ToString::to_string)
+        );
+        restrict_counter!("restrict.net.denied", 1,
+                 "syscall_name" => format!("{:#?}", i.syscall));
+        let errno = match (i.syscall, address) {
+            (Syscall::Bind, _)
+            | (_, None | Some(SocketAddress::Unix(_) | SocketAddress::Abstract(_))) => libc::EACCES,
+            _ => libc::ECONNREFUSED,
+        };
+        TraceAction::Deny(Errno(errno))
+    }
+}
+
+/// the `(address, length)` of the socket addresses of a syscall in the tracee memory
+fn addresses(i: &Interceptor) -> Result<Vec<(u64, u64)>, SeccompError> {
+    Ok(match i.syscall {
+        Syscall::Sendto => vec![(i.registers.arg(4), i.registers.arg(5))],
+        Syscall::Sendmsg => vec![msg_name(i, i.registers.arg(1))?],
+        // `mmsghdr` is a `msghdr` followed by the length sent, every message is checked
+        Syscall::Sendmmsg => {
+            let (msgvec, vlen) = (i.registers.arg(1), i.registers.arg(2).min(MAX_MMSG));
+            (0..vlen)
+                .map(|n| msg_name(i, msgvec + n * size_of::<libc::mmsghdr>() as u64))
+                .collect::<Result<_, _>>()?
+        }
+        _ => vec![(i.registers.arg(1), i.registers.arg(2))],
+    })
+}
+
+/// the `msg_name` and `msg_namelen` of the `msghdr` at `msg`, `msghdr` has padding and isn't
+/// `Plain` so they are read on their own
+fn msg_name(i: &Interceptor, msg: u64) -> Result<(u64, u64), SeccompError> {
+    let name = i.read_struct::<u64>(msg)?;
+    let len = i.read_struct::<u32>(msg + 8)?;
+    Ok((name, len as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(addr: &str) -> SocketAddress {
+        SocketAddress::Ip(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn cidr_blocks() {
+        let net: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+
+        let host: Cidr = "::1".parse().unwrap();
+        assert!(host.contains("::1".parse().unwrap()));
+        assert!(!host.contains("::2".parse().unwrap()));
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains("1.2.3.4".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("example.com".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let policy = NetworkPolicy::deny_all()
+            .deny_ip("10.0.0.1".parse().unwrap(), None)
+            .allow_ip("10.0.0.0/8".parse().unwrap(), Some(5432))
+            .allow_unix("/run/app");
+        assert!(policy.allows(&ip("10.2.0.1:5432")));
+        assert!(!policy.allows(&ip("10.0.0.1:5432")));
+        assert!(!policy.allows(&ip("10.2.0.1:80")));
+        assert!(policy.allows(&SocketAddress::Unix("/run/app/db.sock".into())));
+        assert!(!policy.allows(&SocketAddress::Unix("/run/other.sock".into())));
+        assert!(!policy.allows(&SocketAddress::Abstract(b"app".to_vec())));
+        assert!(!policy.allows(&SocketAddress::Other(libc::AF_NETLINK)));
+
+        let policy = NetworkPolicy::allow_all().deny_abstract("app");
+        assert!(!policy.allows(&SocketAddress::Abstract(b"app".to_vec())));
+        assert!(policy.allows(&SocketAddress::Abstract(b"app2".to_vec())));
+        let policy = NetworkPolicy::deny_all().allow_abstract("app");
+        assert!(policy.allows(&SocketAddress::Abstract(b"app".to_vec())));
+
+        // the rule paths lose their `.` and `..` like the addresses
+        let policy = NetworkPolicy::deny_all().allow_unix("/run/./tmp/../app/");
+        assert!(policy.allows(&SocketAddress::Unix("/run/app/db.sock".into())));
+    }
+
+    #[test]
+    fn decode_addresses() {
+        let sin = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: 5432u16.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(Ipv4Addr::new(10, 0, 0, 1)).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let address = SocketAddress::from_bytes(memory::as_bytes(&sin));
+        assert_eq!(address, ip("10.0.0.1:5432"));
+        assert_eq!(address.to_string(), "10.0.0.1:5432");
+
+        let mut unix = (libc::AF_UNIX as libc::sa_family_t).to_ne_bytes().to_vec();
+        unix.extend(b"\0abstract");
+        assert_eq!(SocketAddress::from_bytes(&unix).to_string(), "@abstract");
+    }
+}
@@ -1,28 +1,69 @@
-pub use crate::{error::SeccompError, syscall::Syscall, wrapper::Action};
 use crate::{
+    audit::{AuditSink, Auditor},
+    clock::{self, VirtualClock},
+    exec::{self, ExecPolicy},
+    fault::FaultInjector,
+    fd::{self, FdTable},
     filter::{
         intercept::{InterceptorFilter, InterceptorMap},
         seccomp::{self, SeccompFilter},
         tracer::{TracerFilter, TracerMap},
         RestrictFilter,
     },
+    format::PrettyTracer,
     interceptor::Interceptor,
+    limits::{self, Limits},
+    net::{self, NetworkPolicy},
+    quota::{OnExceeded, Quota, TokenBucket},
+    random::{self, SeededRandom},
+    remap::{self, PathRemap},
     restrict_counter, restrict_info,
+    stream::{Backpressure, EventPublisher, EventStream},
     tracer::TracingHandle,
-    wrapper::{PtraceWrapper, SeccompWrapper, TraceAction},
+    wrapper::{
+        ExecHandler, Handlers, Observers, OnError, Outcome, PtraceWrapper, SeccompWrapper,
+        SignalAction, SignalHandler, TraceAction,
+    },
 };
-use libc::{raise, SIGSTOP};
+pub use crate::{error::SeccompError, syscall::Syscall, wrapper::Action};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use libc::{c_int, raise, SIGKILL, SIGSTOP};
 
+#[cfg(feature = "metrics")]
+use crate::metrics::RuntimeMetrics;
 #[cfg(feature = "logging")]
 use tracing_subscriber::fmt;
 
 /// Restrict policy
-pub struct Policy {
+///
+/// `C` is a user context handed as `&mut C` to the `*_with` handlers, see `with_context`
+pub struct Policy<C = ()> {
     context: Option<SeccompWrapper>,
     pub(crate) seccomp_rules: Vec<SeccompFilter>,
-    pub(crate) trace_rules: Vec<TracerFilter>,
-    pub(crate) pre_intercept: Vec<InterceptorFilter>,
-    pub(crate) post_intercept: Vec<InterceptorFilter>,
+    pub(crate) trace_rules: Vec<TracerFilter<C>>,
+    pub(crate) pre_intercept: Vec<InterceptorFilter<C>>,
+    pub(crate) post_intercept: Vec<InterceptorFilter<C>>,
+    signal_handlers: Vec<(c_int, SignalHandler<C>)>,
+    pretty: Option<PrettyTracer>,
+    on_error: OnError,
+    limits: Limits,
+    exec_handlers: Vec<ExecHandler>,
+    path_remap: Option<Rc<RefCell<PathRemap>>>,
+    fd_table: Option<Rc<RefCell<FdTable>>>,
+    /// `trace`/`*_intercept`/`*_any` registrations so far
+    rules: usize,
+    audit: Option<Box<dyn AuditSink>>,
+    events: Option<EventPublisher>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<RuntimeMetrics>,
+    user_context: C,
     trace: bool,
     verbose: bool,
 }
@@ -37,6 +78,19 @@ impl Policy {
             trace_rules: Vec::new(),
             pre_intercept: Vec::new(),
             post_intercept: Vec::new(),
+            signal_handlers: Vec::new(),
+            pretty: None,
+            on_error: OnError::default(),
+            limits: Limits::default(),
+            exec_handlers: Vec::new(),
+            path_remap: None,
+            fd_table: None,
+            rules: 0,
+            audit: None,
+            events: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            user_context: (),
             trace: false,
             verbose: false,
         })
@@ -54,6 +108,101 @@ impl Policy {
         Self::new(Action::Kill)
     }
 
+    /// attach a context that every `*_with` handler gets as `&mut C`, `run` hands it back
+    /// once the tracee exited (eg. counters or collected paths)
+    ///
+    /// the handlers registered before keep working, they just don't see the context
+    pub fn with_context<C: 'static>(self, user_context: C) -> Policy<C> {
+        Policy {
+            context: self.context,
+            seccomp_rules: self.seccomp_rules,
+            trace_rules: self
+                .trace_rules
+                .into_iter()
+                .map(|filter| {
+                    filter.map_callback(|mut handler| {
+                        Box::new(move |sc, _: &mut C| handler(sc, &mut ()))
+                    })
+                })
+                .collect(),
+            pre_intercept: without_context(self.pre_intercept),
+            post_intercept: without_context(self.post_intercept),
+            signal_handlers: self
+                .signal_handlers
+                .into_iter()
+                .map(|(signal, mut handler)| {
+                    let handler: SignalHandler<C> =
+                        Box::new(move |sig, _: &mut C| handler(sig, &mut ()));
+                    (signal, handler)
+                })
+                .collect(),
+            pretty: self.pretty,
+            on_error: self.on_error,
+            limits: self.limits,
+            exec_handlers: self.exec_handlers,
+            path_remap: self.path_remap,
+            fd_table: self.fd_table,
+            rules: self.rules,
+            audit: self.audit,
+            events: self.events,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics,
+            user_context,
+            trace: self.trace,
+            verbose: self.verbose,
+        }
+    }
+}
+
+/// The side of the fork `install` returns in
+enum Installed {
+    /// the restricted process, also when nothing is traced and no fork happened
+    Tracee,
+    /// the tracer once the tracee exited, with how it ended
+    Tracer(Result<Outcome, SeccompError>),
+}
+
+/// wrap interceptors written for a `Policy<()>` so they ignore the context of a `Policy<C>`
+fn without_context<C: 'static>(filters: Vec<InterceptorFilter<()>>) -> Vec<InterceptorFilter<C>> {
+    filters
+        .into_iter()
+        .map(|filter| {
+            filter.map_callback(|mut handler| Box::new(move |i, _: &mut C| handler(i, &mut ())))
+        })
+        .collect()
+}
+
+impl<C: 'static> Policy<C> {
+    /// id of the next handler registration, see `AuditRecord::rule`
+    fn next_rule(&mut self) -> usize {
+        self.rules += 1;
+        self.rules - 1
+    }
+
+    /// intercept `syscall` at its entry, or at its exit with `at_exit`, for a feature of the
+    /// policy registered as `rule`
+    ///
+    /// unlike `entry_intercept` the syscall is only traced when seccomp allows it, see
+    /// `track_fds`
+    fn feature_intercept<T>(
+        &mut self,
+        syscall: Syscall,
+        at_exit: bool,
+        rule: usize,
+        mut interceptor: T,
+    ) where
+        T: FnMut(Interceptor) -> TraceAction + 'static,
+    {
+        let filter = InterceptorFilter::new(syscall, move |i, _: &mut C| interceptor(i))
+            .feature()
+            .rule(rule);
+        match at_exit {
+            true => self.post_intercept.push(filter),
+            false => self.pre_intercept.push(filter),
+        }
+        self.trace = true;
+    }
+
     /// Syscall fail with a custom error no
     pub fn fail_with(&mut self, syscall: Syscall, errno: u16) -> &mut Self {
         restrict_counter!("restrict.policy.rule.fail", 1,
@@ -66,45 +215,421 @@ impl Policy {
     }
 
     /// tracing syscalls
-    pub fn trace<T>(&mut self, syscall: Syscall, tracer: T) -> &mut Self
+    pub fn trace<T>(&mut self, syscall: Syscall, mut tracer: T) -> &mut Self
     where
-        T: Fn(Syscall) -> TraceAction + 'static,
+        T: FnMut(Syscall) -> TraceAction + 'static,
+    {
+        self.trace_with(syscall, move |sc, _| tracer(sc))
+    }
+
+    /// tracing syscalls, the handler also gets the policy context
+    pub fn trace_with<T>(&mut self, syscall: Syscall, tracer: T) -> &mut Self
+    where
+        T: FnMut(Syscall, &mut C) -> TraceAction + 'static,
     {
         restrict_counter!("restrict.policy.rule.trace", 1,
                  "syscall_name" => format!("{:#?}",syscall));
         restrict_info!("Trace syscall: {syscall:?}");
-        self.trace_rules.push(TracerFilter::new(syscall, tracer));
+        let rule = self.next_rule();
+        self.trace_rules
+            .push(TracerFilter::new(syscall, tracer).rule(rule));
         self.trace = true;
         self
     }
 
     /// Intercept syscalls and modify their registers at entry and at entry
-    pub fn entry_intercept<T>(&mut self, syscall: Syscall, interceptor: T) -> &mut Self
+    pub fn entry_intercept<T>(&mut self, syscall: Syscall, mut interceptor: T) -> &mut Self
+    where
+        T: FnMut(Interceptor) -> TraceAction + 'static,
+    {
+        self.entry_intercept_with(syscall, move |i, _| interceptor(i))
+    }
+
+    /// Intercept syscalls at entry, the handler also gets the policy context
+    pub fn entry_intercept_with<T>(&mut self, syscall: Syscall, interceptor: T) -> &mut Self
     where
-        T: Fn(Interceptor) -> TraceAction + 'static,
+        T: FnMut(Interceptor, &mut C) -> TraceAction + 'static,
     {
         restrict_counter!("restrict.policy.rule.entry_intercept", 1,
                  "syscall_name" => format!("{:#?}",syscall));
         restrict_info!("intercept syscall: {syscall:?} at entry");
+        let rule = self.next_rule();
         self.pre_intercept
-            .push(InterceptorFilter::new(syscall, interceptor));
+            .push(InterceptorFilter::new(syscall, interceptor).rule(rule));
         self.trace = true;
         self
     }
 
     /// Intercept syscall at the exit to modify their return register
-    pub fn exit_intercept<T>(&mut self, syscall: Syscall, interceptor: T) -> &mut Self
+    pub fn exit_intercept<T>(&mut self, syscall: Syscall, mut interceptor: T) -> &mut Self
+    where
+        T: FnMut(Interceptor) -> TraceAction + 'static,
+    {
+        self.exit_intercept_with(syscall, move |i, _| interceptor(i))
+    }
+
+    /// Intercept syscalls at exit, the handler also gets the policy context
+    pub fn exit_intercept_with<T>(&mut self, syscall: Syscall, interceptor: T) -> &mut Self
     where
-        T: Fn(Interceptor) -> TraceAction + 'static,
+        T: FnMut(Interceptor, &mut C) -> TraceAction + 'static,
     {
         restrict_counter!("restrict.policy.rule.exit_intercept", 1,
                  "syscall_name" => format!("{:#?}",syscall));
         restrict_info!("Intercept syscall: {syscall:?} at exit");
+        let rule = self.next_rule();
         self.post_intercept
-            .push(InterceptorFilter::new(syscall, interceptor));
+            .push(InterceptorFilter::new(syscall, interceptor).rule(rule));
+        self.trace = true;
+        self
+    }
+
+    /// trace every syscall matching `predicate` with the same handler
+    ///
+    /// handlers run in registration order, mixed with the ones of `trace` for the same
+    /// syscall. Syscalls with an explicit `allow`/`deny`/`fail_with` rule keep it, the other
+    /// matching syscalls are traced instead of getting the default action
+    pub fn trace_any<P, T>(&mut self, predicate: P, mut tracer: T) -> &mut Self
+    where
+        P: Fn(Syscall) -> bool,
+        T: FnMut(Syscall) -> TraceAction + 'static,
+    {
+        self.trace_any_with(predicate, move |sc, _| tracer(sc))
+    }
+
+    /// `trace_any`, the handler also gets the policy context
+    pub fn trace_any_with<P, T>(&mut self, predicate: P, tracer: T) -> &mut Self
+    where
+        P: Fn(Syscall) -> bool,
+        T: FnMut(Syscall, &mut C) -> TraceAction + 'static,
+    {
+        restrict_counter!("restrict.policy.rule.trace_any", 1);
+        // one handler shared by the chain of every matching syscall
+        let tracer = Rc::new(RefCell::new(tracer));
+        let rule = self.next_rule();
+        for syscall in Syscall::ALL.iter().copied().filter(|sc| predicate(*sc)) {
+            let tracer = Rc::clone(&tracer);
+            self.trace_rules.push(
+                TracerFilter::new(syscall, move |sc, ctx: &mut C| {
+                    (tracer.borrow_mut())(sc, ctx)
+                })
+                .wildcard()
+                .rule(rule),
+            );
+        }
+        self.trace = true;
+        self
+    }
+
+    /// intercept every syscall matching `predicate` at entry with the same handler,
+    /// see `trace_any`
+    pub fn intercept_any<P, T>(&mut self, predicate: P, mut interceptor: T) -> &mut Self
+    where
+        P: Fn(Syscall) -> bool,
+        T: FnMut(Interceptor) -> TraceAction + 'static,
+    {
+        self.intercept_any_with(predicate, move |i, _| interceptor(i))
+    }
+
+    /// `intercept_any`, the handler also gets the policy context
+    pub fn intercept_any_with<P, T>(&mut self, predicate: P, interceptor: T) -> &mut Self
+    where
+        P: Fn(Syscall) -> bool,
+        T: FnMut(Interceptor, &mut C) -> TraceAction + 'static,
+    {
+        restrict_counter!("restrict.policy.rule.intercept_any", 1);
+        let interceptor = Rc::new(RefCell::new(interceptor));
+        let rule = self.next_rule();
+        for syscall in Syscall::ALL.iter().copied().filter(|sc| predicate(*sc)) {
+            let interceptor = Rc::clone(&interceptor);
+            self.pre_intercept.push(
+                InterceptorFilter::new(syscall, move |i, ctx: &mut C| {
+                    (interceptor.borrow_mut())(i, ctx)
+                })
+                .wildcard()
+                .rule(rule),
+            );
+        }
+        self.trace = true;
+        self
+    }
+
+    /// print every syscall of the traced process to `writer` the way strace does,
+    /// eg. `openat(AT_FDCWD, "/etc/passwd", O_RDONLY|O_CLOEXEC) = 3`
+    ///
+    /// this only observes the syscalls, the other rules apply as usual
+    pub fn trace_all_pretty<W>(&mut self, writer: W) -> &mut Self
+    where
+        W: std::io::Write + 'static,
+    {
+        restrict_counter!("restrict.policy.rule.trace_all_pretty", 1);
+        restrict_info!("Trace all syscalls");
+        self.pretty = Some(PrettyTracer::new(writer));
+        self.trace = true;
+        self
+    }
+
+    /// write a record of every syscall reaching the handlers of the policy (the ones with a
+    /// `trace`, `*_intercept` or `*_any` handler) to `sink`: its decoded arguments, the verdict
+    /// of the handlers and the rule that decided it, its return value and duration
+    ///
+    /// eg. `audit(RotatingFileSink::new("audit.jsonl", 10 << 20, 5)?)`, the syscalls allowed or
+    /// denied by seccomp alone never reach the tracer and aren't recorded
+    pub fn audit(&mut self, sink: impl AuditSink + 'static) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.audit", 1);
+        self.audit = Some(Box::new(sink));
+        self
+    }
+
+    /// publish an event at the entry and at the exit of every syscall of the traced process
+    /// to a channel holding up to `capacity` events, consumed by iterating the returned
+    /// `EventStream` from another thread
+    ///
+    /// with `Backpressure::Block` the traced process waits while the channel is full, with
+    /// `DropNewest` the events that don't fit are lost. Like `trace_all_pretty` this only
+    /// observes the syscalls, the other rules apply as usual
+    pub fn event_stream(&mut self, capacity: usize, backpressure: Backpressure) -> EventStream {
+        restrict_counter!("restrict.policy.rule.event_stream", 1);
+        let (publisher, stream) = EventPublisher::channel(capacity, backpressure);
+        self.events = Some(publisher);
+        self.trace = true;
+        stream
+    }
+
+    /// measure the syscalls reaching the handlers of the policy (all of them with
+    /// `trace_all_pretty` or `event_stream`): `restrict.syscall.latency_seconds` histograms of
+    /// their entry to exit time, `restrict.syscall.verdicts` counters by verdict and rule and
+    /// the `restrict.tasks.live` gauge, labelled by pid and executable
+    #[cfg(feature = "metrics")]
+    pub fn runtime_metrics(&mut self) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.runtime_metrics", 1);
+        self.metrics = Some(RuntimeMetrics::default());
+        self
+    }
+
+    /// run `handler` before `signal` is delivered to the traced process, it can let the
+    /// signal through, suppress it or deliver another signal instead
+    ///
+    /// handlers of the same signal run in registration order until one doesn't `Deliver`
+    pub fn on_signal<T>(&mut self, signal: c_int, mut handler: T) -> &mut Self
+    where
+        T: FnMut(c_int) -> SignalAction + 'static,
+    {
+        self.on_signal_with(signal, move |sig, _| handler(sig))
+    }
+
+    /// `on_signal`, the handler also gets the policy context
+    pub fn on_signal_with<T>(&mut self, signal: c_int, handler: T) -> &mut Self
+    where
+        T: FnMut(c_int, &mut C) -> SignalAction + 'static,
+    {
+        restrict_counter!("restrict.policy.rule.on_signal", 1,
+                 "signal" => signal.to_string());
+        self.signal_handlers.push((signal, Box::new(handler)));
+        self.trace = true;
+        self
+    }
+
+    /// allow at most `max` calls of `syscall`, the next ones get `on_exceeded`
+    ///
+    /// the quota is a `trace` handler, it counts the calls that reach it in the handler chain
+    pub fn quota(&mut self, syscall: Syscall, max: u64, on_exceeded: OnExceeded) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.quota", 1,
+                 "syscall_name" => format!("{:#?}",syscall),
+                 "max" => max.to_string());
+        let mut quota = Quota::new(max, on_exceeded);
+        self.trace(syscall, move |sc| quota.check(sc))
+    }
+
+    /// allow at most `calls` calls of `syscall` every `per` (a token bucket, so bursts up to
+    /// `calls` are allowed), the calls over the rate get `on_exceeded`
+    ///
+    /// eg. `rate_limit(Syscall::Openat, 50, Duration::from_secs(1), OnExceeded::Fail(Errno(libc::EAGAIN)))`
+    pub fn rate_limit(
+        &mut self,
+        syscall: Syscall,
+        calls: u32,
+        per: Duration,
+        on_exceeded: OnExceeded,
+    ) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.rate_limit", 1,
+                 "syscall_name" => format!("{:#?}",syscall),
+                 "calls" => calls.to_string());
+        let mut bucket = TokenBucket::new(calls, per, on_exceeded);
+        self.trace(syscall, move |sc| bucket.check(sc, Instant::now()))
+    }
+
+    /// inject the faults of `faults` into the restricted process, keep `faults.report()` to see
+    /// which calls were faulted
+    pub fn inject_faults(&mut self, faults: FaultInjector) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.inject_faults", 1);
+        let rule = self.next_rule();
+        for (syscall, interceptor) in faults.into_interceptors() {
+            self.feature_intercept(syscall, false, rule, interceptor);
+        }
+        self
+    }
+
+    /// make the restricted process see the time of `clock` instead of the real one, the
+    /// programs it execs lose their vDSO so every clock read is a syscall
+    pub fn virtual_clock(&mut self, clock: VirtualClock) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.virtual_clock", 1);
+        let clock = Rc::new(RefCell::new(clock));
+        let rule = self.next_rule();
+        for syscall in clock::ENTRY_SYSCALLS {
+            let clock = Rc::clone(&clock);
+            self.feature_intercept(*syscall, false, rule, move |mut i| {
+                clock.borrow_mut().entry(&mut i)
+            });
+        }
+        for syscall in clock::EXIT_SYSCALLS {
+            let clock = Rc::clone(&clock);
+            self.feature_intercept(*syscall, true, rule, move |mut i| {
+                clock.borrow_mut().exit(&mut i)
+            });
+        }
+        self.exec_handlers.push(Box::new(PtraceWrapper::hide_vdso));
+        self
+    }
+
+    /// make every random byte of the restricted process come from a stream seeded with
+    /// `seed`: `getrandom` and the reads of `/dev/urandom` and `/dev/random`, so two runs
+    /// with the same seed see the same bytes
+    pub fn deterministic_random(&mut self, seed: u64) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.deterministic_random", 1);
+        let random = Rc::new(RefCell::new(SeededRandom::new(seed)));
+        let rule = self.next_rule();
+        for syscall in random::SYSCALLS {
+            let random = Rc::clone(&random);
+            self.feature_intercept(*syscall, true, rule, move |mut i| {
+                random.borrow_mut().exit(&mut i)
+            });
+        }
+        self.exec_handlers
+            .push(Box::new(move |tracee| random.borrow_mut().exec(tracee)));
+        self
+    }
+
+    /// keep a table of the fds of the restricted process, updated at the exit of the syscalls
+    /// that open, copy and close them and at exec, so `Interceptor::fd_info` knows what an fd
+    /// is (with the address of the sockets) without reading `/proc` every time
+    ///
+    /// the table is updated before the other `exit_intercept` handlers run. Like every feature
+    /// of the policy it only sees the syscalls seccomp allows, an explicit `deny` or
+    /// `fail_with` keeps its syscall from it
+    pub fn track_fds(&mut self) -> &mut Self {
+        if self.fd_table.is_some() {
+            return self;
+        }
+        restrict_counter!("restrict.policy.rule.track_fds", 1);
+        let table = Rc::new(RefCell::new(FdTable::default()));
+        let rule = self.next_rule();
+        let updates = fd::syscalls().map(|syscall| {
+            let table = Rc::clone(&table);
+            InterceptorFilter::new(syscall, move |i: Interceptor, _: &mut C| {
+                table.borrow_mut().syscall_exit(&i);
+                TraceAction::Continue
+            })
+            .feature()
+            .rule(rule)
+        });
+        self.post_intercept.splice(0..0, updates);
+        let exec_table = Rc::clone(&table);
+        self.exec_handlers.push(Box::new(move |tracee| {
+            exec_table.borrow_mut().exec(tracee.get_process().get_pid());
+            Ok(())
+        }));
+        self.fd_table = Some(table);
+        self.trace = true;
+        self
+    }
+
+    /// redirect the absolute path `from` and everything under it to `to` in the file syscalls
+    /// of the restricted process, a bind mount without root or mount namespaces
+    ///
+    /// eg. `remap_path("/etc/resolv.conf", "tests/fixtures/resolv.conf")`, relative paths of
+    /// the tracee are resolved from its cwd or directory fd and the longest `from` wins
+    pub fn remap_path(&mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.remap_path", 1);
+        let remap = match &self.path_remap {
+            Some(remap) => Rc::clone(remap),
+            None => {
+                let remap = Rc::new(RefCell::new(PathRemap::default()));
+                let rule = self.next_rule();
+                for syscall in remap::syscalls() {
+                    let remap = Rc::clone(&remap);
+                    self.feature_intercept(syscall, false, rule, move |mut i| {
+                        remap.borrow().entry(&mut i)
+                    });
+                }
+                self.path_remap.insert(remap).clone()
+            }
+        };
+        remap.borrow_mut().add(from.into(), to.into());
+        self
+    }
+
+    /// check the socket addresses of `connect`, `bind`, `sendto`, `sendmsg` and `sendmmsg`
+    /// against `network`, the denied ones fail with `ECONNREFUSED` or `EACCES`
+    ///
+    /// eg. `network_policy(NetworkPolicy::deny_all().allow_ip("10.0.0.0/8".parse()?, Some(5432)))`
+    pub fn network_policy(&mut self, network: NetworkPolicy) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.network_policy", 1);
+        let network = Rc::new(network);
+        let rule = self.next_rule();
+        for syscall in net::SYSCALLS {
+            let network = Rc::clone(&network);
+            self.feature_intercept(*syscall, false, rule, move |i| network.check(&i));
+        }
+        self
+    }
+
+    /// only let the restricted process exec the binaries allowed by `exec`, the others fail
+    /// with `EACCES` instead of killing it
+    ///
+    /// eg. `exec_policy(ExecPolicy::new().allow_path("/usr/bin/git")?.strip_dangerous_env())`
+    pub fn exec_policy(&mut self, exec: ExecPolicy) -> &mut Self {
+        restrict_counter!("restrict.policy.rule.exec_policy", 1);
+        let exec = Rc::new(exec);
+        let rule = self.next_rule();
+        for syscall in exec::SYSCALLS {
+            let exec = Rc::clone(&exec);
+            self.feature_intercept(*syscall, false, rule, move |mut i| exec.check(&mut i));
+        }
+        self
+    }
+
+    /// kill the restricted process with `SIGKILL` once it ran for `limit` of wall-clock time,
+    /// `run_with_outcome` then reports `Outcome::TimeLimit`
+    ///
+    /// the tracer enforces it, so a tracer is forked even without tracing rules
+    pub fn time_limit(&mut self, limit: Duration) -> &mut Self {
+        restrict_counter!("restrict.policy.limit.time", 1);
+        self.limits.time = Some(limit);
         self.trace = true;
         self
     }
+
+    /// limit the CPU time of the restricted process with `RLIMIT_CPU`, it gets `SIGXCPU`
+    /// once it used `limit` (rounded up to whole seconds) and `SIGKILL` one second later,
+    /// `run_with_outcome` then reports `Outcome::CpuLimit`
+    ///
+    /// without a tracer the CPU time the process used before `apply` counts too
+    pub fn cpu_limit(&mut self, limit: Duration) -> &mut Self {
+        restrict_counter!("restrict.policy.limit.cpu", 1);
+        self.limits.cpu = Some(limit);
+        self
+    }
+
+    /// what the tracer does when it fails to handle a stop of the tracee, kills it by default
+    ///
+    /// with `OnError::Kill` and `OnError::Detach` the error is returned by `run` once the
+    /// tracee exited, `apply` exits with 1
+    pub fn on_error(&mut self, on_error: OnError) -> &mut Self {
+        restrict_counter!("restrict.policy.on_error", 1,
+                 "action" => format!("{:?}", on_error));
+        self.on_error = on_error;
+        self
+    }
     /// allow a syscall
     pub fn allow(&mut self, syscall: Syscall) -> &mut Self {
         restrict_counter!("restrict.policy.rule.allow", 1,
@@ -133,12 +658,74 @@ impl Policy {
     //}
     /// apply
     pub fn apply(&mut self) -> Result<(), SeccompError> {
+        if let Installed::Tracer(result) = self.install()? {
+            restrict_counter!("restrict.policy.action.parent.exit", 1);
+            // the tracer must not go on running the code meant for the restricted process
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(err) => {
+                    eprintln!("restrict: {err}");
+                    std::process::exit(1);
+                }
+            };
+            // if a handler or a limit killed the child the parent is killed too
+            // todo(z0rw): exit gracefully
+            match outcome {
+                Outcome::PolicyKill
+                | Outcome::TimeLimit
+                | Outcome::CpuLimit
+                | Outcome::Signaled(SIGKILL) => std::process::exit(SIGKILL),
+                _ => std::process::exit(0),
+            }
+        }
+        Ok(())
+    }
+
+    /// run `tracee` in a child process restricted by this policy and return the context
+    /// once it exited, so the state collected by the handlers can be inspected
+    ///
+    /// unlike `apply` the calling process itself stays unrestricted, it only runs the handlers
+    pub fn run<F: FnOnce()>(self, tracee: F) -> Result<C, SeccompError> {
+        self.run_with_outcome(tracee).map(|(context, _)| context)
+    }
+
+    /// `run`, also returning how the child process ended (eg. `Outcome::TimeLimit`)
+    pub fn run_with_outcome<F: FnOnce()>(
+        mut self,
+        tracee: F,
+    ) -> Result<(C, Outcome), SeccompError> {
+        // the handlers run in the parent, so fork even when nothing is traced
+        self.trace = true;
+        match self.install()? {
+            Installed::Tracer(result) => result.map(|outcome| (self.user_context, outcome)),
+            Installed::Tracee => {
+                tracee();
+                std::process::exit(0);
+            }
+        }
+    }
+
+    /// load the rules, forking a tracer first when some rule needs one
+    fn install(&mut self) -> Result<Installed, SeccompError> {
         let mut context = self.context.take().ok_or(SeccompError::Fork)?;
         // in bpf the order of filters is important
         // but we shouldn't care because we ensure no conflicts happen
 
+        // the explicit `allow` of a syscall a feature intercepts is replaced by its TRACE rule,
+        // so the feature still sees it
+        let featured: HashSet<Syscall> = self
+            .pre_intercept
+            .iter()
+            .chain(self.post_intercept.iter())
+            .filter(|f| f.is_feature())
+            .map(|f| f.syscall())
+            .collect();
+
         // apply seccomp rules
         for filter in self.seccomp_rules.iter() {
+            if filter.action() == Action::Allow && featured.contains(&filter.syscall()) {
+                continue;
+            }
             restrict_info!(format!(
                 "Applying {:?} filter for {:?}",
                 filter.action(),
@@ -160,29 +747,51 @@ impl Policy {
             restrict_info!("[+] Forking the current process");
 
             restrict_counter!("restrict.policy.action.fork", 1);
-            let spawned = self.spawn_traced().unwrap();
+            let spawned = self.spawn_traced()?;
             match spawned {
                 TracingHandle::Child => {
                     // apply seccomp TRACE rule specificallt
-                    for filter in self.trace_rules.iter() {
-                        restrict_info!(format!(
-                            "[+] Applying Traceing filter for {:?}",
-                            filter.syscall()
-                        ));
-                        filter.apply(&mut context)?;
-                    }
-                    for filter in self.post_intercept.iter() {
-                        restrict_info!(format!(
-                            "[+] Applying post Intercept filter for {:?}",
-                            filter.syscall()
-                        ));
-                        filter.apply(&mut context)?;
-                    }
-                    for filter in self.pre_intercept.iter() {
-                        restrict_info!(format!(
-                            "[+] Applying Pre Intercept filter for {:?}",
-                            filter.syscall()
-                        ));
+                    // a syscall with several handlers gets a single rule, the syscalls only
+                    // matched by a `*_any` predicate keep their explicit seccomp rule and the
+                    // ones of a feature are only traced when seccomp allows them
+                    let explicit: HashMap<Syscall, Action> = self
+                        .seccomp_rules
+                        .iter()
+                        .map(|f| (f.syscall(), f.action()))
+                        .collect();
+                    let default = context.default_action;
+                    let allowed = |syscall| {
+                        explicit.get(&syscall).copied().unwrap_or(default) == Action::Allow
+                    };
+                    let mut traced = HashSet::new();
+                    let filters = self
+                        .trace_rules
+                        .iter()
+                        .map(|f| {
+                            (
+                                f.syscall(),
+                                f.is_wildcard(),
+                                false,
+                                f as &dyn RestrictFilter,
+                            )
+                        })
+                        .chain(
+                            self.post_intercept
+                                .iter()
+                                .chain(self.pre_intercept.iter())
+                                .map(|f| {
+                                    let filter = f as &dyn RestrictFilter;
+                                    (f.syscall(), f.is_wildcard(), f.is_feature(), filter)
+                                }),
+                        );
+                    for (syscall, wildcard, feature, filter) in filters {
+                        if (wildcard && explicit.contains_key(&syscall))
+                            || (feature && !allowed(syscall))
+                            || !traced.insert(syscall)
+                        {
+                            continue;
+                        }
+                        restrict_info!(format!("[+] Applying Traceing filter for {:?}", syscall));
                         filter.apply(&mut context)?;
                     }
                     restrict_info!("[Child-process]: tracing is enabled(PTRACE_TRACEME)");
@@ -199,6 +808,9 @@ impl Policy {
                     restrict_counter!("restrict.policy.action.child_process.sigstop", 1);
                     // After this point the parent and the child are in sync so we
                     // load the accumulated filters and start tracing
+                    if let Some(limit) = self.limits.cpu {
+                        limits::set_cpu_limit(limit)?;
+                    }
                     context.load()?;
 
                     restrict_info!("[Child-process]: Synced, LOADING filters succeded");
@@ -213,8 +825,15 @@ impl Policy {
 
                     restrict_info!("[Parent-process]: Waiting for sync signal");
                     PtraceWrapper::with_pid(child_pid).wait_for_signal(SIGSTOP)?;
-                    PtraceWrapper::with_pid(child_pid).set_traceseccomp_option()?;
-                    PtraceWrapper::with_pid(child_pid).syscall_trace()?;
+                    PtraceWrapper::with_pid(child_pid).seize()?;
+                    let observers = Observers {
+                        pretty: self.pretty.take(),
+                        audit: self.audit.take().map(Auditor::new),
+                        events: self.events.take(),
+                        #[cfg(feature = "metrics")]
+                        metrics: self.metrics.take(),
+                    };
+                    PtraceWrapper::with_pid(child_pid).resume(observers.trace_all())?;
 
                     restrict_counter!("restrict.policy.action.install_seccompfilters", 1);
 
@@ -225,27 +844,35 @@ impl Policy {
                     let intercept_r = std::mem::take(&mut self.pre_intercept);
                     let post_intercept_r = std::mem::take(&mut self.post_intercept);
 
-                    let mapped_tracers = TracerMap::from(trace_r);
-                    let mapped_intercepters = InterceptorMap::from(intercept_r);
-                    let mapped_post_intercepters = InterceptorMap::from(post_intercept_r);
+                    let handlers = Handlers {
+                        tracers: TracerMap::from(trace_r),
+                        entry: InterceptorMap::from(intercept_r),
+                        exit: InterceptorMap::from(post_intercept_r),
+                        signals: std::mem::take(&mut self.signal_handlers),
+                        exec: std::mem::take(&mut self.exec_handlers),
+                        fds: self.fd_table.take(),
+                    };
 
                     restrict_info!("[Parent-process]: Listening to incoming syscalls from child process: {child_pid}");
-                    PtraceWrapper::with_pid(child_pid).event_loop(
-                        mapped_tracers,
-                        mapped_intercepters,
-                        mapped_post_intercepters,
-                    )?;
-
-                    restrict_counter!("restrict.policy.action.parent.exit", 1);
-                    std::process::exit(0);
+                    let result = PtraceWrapper::with_pid(child_pid).event_loop(
+                        handlers,
+                        observers,
+                        self.limits,
+                        self.on_error,
+                        &mut self.user_context,
+                    );
+                    return Ok(Installed::Tracer(result));
                 }
             }
         } else {
             restrict_info!("[+] Loading Seccomp Context");
             // if there is no tracing just load the filters directly
+            if let Some(limit) = self.limits.cpu {
+                limits::set_cpu_limit(limit)?;
+            }
             context.load()?;
         }
-        Ok(())
+        Ok(Installed::Tracee)
     }
     /// verbose mode
     pub fn verbose(mut self, enable: bool) -> Self {
@@ -254,7 +881,7 @@ impl Policy {
         eprintln!("'loading' feature is not enabled for verbose() to work");
         #[cfg(feature = "logging")]
         if enable {
-            let _subscriber = fmt::Subscriber::builder()
+            fmt::Subscriber::builder()
                 .with_writer(std::io::stderr)
                 .with_thread_ids(true)
                 .compact()
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use crate::{restrict_counter, restrict_warn, syscall::Syscall, Errno, TraceAction};
+
+/// What happens to a syscall over its `quota` or `rate_limit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnExceeded {
+    /// skip the syscall and make it fail with this errno (eg. `EAGAIN`)
+    Fail(Errno),
+    /// kill the traced process
+    Kill,
+    /// let the syscall run, only log it and count it in the metrics
+    Log,
+}
+
+impl OnExceeded {
+    /// the action of a syscall over its limit
+    fn action(self, syscall: Syscall, rule: &'static str) -> TraceAction {
+        restrict_counter!("restrict.quota.exceeded", 1,
+                 "syscall_name" => syscall.name(),
+                 "rule" => rule);
+        restrict_warn!("{:?} is over its {}", syscall, rule);
+        match self {
+            OnExceeded::Fail(errno) => TraceAction::Deny(errno),
+            OnExceeded::Kill => TraceAction::Kill,
+            OnExceeded::Log => TraceAction::Continue,
+        }
+    }
+}
+
+/// At most `max` calls of a syscall for the whole run
+pub(crate) struct Quota {
+    max: u64,
+    used: u64,
+    on_exceeded: OnExceeded,
+}
+
+impl Quota {
+    pub(crate) fn new(max: u64, on_exceeded: OnExceeded) -> Self {
+        Self {
+            max,
+            used: 0,
+            on_exceeded,
+        }
+    }
+
+    /// count a call of `syscall`
+    pub(crate) fn check(&mut self, syscall: Syscall) -> TraceAction {
+        if self.used >= self.max {
+            return self.on_exceeded.action(syscall, "quota");
+        }
+        self.used += 1;
+        restrict_counter!("restrict.quota.used", 1,
+                 "syscall_name" => syscall.name());
+        TraceAction::Continue
+    }
+}
+
+/// Token bucket refilled with `calls` tokens every `per`, each call takes one
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    /// tokens added per second
+    refill: f64,
+    last: Instant,
+    on_exceeded: OnExceeded,
+}
+
+impl TokenBucket {
+    /// a full bucket, so a burst of `calls` is allowed right away
+    pub(crate) fn new(calls: u32, per: Duration, on_exceeded: OnExceeded) -> Self {
+        Self {
+            capacity: calls as f64,
+            tokens: calls as f64,
+            refill: calls as f64 / per.as_secs_f64(),
+            last: Instant::now(),
+            on_exceeded,
+        }
+    }
+
+    /// take a token for a call of `syscall` made at `now`
+    pub(crate) fn check(&mut self, syscall: Syscall, now: Instant) -> TraceAction {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill).min(self.capacity);
+        self.last = now;
+        if self.tokens < 1.0 {
+            return self.on_exceeded.action(syscall, "rate limit");
+        }
+        self.tokens -= 1.0;
+        restrict_counter!("restrict.rate_limit.used", 1,
+                 "syscall_name" => syscall.name());
+        TraceAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denied(action: TraceAction) -> bool {
+        matches!(action, TraceAction::Deny(Errno(libc::EAGAIN)))
+    }
+
+    #[test]
+    fn quota_counts_every_call() {
+        let mut quota = Quota::new(2, OnExceeded::Fail(Errno(libc::EAGAIN)));
+        assert!(!denied(quota.check(Syscall::Connect)));
+        assert!(!denied(quota.check(Syscall::Connect)));
+        assert!(denied(quota.check(Syscall::Connect)));
+        assert!(denied(quota.check(Syscall::Connect)));
+
+        let mut logged = Quota::new(0, OnExceeded::Log);
+        assert!(matches!(
+            logged.check(Syscall::Connect),
+            TraceAction::Continue
+        ));
+    }
+
+    #[test]
+    fn token_bucket_refills_over_time() {
+        let mut bucket = TokenBucket::new(
+            2,
+            Duration::from_secs(1),
+            OnExceeded::Fail(Errno(libc::EAGAIN)),
+        );
+        let start = bucket.last;
+        // the burst of the full bucket, then nothing left
+        assert!(!denied(bucket.check(Syscall::Openat, start)));
+        assert!(!denied(bucket.check(Syscall::Openat, start)));
+        assert!(denied(bucket.check(Syscall::Openat, start)));
+        // one token every 500ms
+        let later = start + Duration::from_millis(500);
+        assert!(!denied(bucket.check(Syscall::Openat, later)));
+        assert!(denied(bucket.check(Syscall::Openat, later)));
+        // never more than the capacity
+        let much_later = later + Duration::from_secs(60);
+        assert!(!denied(bucket.check(Syscall::Openat, much_later)));
+        assert!(!denied(bucket.check(Syscall::Openat, much_later)));
+        assert!(denied(bucket.check(Syscall::Openat, much_later)));
+    }
+}
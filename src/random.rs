@@ -0,0 +1,318 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    interceptor::Interceptor, remap, syscall::Syscall, wrapper::PtraceWrapper, Errno, SeccompError,
+    TraceAction,
+};
+
+/// paths whose reads are replaced by the seeded stream
+const RANDOM_DEVICES: &[&str] = &["/dev/urandom", "/dev/random"];
+
+/// syscalls rewritten at their exit: the reads of random bytes and the ones moving the fds
+/// of the random devices around
+#[cfg(target_arch = "x86_64")]
+pub(crate) const SYSCALLS: &[Syscall] = &[
+    Syscall::Getrandom,
+    Syscall::Read,
+    Syscall::Pread64,
+    Syscall::Readv,
+    Syscall::Openat,
+    Syscall::Close,
+    Syscall::Dup,
+    Syscall::Dup3,
+    Syscall::Fcntl,
+    Syscall::Open,
+    Syscall::Dup2,
+];
+#[cfg(not(target_arch = "x86_64"))]
+pub(crate) const SYSCALLS: &[Syscall] = &[
+    Syscall::Getrandom,
+    Syscall::Read,
+    Syscall::Pread64,
+    Syscall::Readv,
+    Syscall::Openat,
+    Syscall::Close,
+    Syscall::Dup,
+    Syscall::Dup3,
+    Syscall::Fcntl,
+];
+
+/// ChaCha20 keystream (RFC 8439) keyed with the seed, shaped like the generator behind the
+/// kernel's own random bytes
+pub(crate) struct ChaCha20 {
+    state: [u32; 16],
+    block: [u8; 64],
+    /// bytes of `block` already handed out
+    used: usize,
+}
+
+impl ChaCha20 {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut state = [0; 16];
+        state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+        // the rest of the key, the counter and the nonce start at 0
+        state[4] = seed as u32;
+        state[5] = (seed >> 32) as u32;
+        Self {
+            state,
+            block: [0; 64],
+            used: 64,
+        }
+    }
+
+    fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(16);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(12);
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(8);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(7);
+    }
+
+    /// compute the next block and bump the 64 bits block counter
+    fn refill(&mut self) {
+        let mut x = self.state;
+        for _ in 0..10 {
+            Self::quarter_round(&mut x, 0, 4, 8, 12);
+            Self::quarter_round(&mut x, 1, 5, 9, 13);
+            Self::quarter_round(&mut x, 2, 6, 10, 14);
+            Self::quarter_round(&mut x, 3, 7, 11, 15);
+            Self::quarter_round(&mut x, 0, 5, 10, 15);
+            Self::quarter_round(&mut x, 1, 6, 11, 12);
+            Self::quarter_round(&mut x, 2, 7, 8, 13);
+            Self::quarter_round(&mut x, 3, 4, 9, 14);
+        }
+        for (n, word) in x.iter().zip(self.state).enumerate() {
+            self.block[n * 4..n * 4 + 4]
+                .copy_from_slice(&word.0.wrapping_add(word.1).to_le_bytes());
+        }
+        self.state[12] = self.state[12].wrapping_add(1);
+        if self.state[12] == 0 {
+            self.state[13] = self.state[13].wrapping_add(1);
+        }
+        self.used = 0;
+    }
+
+    /// the next `len` bytes of the stream
+    pub(crate) fn bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(len);
+        while out.len() < len {
+            if self.used == self.block.len() {
+                self.refill();
+            }
+            let take = (len - out.len()).min(self.block.len() - self.used);
+            out.extend_from_slice(&self.block[self.used..self.used + take]);
+            self.used += take;
+        }
+        out
+    }
+}
+
+/// Seeded entropy of `Policy::deterministic_random`, follows the fds opened on the random
+/// devices and overwrites what `getrandom`, their reads and `AT_RANDOM` hold
+pub(crate) struct SeededRandom {
+    stream: ChaCha20,
+    fds: HashSet<u64>,
+}
+
+impl SeededRandom {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            stream: ChaCha20::new(seed),
+            fds: HashSet::new(),
+        }
+    }
+
+    /// rewrite the results of a syscall at its exit
+    pub(crate) fn exit(&mut self, i: &mut Interceptor) -> TraceAction {
+        let ret = i.registers.return_value();
+        if Errno::from_return_value(ret as i64).is_some() {
+            return TraceAction::Continue;
+        }
+        let fd = i.arg(0);
+        // a buffer we can't write was already refused by the kernel
+        let _ = match i.syscall {
+            Syscall::Getrandom => self.fill(i, i.arg(0), ret),
+            Syscall::Read | Syscall::Pread64 if self.fds.contains(&fd) => {
+                self.fill(i, i.arg(1), ret)
+            }
+            Syscall::Readv if self.fds.contains(&fd) => self.fill_iovec(i, ret),
+            Syscall::Openat => self.opened(i, Some(i.arg(0)), i.arg(1), ret),
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Open => self.opened(i, None, i.arg(0), ret),
+            Syscall::Close => {
+                self.fds.remove(&fd);
+                Ok(())
+            }
+            #[cfg(target_arch = "x86_64")]
+            Syscall::Dup2 => self.duplicated(fd, ret),
+            Syscall::Dup | Syscall::Dup3 => self.duplicated(fd, ret),
+            Syscall::Fcntl => match i.arg(1) as i32 {
+                libc::F_DUPFD | libc::F_DUPFD_CLOEXEC => self.duplicated(fd, ret),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        };
+        TraceAction::Continue
+    }
+
+    /// at the exec stops, replace the 16 random bytes the kernel hands to the new program
+    /// (`AT_RANDOM`, the seed of its stack canaries) and hide the vDSO, newer libcs have a
+    /// vDSO `getrandom`
+    pub(crate) fn exec(&mut self, tracee: &PtraceWrapper) -> Result<(), SeccompError> {
+        if let Some(entry) = tracee.auxv_entry(libc::AT_RANDOM)? {
+            let bytes = tracee.read_word(entry + 8)?;
+            tracee.write_memory(bytes, &self.stream.bytes(16))?;
+        }
+        tracee.hide_vdso()
+    }
+
+    /// overwrite the `len` bytes read at `addr`
+    fn fill(&mut self, i: &Interceptor, addr: u64, len: u64) -> Result<(), SeccompError> {
+        i.write_bytes(addr, &self.stream.bytes(len as usize))
+    }
+
+    /// overwrite the `len` bytes spread over the iovecs of `readv`
+    fn fill_iovec(&mut self, i: &Interceptor, mut len: u64) -> Result<(), SeccompError> {
+        let (iov, iovcnt) = (i.arg(1), i.arg(2));
+        for n in 0..iovcnt {
+            if len == 0 {
+                break;
+            }
+            let iov: libc::iovec =
+                i.read_struct(iov + n * std::mem::size_of::<libc::iovec>() as u64)?;
+            let chunk = len.min(iov.iov_len as u64);
+            self.fill(i, iov.iov_base as u64, chunk)?;
+            len -= chunk;
+        }
+        Ok(())
+    }
+
+    /// remember `fd` when it was opened on a random device
+    ///
+    /// the kernel resolved the path (relative, `//dev/urandom`, symlinks..) to the file `fd`
+    /// is open on, the path argument relative to `dirfd` is only normalized without `/proc`
+    fn opened(
+        &mut self,
+        i: &Interceptor,
+        dirfd: Option<u64>,
+        path: u64,
+        fd: u64,
+    ) -> Result<(), SeccompError> {
+        let opened = match std::fs::read_link(format!("/proc/{}/fd/{}", i.child_pid, fd as i32)) {
+            Ok(opened) => opened,
+            Err(_) => {
+                let path = i.read_c_string(path, libc::PATH_MAX as usize)?;
+                let path = Path::new(OsStr::from_bytes(path.as_bytes()));
+                let absolute = match path.is_absolute() {
+                    true => PathBuf::from(path),
+                    false => remap::directory(i.child_pid, dirfd)?.join(path),
+                };
+                remap::normalize(&absolute)
+            }
+        };
+        if RANDOM_DEVICES
+            .iter()
+            .any(|device| opened == Path::new(device))
+        {
+            self.fds.insert(fd);
+        } else {
+            // the fd of a closed random device may be reused
+            self.fds.remove(&fd);
+        }
+        Ok(())
+    }
+
+    /// `new` is a copy of `old`
+    fn duplicated(&mut self, old: u64, new: u64) -> Result<(), SeccompError> {
+        if self.fds.contains(&old) {
+            self.fds.insert(new);
+        } else {
+            self.fds.remove(&new);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chacha20_block() {
+        // RFC 8439 2.3.2
+        let mut stream = ChaCha20::new(0);
+        for n in 0..8 {
+            let b = n as u32 * 4;
+            stream.state[4 + n] =
+                u32::from_le_bytes([b as u8, b as u8 + 1, b as u8 + 2, b as u8 + 3]);
+        }
+        stream.state[12..].copy_from_slice(&[1, 0x0900_0000, 0x4a00_0000, 0]);
+        assert_eq!(
+            stream.bytes(16),
+            [
+                0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20,
+                0x71, 0xc4
+            ]
+        );
+    }
+
+    #[test]
+    fn seeded_streams() {
+        let (mut a, mut b) = (ChaCha20::new(42), ChaCha20::new(42));
+        // the same bytes no matter how they are split
+        let mut split = a.bytes(10);
+        split.extend(a.bytes(100));
+        assert_eq!(split, b.bytes(110));
+        assert_ne!(ChaCha20::new(42).bytes(32), ChaCha20::new(43).bytes(32));
+    }
+
+    #[test]
+    fn exit_writes_through_the_entry_arguments() {
+        let mut random = SeededRandom::new(7);
+        let mut buf = [0u8; 16];
+        let args = [buf.as_mut_ptr() as u64, 16, 0, 0, 0, 0];
+        random.exit(&mut Interceptor::exit_stop(Syscall::Getrandom, args, 16));
+        // SAFETY: written through the kernel, read it back from memory
+        let buf = unsafe { std::ptr::read_volatile(&buf) };
+        assert_eq!(buf.to_vec(), ChaCha20::new(7).bytes(16));
+    }
+
+    #[test]
+    fn random_devices_by_any_path() {
+        let mut random = SeededRandom::new(7);
+        let path = b"//dev/../dev/./urandom\0";
+        let file = std::fs::File::open("//dev/../dev/./urandom").unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&file) as u64;
+        let args = [libc::AT_FDCWD as u64, path.as_ptr() as u64, 0, 0, 0, 0];
+        random.exit(&mut Interceptor::exit_stop(Syscall::Openat, args, fd));
+        assert!(random.fds.contains(&fd));
+
+        let mut buf = [0u8; 8];
+        let args = [fd, buf.as_mut_ptr() as u64, 8, 0, 0, 0];
+        random.exit(&mut Interceptor::exit_stop(Syscall::Read, args, 8));
+        // SAFETY: written through the kernel, read it back from memory
+        let buf = unsafe { std::ptr::read_volatile(&buf) };
+        assert_eq!(buf.to_vec(), ChaCha20::new(7).bytes(8));
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&file) as u64;
+        let args = [
+            libc::AT_FDCWD as u64,
+            b"/dev/null\0".as_ptr() as u64,
+            0,
+            0,
+            0,
+            0,
+        ];
+        random.exit(&mut Interceptor::exit_stop(Syscall::Openat, args, fd));
+        assert!(!random.fds.contains(&fd));
+    }
+}
@@ -0,0 +1,98 @@
+/// `NT_ARM_SYSTEM_CALL` regset, arm64 keeps the number of the running syscall outside of x8
+pub(crate) const NT_ARM_SYSTEM_CALL: libc::c_int = 0x404;
+
+/// Where a syscall ABI keeps its values in the `NT_PRSTATUS` register set
+///
+/// every supported register set is a flat array of 64 bits registers, so an ABI is just
+/// a list of register names and the indexes of the ones syscalls use
+pub(crate) struct Abi {
+    /// register names in `NT_PRSTATUS` order
+    pub(crate) registers: &'static [&'static str],
+    /// register holding the syscall number
+    pub(crate) syscall_number: usize,
+    /// register holding the return value at syscall exit
+    pub(crate) return_value: usize,
+    /// registers holding the 6 syscall arguments
+    pub(crate) args: [usize; 6],
+    /// program counter
+    pub(crate) instruction_pointer: usize,
+    /// stack pointer
+    pub(crate) stack_pointer: usize,
+    /// length of the syscall instruction, to rewind and restart a syscall
+    pub(crate) syscall_insn_len: u64,
+    /// register the syscall number is read from when the syscall instruction runs again,
+    /// if it isn't `syscall_number` (x86_64 reports it in orig_rax but reads it from rax)
+    pub(crate) restart_number: Option<usize>,
+    /// regset the syscall number has to be written to for the kernel to see the change
+    pub(crate) syscall_number_regset: Option<libc::c_int>,
+}
+
+/// x86_64, `struct user_regs_struct`
+#[cfg_attr(not(any(test, target_arch = "x86_64")), allow(dead_code))]
+pub(crate) const X86_64: Abi = Abi {
+    registers: &[
+        "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx",
+        "rsi", "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds",
+        "es", "fs", "gs",
+    ],
+    syscall_number: 15,
+    return_value: 10,
+    // rdi, rsi, rdx, r10, r8, r9. `syscall` clobbers rcx so the kernel uses r10 instead
+    args: [14, 13, 12, 7, 9, 8],
+    instruction_pointer: 16,
+    stack_pointer: 19,
+    syscall_insn_len: 2,
+    restart_number: Some(10),
+    syscall_number_regset: None,
+};
+
+/// aarch64, `struct user_pt_regs`
+#[cfg_attr(not(any(test, target_arch = "aarch64")), allow(dead_code))]
+pub(crate) const AARCH64: Abi = Abi {
+    registers: &[
+        "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13",
+        "x14", "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26",
+        "x27", "x28", "x29", "x30", "sp", "pc", "pstate",
+    ],
+    syscall_number: 8,
+    // x0 is both the first argument and the return value
+    return_value: 0,
+    args: [0, 1, 2, 3, 4, 5],
+    instruction_pointer: 32,
+    stack_pointer: 31,
+    syscall_insn_len: 4,
+    restart_number: None,
+    syscall_number_regset: Some(NT_ARM_SYSTEM_CALL),
+};
+
+/// riscv64, `struct user_regs_struct`
+#[cfg_attr(not(any(test, target_arch = "riscv64")), allow(dead_code))]
+pub(crate) const RISCV64: Abi = Abi {
+    registers: &[
+        "pc", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ],
+    syscall_number: 17,
+    // a0 is both the first argument and the return value
+    return_value: 10,
+    args: [10, 11, 12, 13, 14, 15],
+    instruction_pointer: 0,
+    stack_pointer: 2,
+    syscall_insn_len: 4,
+    restart_number: None,
+    syscall_number_regset: None,
+};
+
+/// the ABI of the architecture we are built for
+#[cfg(target_arch = "x86_64")]
+pub(crate) const NATIVE: &Abi = &X86_64;
+/// the ABI of the architecture we are built for
+#[cfg(target_arch = "aarch64")]
+pub(crate) const NATIVE: &Abi = &AARCH64;
+/// the ABI of the architecture we are built for
+#[cfg(target_arch = "riscv64")]
+pub(crate) const NATIVE: &Abi = &RISCV64;
+
+/// the largest register set among the supported ABIs (aarch64)
+pub(crate) const MAX_REGS: usize = 34;
@@ -0,0 +1,313 @@
+use libc::pid_t;
+
+use crate::{memory, wrapper::PtraceWrapper, SeccompError};
+
+/// register layouts of the supported syscall ABIs
+pub(crate) mod abi;
+
+use abi::{Abi, MAX_REGS};
+
+#[derive(Clone)]
+/// registers
+///
+/// read and written as a whole with `PTRACE_GETREGSET`/`PTRACE_SETREGSET` (`NT_PRSTATUS`),
+/// the syscall accessors (`arg`, `syscall_number`, `return_value`...) work the same on
+/// x86_64, aarch64 and riscv64
+pub struct Registers {
+    abi: &'static Abi,
+    regs: [u64; MAX_REGS],
+    /// syscall number to write through the ABI's syscall number regset on commit
+    new_syscall_number: Option<u64>,
+}
+
+impl Registers {
+    /// encapsulate libc user_regs_struct in Registers
+    pub fn from_raw(raw: libc::user_regs_struct) -> Self {
+        Self::from_regset(abi::NATIVE, memory::as_bytes(&raw))
+    }
+
+    /// convert it back
+    pub fn into_raw(self) -> libc::user_regs_struct {
+        memory::from_bytes(&self.to_regset())
+    }
+
+    /// empty registers, filled by `PTRACE_GETREGSET` through `regset_mut`
+    pub(crate) fn empty(abi: &'static Abi) -> Self {
+        Self {
+            abi,
+            regs: [0; MAX_REGS],
+            new_syscall_number: None,
+        }
+    }
+
+    /// build the registers from the raw bytes of an `NT_PRSTATUS` regset
+    pub(crate) fn from_regset(abi: &'static Abi, bytes: &[u8]) -> Self {
+        let mut regs = Self::empty(abi);
+        for (reg, word) in regs.regset_mut().iter_mut().zip(bytes.chunks_exact(8)) {
+            *reg = u64::from_ne_bytes(word.try_into().expect("8 bytes chunk"));
+        }
+        regs
+    }
+
+    /// the raw bytes of the `NT_PRSTATUS` regset
+    pub(crate) fn to_regset(&self) -> Vec<u8> {
+        self.regset().iter().flat_map(|r| r.to_ne_bytes()).collect()
+    }
+
+    /// the registers of the regset, in the kernel order
+    pub(crate) fn regset(&self) -> &[u64] {
+        &self.regs[..self.abi.registers.len()]
+    }
+
+    /// mutable registers of the regset, in the kernel order
+    pub(crate) fn regset_mut(&mut self) -> &mut [u64] {
+        &mut self.regs[..self.abi.registers.len()]
+    }
+
+    /// the syscall number to write to a dedicated regset when committing, if any
+    pub(crate) fn syscall_number_regset(&self) -> Option<(libc::c_int, u64)> {
+        self.abi.syscall_number_regset.zip(self.new_syscall_number)
+    }
+
+    /// Change the syscall registers
+    pub(crate) fn commit_regs(&self, child_pid: pid_t) -> Result<(), SeccompError> {
+        PtraceWrapper::set_registers(child_pid, self)
+    }
+
+    /// get a map of all registers
+    pub fn as_map(&self) -> Vec<(&'static str, u64)> {
+        self.abi
+            .registers
+            .iter()
+            .copied()
+            .zip(self.regset().iter().copied())
+            .collect()
+    }
+
+    /// get syscall by name
+    pub fn get(&self, reg_name: &str) -> Option<u64> {
+        let index = self.abi.registers.iter().position(|r| *r == reg_name)?;
+        Some(self.regs[index])
+    }
+
+    /// set a syscall value by spesifying its name
+    pub fn set(&mut self, name: &str, val: u64) -> Result<(), SeccompError> {
+        let index = self
+            .abi
+            .registers
+            .iter()
+            .position(|r| *r == name)
+            .ok_or(SeccompError::Unknown)?;
+        self.regs[index] = val;
+        Ok(())
+    }
+
+    /// syscall number
+    pub fn syscall_number(&self) -> u64 {
+        self.regs[self.abi.syscall_number]
+    }
+
+    /// change the syscall about to run (orig_rax, x8 or a7), only meaningful at syscall entry
+    pub fn set_syscall_number(&mut self, nr: u64) {
+        self.regs[self.abi.syscall_number] = nr;
+        self.new_syscall_number = Some(nr);
+    }
+
+    /// ret value
+    pub fn return_value(&self) -> u64 {
+        self.regs[self.abi.return_value]
+    }
+
+    /// set ret value
+    pub fn set_return_value(&mut self, val: u64) {
+        self.regs[self.abi.return_value] = val;
+    }
+
+    /// the n-th syscall argument
+    ///
+    /// rdi, rsi, rdx, r10, r8, r9 on x86_64, x0-x5 on aarch64 and a0-a5 on riscv64.
+    /// On aarch64 and riscv64 the first argument register holds the return value at
+    /// syscall exit so `arg(0)` is only valid at entry
+    ///
+    /// # Panics
+    /// if `n` is greater than 5, syscalls take at most 6 arguments
+    pub fn arg(&self, n: usize) -> u64 {
+        assert!(n < 6, "syscalls take at most 6 arguments");
+        self.regs[self.abi.args[n]]
+    }
+
+    /// the six syscall arguments, see `arg`
+    pub(crate) fn args(&self) -> [u64; 6] {
+        std::array::from_fn(|n| self.arg(n))
+    }
+
+    /// set the n-th syscall argument, see `arg`
+    ///
+    /// # Panics
+    /// if `n` is greater than 5
+    pub fn set_arg(&mut self, n: usize, val: u64) {
+        assert!(n < 6, "syscalls take at most 6 arguments");
+        self.regs[self.abi.args[n]] = val;
+    }
+
+    /// instruction pointer (rip or pc)
+    pub fn instruction_pointer(&self) -> u64 {
+        self.regs[self.abi.instruction_pointer]
+    }
+
+    /// stack pointer (rsp or sp)
+    pub fn stack_pointer(&self) -> u64 {
+        self.regs[self.abi.stack_pointer]
+    }
+
+    /// rewind to the syscall instruction so the current syscall runs again
+    pub(crate) fn rewind_syscall(&mut self) {
+        self.regs[self.abi.instruction_pointer] -= self.abi.syscall_insn_len;
+        if let Some(restart) = self.abi.restart_number {
+            self.regs[restart] = self.syscall_number();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// zeroed raw registers
+    fn raw() -> libc::user_regs_struct {
+        // SAFETY: user_regs_struct is plain integers
+        unsafe { std::mem::zeroed() }
+    }
+
+    /// a regset where every register holds `100 + its index`
+    fn numbered(abi: &'static Abi) -> Registers {
+        let bytes: Vec<u8> = (0..abi.registers.len() as u64)
+            .flat_map(|i| (100 + i).to_ne_bytes())
+            .collect();
+        let regs = Registers::from_regset(abi, &bytes);
+        assert_eq!(regs.to_regset(), bytes);
+        regs
+    }
+
+    /// the value `numbered` put in the register called `name`
+    fn value_of(abi: &Abi, name: &str) -> u64 {
+        100 + abi.registers.iter().position(|r| *r == name).unwrap() as u64
+    }
+
+    /// check the syscall accessors read the registers called `args`, `nr`..
+    fn check_layout(abi: &'static Abi, args: [&str; 6], nr: &str, ret: &str, ip: &str, sp: &str) {
+        let regs = numbered(abi);
+        for (n, name) in args.iter().enumerate() {
+            assert_eq!(regs.arg(n), value_of(abi, name), "argument {n}");
+        }
+        assert_eq!(regs.syscall_number(), value_of(abi, nr));
+        assert_eq!(regs.return_value(), value_of(abi, ret));
+        assert_eq!(regs.instruction_pointer(), value_of(abi, ip));
+        assert_eq!(regs.stack_pointer(), value_of(abi, sp));
+        assert_eq!(regs.as_map().len(), abi.registers.len());
+    }
+
+    #[test]
+    fn x86_64_layout() {
+        let args = ["rdi", "rsi", "rdx", "r10", "r8", "r9"];
+        check_layout(&abi::X86_64, args, "orig_rax", "rax", "rip", "rsp");
+
+        let mut regs = numbered(&abi::X86_64);
+        regs.rewind_syscall();
+        assert_eq!(regs.get("rip"), Some(value_of(&abi::X86_64, "rip") - 2));
+        // the syscall instruction reads the number from rax
+        assert_eq!(regs.get("rax"), regs.get("orig_rax"));
+        regs.set_syscall_number(39);
+        assert_eq!(regs.syscall_number_regset(), None);
+    }
+
+    #[test]
+    fn aarch64_layout() {
+        let args = ["x0", "x1", "x2", "x3", "x4", "x5"];
+        check_layout(&abi::AARCH64, args, "x8", "x0", "pc", "sp");
+
+        let mut regs = numbered(&abi::AARCH64);
+        assert_eq!(regs.get("pstate"), Some(value_of(&abi::AARCH64, "pstate")));
+        assert_eq!(regs.syscall_number_regset(), None);
+        // the kernel ignores x8 once the syscall started
+        regs.set_syscall_number(-1i64 as u64);
+        assert_eq!(
+            regs.syscall_number_regset(),
+            Some((abi::NT_ARM_SYSTEM_CALL, -1i64 as u64))
+        );
+        regs.rewind_syscall();
+        assert_eq!(regs.get("pc"), Some(value_of(&abi::AARCH64, "pc") - 4));
+    }
+
+    #[test]
+    fn riscv64_layout() {
+        let args = ["a0", "a1", "a2", "a3", "a4", "a5"];
+        check_layout(&abi::RISCV64, args, "a7", "a0", "pc", "sp");
+
+        let mut regs = numbered(&abi::RISCV64);
+        regs.set_syscall_number(63);
+        assert_eq!(regs.get("a7"), Some(63));
+        assert_eq!(regs.syscall_number_regset(), None);
+    }
+
+    #[test]
+    fn unknown_register_name() {
+        let mut regs = numbered(&abi::RISCV64);
+        assert_eq!(regs.get("rax"), None);
+        assert!(regs.set("x31", 1).is_err());
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn x86_64_syscall_abi() {
+        let mut raw = raw();
+        (raw.rdi, raw.rsi, raw.rdx, raw.r10, raw.r8, raw.r9) = (1, 2, 3, 4, 5, 6);
+        // rcx is clobbered by `syscall`, it must never be read as an argument
+        raw.rcx = 0xdead;
+        (raw.orig_rax, raw.rax) = (257, -38i64 as u64);
+        (raw.rip, raw.rsp) = (0x401000, 0x7ffc_0000);
+
+        let mut regs = Registers::from_raw(raw);
+        let args: Vec<u64> = (0..6).map(|n| regs.arg(n)).collect();
+        assert_eq!(args, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(regs.syscall_number(), 257);
+        assert_eq!(regs.instruction_pointer(), 0x401000);
+        assert_eq!(regs.stack_pointer(), 0x7ffc_0000);
+
+        regs.set_arg(3, 40);
+        regs.set_syscall_number(2);
+        regs.set_return_value(7);
+        let raw = regs.into_raw();
+        assert_eq!((raw.r10, raw.rcx), (40, 0xdead));
+        assert_eq!((raw.orig_rax, raw.rax), (2, 7));
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn aarch64_syscall_abi() {
+        let mut raw = raw();
+        for n in 0..6 {
+            raw.regs[n] = n as u64 + 1;
+        }
+        raw.regs[8] = 56;
+        (raw.pc, raw.sp) = (0x401000, 0x7ffc_0000);
+
+        let mut regs = Registers::from_raw(raw);
+        let args: Vec<u64> = (0..6).map(|n| regs.arg(n)).collect();
+        assert_eq!(args, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(regs.syscall_number(), 56);
+        assert_eq!(regs.instruction_pointer(), 0x401000);
+        assert_eq!(regs.stack_pointer(), 0x7ffc_0000);
+
+        regs.set_arg(3, 40);
+        regs.set_syscall_number(57);
+        let raw = regs.into_raw();
+        assert_eq!((raw.regs[3], raw.regs[8]), (40, 57));
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 6 arguments")]
+    fn seventh_argument_panics() {
+        Registers::from_raw(raw()).arg(6);
+    }
+}
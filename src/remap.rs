@@ -0,0 +1,272 @@
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Component, Path, PathBuf},
+};
+
+use libc::pid_t;
+
+use crate::{interceptor::Interceptor, syscall::Syscall, Errno, SeccompError, TraceAction};
+
+/// a path argument, with the argument of its directory fd (`None` for the cwd)
+type PathArg = (Option<usize>, usize);
+
+/// syscalls with path arguments on every architecture
+const SYSCALLS: &[(Syscall, &[PathArg])] = &[
+    (Syscall::Openat, &[(Some(0), 1)]),
+    (Syscall::Openat2, &[(Some(0), 1)]),
+    (Syscall::Newfstatat, &[(Some(0), 1)]),
+    (Syscall::Statx, &[(Some(0), 1)]),
+    (Syscall::Faccessat, &[(Some(0), 1)]),
+    (Syscall::Faccessat2, &[(Some(0), 1)]),
+    (Syscall::Readlinkat, &[(Some(0), 1)]),
+    (Syscall::Execve, &[(None, 0)]),
+    (Syscall::Execveat, &[(Some(0), 1)]),
+    (Syscall::Chdir, &[(None, 0)]),
+    (Syscall::Chroot, &[(None, 0)]),
+    (Syscall::Mkdirat, &[(Some(0), 1)]),
+    (Syscall::Mknodat, &[(Some(0), 1)]),
+    (Syscall::Unlinkat, &[(Some(0), 1)]),
+    (Syscall::Fchmodat, &[(Some(0), 1)]),
+    (Syscall::Fchownat, &[(Some(0), 1)]),
+    (Syscall::Utimensat, &[(Some(0), 1)]),
+    (Syscall::Renameat, &[(Some(0), 1), (Some(2), 3)]),
+    (Syscall::Renameat2, &[(Some(0), 1), (Some(2), 3)]),
+    (Syscall::Linkat, &[(Some(0), 1), (Some(2), 3)]),
+    (Syscall::Symlinkat, &[(Some(1), 2)]),
+    (Syscall::Truncate, &[(None, 0)]),
+    (Syscall::Statfs, &[(None, 0)]),
+    (Syscall::Getxattr, &[(None, 0)]),
+    (Syscall::Lgetxattr, &[(None, 0)]),
+    (Syscall::Setxattr, &[(None, 0)]),
+    (Syscall::Lsetxattr, &[(None, 0)]),
+];
+
+/// syscalls only x86_64 still has, newer architectures only have their `*at` variant
+#[cfg(target_arch = "x86_64")]
+const LEGACY_SYSCALLS: &[(Syscall, &[PathArg])] = &[
+    (Syscall::Open, &[(None, 0)]),
+    (Syscall::Creat, &[(None, 0)]),
+    (Syscall::Stat, &[(None, 0)]),
+    (Syscall::Lstat, &[(None, 0)]),
+    (Syscall::Access, &[(None, 0)]),
+    (Syscall::Readlink, &[(None, 0)]),
+    (Syscall::Mkdir, &[(None, 0)]),
+    (Syscall::Mknod, &[(None, 0)]),
+    (Syscall::Rmdir, &[(None, 0)]),
+    (Syscall::Unlink, &[(None, 0)]),
+    (Syscall::Chmod, &[(None, 0)]),
+    (Syscall::Chown, &[(None, 0)]),
+    (Syscall::Lchown, &[(None, 0)]),
+    (Syscall::Rename, &[(None, 0), (None, 1)]),
+    (Syscall::Link, &[(None, 0), (None, 1)]),
+    // the target of a symlink is only text, its own path is remapped
+    (Syscall::Symlink, &[(None, 1)]),
+];
+#[cfg(not(target_arch = "x86_64"))]
+const LEGACY_SYSCALLS: &[(Syscall, &[PathArg])] = &[];
+
+/// the syscalls whose paths are remapped
+pub(crate) fn syscalls() -> impl Iterator<Item = Syscall> {
+    SYSCALLS.iter().chain(LEGACY_SYSCALLS).map(|(sc, _)| *sc)
+}
+
+fn path_arguments(syscall: Syscall) -> &'static [PathArg] {
+    SYSCALLS
+        .iter()
+        .chain(LEGACY_SYSCALLS)
+        .find(|(sc, _)| *sc == syscall)
+        .map_or(&[], |(_, args)| *args)
+}
+
+/// `path` without `.` and `..`, resolved without following symlinks
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+/// Prefix rules of `Policy::remap_path`, rewriting the path arguments of the file syscalls
+#[derive(Debug, Default)]
+pub(crate) struct PathRemap {
+    rules: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathRemap {
+    /// redirect `from` and everything under it to `to`, a relative `to` is taken from the
+    /// cwd of the tracer
+    pub(crate) fn add(&mut self, from: PathBuf, to: PathBuf) {
+        let to = match std::env::current_dir() {
+            Ok(cwd) if to.is_relative() => cwd.join(to),
+            _ => to,
+        };
+        self.rules.push((normalize(&from), to));
+    }
+
+    /// where `path` (absolute and normalized) goes, the longest matching prefix wins
+    fn remap(&self, path: &Path) -> Option<PathBuf> {
+        self.rules
+            .iter()
+            .filter(|(from, _)| path.starts_with(from))
+            .max_by_key(|(from, _)| from.components().count())
+            .map(|(from, to)| match path.strip_prefix(from) {
+                Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+                _ => to.clone(),
+            })
+    }
+
+    /// rewrite the path arguments of a syscall at its entry
+    ///
+    /// the syscall is denied with `EACCES` when a path can't be checked or rewritten, it
+    /// would otherwise reach the original path
+    pub(crate) fn entry(&self, i: &mut Interceptor) -> TraceAction {
+        let mut rewritten = false;
+        for (dirfd, arg) in path_arguments(i.syscall) {
+            match self.rewrite(i, *dirfd, *arg) {
+                Ok(done) => rewritten |= done,
+                Err(err) => return Self::deny(i, err),
+            }
+        }
+        if rewritten {
+            if let Err(err) = i.commit_regs() {
+                return Self::deny(i, err);
+            }
+        }
+        TraceAction::Continue
+    }
+
+    // only logged
+    #[cfg_attr(not(feature = "logging"), allow(unused_variables))]
+    fn deny(i: &Interceptor, err: SeccompError) -> TraceAction {
+        crate::restrict_warn!(
+            "denied {:?}, its path can't be remapped: {}",
+            i.syscall,
+            err
+        );
+        TraceAction::Deny(Errno(libc::EACCES))
+    }
+
+    /// point argument `arg` to the remapped path if it has one
+    fn rewrite(
+        &self,
+        i: &mut Interceptor,
+        dirfd: Option<usize>,
+        arg: usize,
+    ) -> Result<bool, SeccompError> {
+        let addr = i.registers.arg(arg);
+        if addr == 0 {
+            return Ok(false);
+        }
+        let path = match i.read_c_string(addr, libc::PATH_MAX as usize) {
+            Ok(path) => path,
+            // a path we can't read fails the same way once the kernel tries
+            Err(SeccompError::MemoryFault(..) | SeccompError::UnterminatedString(..)) => {
+                return Ok(false)
+            }
+            Err(err) => return Err(err),
+        };
+        let path = Path::new(OsStr::from_bytes(path.as_bytes()));
+        // `AT_EMPTY_PATH` works on the fd itself
+        if path.as_os_str().is_empty() {
+            return Ok(false);
+        }
+        let absolute = match path.is_absolute() {
+            true => normalize(path),
+            false => {
+                normalize(&directory(i.child_pid, dirfd.map(|n| i.registers.arg(n)))?.join(path))
+            }
+        };
+        let Some(remapped) = self.remap(&absolute) else {
+            return Ok(false);
+        };
+        // never written in place, the path may be a string the program uses again and the
+        // replacement is often longer
+        let mut bytes = remapped.into_os_string().into_vec();
+        bytes.push(0);
+        let scratch = i.write_scratch(&bytes)?;
+        i.registers.set_arg(arg, scratch);
+        Ok(true)
+    }
+}
+
+/// the directory a relative path of `pid` starts from: its cwd, or the directory `dirfd`
+/// is open on
+pub(crate) fn directory(pid: pid_t, dirfd: Option<u64>) -> Result<PathBuf, SeccompError> {
+    let link = match dirfd {
+        Some(fd) if fd as i32 != libc::AT_FDCWD => format!("/proc/{pid}/fd/{}", fd as i32),
+        _ => format!("/proc/{pid}/cwd"),
+    };
+    Ok(std::fs::read_link(link)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registers::Registers;
+
+    #[test]
+    fn longest_prefix_wins() {
+        let mut remap = PathRemap::default();
+        remap.add("/var/lib/app".into(), "/tmp/app".into());
+        remap.add("/var/lib/app/cache/".into(), "/tmp/cache".into());
+        remap.add("/etc/resolv.conf".into(), "/fixtures/resolv.conf".into());
+
+        let remapped = |path: &str| remap.remap(Path::new(path));
+        assert_eq!(remapped("/var/lib/app"), Some("/tmp/app".into()));
+        assert_eq!(remapped("/var/lib/app/db/x"), Some("/tmp/app/db/x".into()));
+        assert_eq!(
+            remapped("/var/lib/app/cache/y"),
+            Some("/tmp/cache/y".into())
+        );
+        assert_eq!(
+            remapped("/etc/resolv.conf"),
+            Some("/fixtures/resolv.conf".into())
+        );
+        // prefixes match whole components only
+        assert_eq!(remapped("/var/lib/application"), None);
+        assert_eq!(remapped("/etc"), None);
+    }
+
+    #[test]
+    fn entry_fails_closed() {
+        let mut remap = PathRemap::default();
+        remap.add("/var/lib/app".into(), "/tmp/app".into());
+        let path = b"/var/lib/app/db\0";
+        let other = b"/etc/hosts\0";
+
+        let entry = |addr: u64| {
+            // SAFETY: user_regs_struct is plain integers
+            let mut regs = Registers::from_raw(unsafe { std::mem::zeroed() });
+            regs.set_arg(0, libc::AT_FDCWD as u64);
+            regs.set_arg(1, addr);
+            // the process isn't traced and the null stack pointer leaves no scratch memory,
+            // the replacement path can't be written
+            let mut i = Interceptor::new(Syscall::Openat, regs, std::process::id() as pid_t);
+            remap.entry(&mut i)
+        };
+        assert!(matches!(
+            entry(path.as_ptr() as u64),
+            TraceAction::Deny(Errno(libc::EACCES))
+        ));
+        assert!(matches!(
+            entry(other.as_ptr() as u64),
+            TraceAction::Continue
+        ));
+        // the kernel fails on the bad address itself
+        assert!(matches!(entry(1), TraceAction::Continue));
+    }
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize(Path::new("/a/./b/../c/")), Path::new("/a/c"));
+        assert_eq!(normalize(Path::new("/../..")), Path::new("/"));
+    }
+}
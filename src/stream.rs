@@ -0,0 +1,144 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc::{self, Receiver, SyncSender, TrySendError},
+    Arc,
+};
+
+use crate::{format::SyscallEvent, interceptor::Interceptor};
+
+/// What the tracer does when the channel of `Policy::event_stream` is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// wait for the consumer, the traced process is paused meanwhile and no event is lost
+    #[default]
+    Block,
+    /// drop the event and go on, `EventStream::dropped` counts the lost events
+    DropNewest,
+}
+
+/// The syscall events of the traced process, an entry and an exit event per syscall
+///
+/// iterating blocks until the next event and ends once the traced process exited, consume it
+/// from another thread than the one calling `Policy::run`
+#[derive(Debug)]
+pub struct EventStream {
+    receiver: Receiver<SyscallEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventStream {
+    /// events dropped so far because the channel was full (`Backpressure::DropNewest`)
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// the next event if one is waiting, without blocking
+    pub fn try_next(&self) -> Option<SyscallEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = SyscallEvent;
+
+    fn next(&mut self) -> Option<SyscallEvent> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// `Policy::event_stream` state in the event loop: the sending side of the channel and the
+/// syscall waiting for its exit stop
+pub(crate) struct EventPublisher {
+    sender: SyncSender<SyscallEvent>,
+    backpressure: Backpressure,
+    dropped: Arc<AtomicU64>,
+    pending: Option<SyscallEvent>,
+}
+
+impl EventPublisher {
+    /// a channel holding up to `capacity` events
+    pub(crate) fn channel(capacity: usize, backpressure: Backpressure) -> (Self, EventStream) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let dropped = Arc::default();
+        let publisher = Self {
+            sender,
+            backpressure,
+            dropped: Arc::clone(&dropped),
+            pending: None,
+        };
+        (publisher, EventStream { receiver, dropped })
+    }
+
+    /// the syscall entry, the seccomp stop following the entry stop of the same syscall is
+    /// ignored
+    pub(crate) fn entry(&mut self, interceptor: &Interceptor) {
+        if self.pending.is_none() {
+            let event = SyscallEvent::entry(interceptor);
+            self.publish(event.clone());
+            self.pending = Some(event);
+        }
+    }
+
+    /// the syscall exit of the pending syscall
+    pub(crate) fn exit(&mut self, interceptor: &Interceptor) {
+        if let Some(mut event) = self.pending.take() {
+            event.finish(interceptor);
+            self.publish(event);
+        }
+    }
+
+    /// a consumer that went away only loses the events
+    fn publish(&self, event: SyscallEvent) {
+        match self.backpressure {
+            Backpressure::Block => {
+                let _ = self.sender.send(event);
+            }
+            Backpressure::DropNewest => {
+                if let Err(TrySendError::Full(_)) = self.sender.try_send(event) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{format::Phase, registers::Registers, syscall::Syscall};
+
+    fn interceptor(ret: i64) -> Interceptor {
+        // SAFETY: user_regs_struct is plain integers
+        let raw = unsafe { std::mem::zeroed::<libc::user_regs_struct>() };
+        let mut regs = Registers::from_raw(raw);
+        regs.set_return_value(ret as u64);
+        Interceptor::new(Syscall::Getpid, regs, std::process::id() as libc::pid_t)
+    }
+
+    #[test]
+    fn entry_and_exit_events() {
+        let (mut publisher, stream) = EventPublisher::channel(4, Backpressure::Block);
+        publisher.entry(&interceptor(0));
+        // the seccomp stop of the same syscall
+        publisher.entry(&interceptor(0));
+        publisher.exit(&interceptor(42));
+        drop(publisher);
+
+        let events: Vec<_> = stream.collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].phase, events[0].ret), (Phase::Entry, None));
+        assert_eq!((events[1].phase, events[1].ret), (Phase::Exit, Some(42)));
+    }
+
+    #[test]
+    fn lossy_channel_counts_dropped_events() {
+        let (mut publisher, stream) = EventPublisher::channel(1, Backpressure::DropNewest);
+        for _ in 0..3 {
+            publisher.entry(&interceptor(0));
+            publisher.exit(&interceptor(0));
+        }
+        assert_eq!(stream.dropped(), 5);
+        assert!(stream.try_next().is_some());
+        assert!(stream.try_next().is_none());
+    }
+}
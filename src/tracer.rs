@@ -2,7 +2,7 @@ use libc::pid_t;
 
 use crate::{
     policy::Policy,
-    wrapper::{self, ForkResult, PtraceWrapper},
+    wrapper::{ForkResult, PtraceWrapper},
     SeccompError,
 };
 
@@ -20,27 +20,23 @@ pub(crate) enum TracingHandle {
         // filters: Vec<Box<dyn RestrictFilter>>,
     },
 }
-impl Policy {
+impl<C> Policy<C> {
     /// this forks the current process and returns a `TracingHandle`
     /// before returning the child enables tracing(PTRACE_TRACEME)
     /// TODO(x0rw): move this
     pub(crate) fn spawn_traced(&mut self) -> Result<TracingHandle, SeccompError> {
         let result = PtraceWrapper::fork()?;
         match result.get_process() {
-            ForkResult::Child => {
-                // child process
-                // enable_tracing is setting PTRACE_TRACEME option
-                wrapper::PtraceWrapper::with_pid(0).enable_tracing()?;
-                return Ok(TracingHandle::Child);
-            }
+            // the parent seizes the child once it stopped itself with SIGSTOP
+            ForkResult::Child => Ok(TracingHandle::Child),
             ForkResult::Parent(pid) => {
                 // println!("pid: {_pid}");
                 // the caller shoould already have 'trace_rules'
                 // let filters = std::mem::take(&mut self.trace_rules);
-                return Ok(TracingHandle::Parent {
+                Ok(TracingHandle::Parent {
                     child_pid: pid.to_owned(),
                     // tracer: result,
-                });
+                })
                 // std::process::exit(0);
             }
         }
@@ -1,21 +1,30 @@
 use std::{
+    cell::RefCell,
     ffi::c_void,
     io::{self},
-    mem::MaybeUninit,
     ptr::{self, NonNull},
+    rc::Rc,
 };
 
 use libseccomp_sys::*;
 
 use crate::{
-    error::SeccompError,
+    audit::{Auditor, Verdict},
+    error::{Errno, SeccompError},
+    fd::FdTable,
     filter::{intercept::InterceptorMap, tracer::TracerMap},
+    format::PrettyTracer,
     interceptor::Interceptor,
-    registers::Registers,
+    limits::{Limits, Watchdog},
+    registers::{abi, Registers},
     restrict_error,
+    stream::EventPublisher,
     syscall::Syscall,
 };
 
+#[cfg(feature = "metrics")]
+use crate::metrics::RuntimeMetrics;
+
 #[allow(dead_code)]
 /// Todo(x0rw): here make emums for libseccomp-sys that interact with it
 #[derive(Debug)]
@@ -29,6 +38,16 @@ impl SeccompWrapper {
         // SAFETY: `seccomp_init` returns a raw context. Assumes `default_action` is valid.
         let context_ptr = unsafe { seccomp_init(default_action.to_raw()) };
         let context = NonNull::new(context_ptr).ok_or(SeccompError::InitContextFailed)?;
+        // report the errno of the kernel from `seccomp_load` instead of `-ECANCELED`, older
+        // libseccomp (< 2.5) don't know the attribute and keep the default
+        // SAFETY: `context` was just initialized by `seccomp_init`.
+        unsafe {
+            seccomp_attr_set(
+                context.as_ptr(),
+                scmp_filter_attr::SCMP_FLTATR_API_SYSRAWRC,
+                1,
+            )
+        };
 
         Ok(Self {
             context,
@@ -54,14 +73,14 @@ impl SeccompWrapper {
     pub(crate) fn add_rule(&self, action: Action, syscall: Syscall) -> Result<(), SeccompError> {
         let context = self.context.as_ptr();
         // let syscall = Self::resolve_syscall(syscall)?;
-        let syscall = syscall as i32;
-        let action = action.to_raw();
+        let raw_action = action.to_raw();
 
         // SAFETY: `context` is valid, and `action` and `syscall` are well-formed.
-        let seccomp_add_result = unsafe { seccomp_rule_add(context, action, syscall, 0) };
+        let seccomp_add_result =
+            unsafe { seccomp_rule_add(context, raw_action, syscall as i32, 0) };
 
         if seccomp_add_result != 0 {
-            return Err(SeccompError::FailedToAddResultToSeccompFilter);
+            return Err(SeccompError::add_rule(syscall, action, seccomp_add_result));
         }
 
         Ok(())
@@ -74,7 +93,7 @@ impl SeccompWrapper {
         // SAFETY: `context_ptr` is a valid context initialized with `seccomp_init`.
         let load_result = unsafe { seccomp_load(context_ptr) };
         if load_result != 0 {
-            return Err(SeccompError::LoadError);
+            return Err(SeccompError::load(load_result));
         }
         Ok(())
     }
@@ -107,9 +126,11 @@ impl Action {
 }
 
 use libc::{
-    kill, pid_t, ptrace, waitpid, PTRACE_CONT, PTRACE_GETREGS, PTRACE_KILL, PTRACE_O_TRACESECCOMP,
-    PTRACE_O_TRACESYSGOOD, PTRACE_SETOPTIONS, PTRACE_SETREGS, PTRACE_SYSCALL, PTRACE_TRACEME,
-    SIGKILL, SIGTRAP, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WSTOPSIG,
+    c_int, kill, pid_t, ptrace, waitpid, NT_PRSTATUS, PTRACE_CONT, PTRACE_DETACH,
+    PTRACE_EVENT_STOP, PTRACE_GETREGSET, PTRACE_KILL, PTRACE_LISTEN, PTRACE_O_TRACEEXEC,
+    PTRACE_O_TRACESECCOMP, PTRACE_O_TRACESYSGOOD, PTRACE_PEEKDATA, PTRACE_POKEDATA, PTRACE_SEIZE,
+    PTRACE_SETREGSET, PTRACE_SYSCALL, SIGCONT, SIGKILL, SIGSTOP, SIGTRAP, SIGTSTP, SIGTTIN,
+    SIGTTOU, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WSTOPSIG, WTERMSIG,
 };
 /// Fork
 #[derive(Debug)]
@@ -164,13 +185,15 @@ impl PtraceWrapper {
 
     /// wait for the child to raise the signal
     /// this is crucial to sync the child with the parent
-    /// and set the PTRACE_O_TRACESECCOMP flag at the right time
+    /// and seize it at the right time
     /// why do we quit when we the signal doesn't match?
     /// - in `apply()` function after forking, the child raises SIGSTOP to stop itself
-    /// waiting for the parent to catch this signal
+    ///   waiting for the parent to catch this signal
     pub fn wait_for_signal(&self, expected: i32) -> Result<(), io::Error> {
         let mut status = 0;
-        let ret = unsafe { libc::waitpid(self.get_process().get_pid(), &mut status, 0) };
+        // the child is not traced yet, its stop is only reported with WUNTRACED
+        let ret =
+            unsafe { libc::waitpid(self.get_process().get_pid(), &mut status, libc::WUNTRACED) };
         if ret == -1 {
             restrict_error!("waitpid failed");
             return Err(io::Error::last_os_error());
@@ -186,228 +209,346 @@ impl PtraceWrapper {
                 expected,
                 status
             );
-            return Err(io::Error::new(io::ErrorKind::Other, "Unexpected signal"));
+            return Err(io::Error::other("Unexpected signal"));
         }
         Ok(())
     }
 
-    /// event loop
-
-    pub fn event_loop(
+    /// event loop, runs until the tracee exits and returns how it ended
+    ///
+    /// `ctx` is the policy context handed to every handler. When handling a stop fails the
+    /// tracee is killed, detached or resumed according to `on_error`, in the first two cases
+    /// the error is returned once the tracee exited
+    pub(crate) fn event_loop<C>(
         &self,
-        trace_map: TracerMap,
-        intercept_map: InterceptorMap,
-        post_intercept_map: InterceptorMap,
-    ) -> Result<(), SeccompError> {
+        handlers: Handlers<C>,
+        observers: Observers,
+        limits: Limits,
+        on_error: OnError,
+        ctx: &mut C,
+    ) -> Result<Outcome, SeccompError> {
         let child = self.get_process().get_pid();
-        let wrapper = PtraceWrapper::with_pid(child);
-        let mut in_syscall = false;
-        // println!("[!] child pid {}", wrapper.get_process().get_pid());
+        let watchdog = match limits
+            .time
+            .map(|limit| Watchdog::start(child, limit))
+            .transpose()
+        {
+            Ok(watchdog) => watchdog,
+            Err(err) => {
+                // without its watchdog the tracee could run forever
+                self.kill_execution()?;
+                unsafe { waitpid(child, std::ptr::null_mut(), 0) };
+                return Err(err);
+            }
+        };
+        let mut tracer = EventLoop {
+            wrapper: PtraceWrapper::with_pid(child),
+            handlers,
+            // printing every syscall needs the entry and exit stops of all of them, otherwise
+            // the tracee only stops at seccomp `TRACE` rules and at the exits asked for
+            trace_all: observers.trace_all(),
+            observers,
+            ctx,
+            in_syscall: false,
+            entry_args: [0; 6],
+            skipped: None,
+            skip_exit: false,
+            wait_exit: false,
+            policy_kill: false,
+        };
+        // the error the tracee was killed or detached for
+        let mut failure = None;
         let mut status = 0;
+        // SAFETY: rusage is plain data
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
         loop {
-            let ret = unsafe { waitpid(child, &mut status, 0) };
+            let ret = unsafe { libc::wait4(child, &mut status, 0, &mut usage) };
 
             if ret == -1 {
                 let err = io::Error::last_os_error();
-                if err.raw_os_error() == Some(libc::ECHILD) {
-                    break;
+                match err.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(libc::ECHILD) => break,
+                    _ => return Err(SeccompError::Wait(child, err)),
                 }
-                panic!("waitpid failed: {}", err);
-                // return Err(SeccompError::Unknown);
             }
 
             if WIFEXITED(status) || WIFSIGNALED(status) {
+                if let Some(pretty) = tracer.observers.pretty.as_mut() {
+                    if let Err(err) = pretty.exited(status) {
+                        restrict_error!("failed to print the exit of process {}: {}", child, err);
+                        if on_error != OnError::Continue {
+                            failure.get_or_insert(err);
+                        }
+                    }
+                }
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = tracer.observers.metrics.as_mut() {
+                    metrics.exited();
+                }
+                if let Some(audit) = tracer.observers.audit.as_mut() {
+                    if let Err(err) = audit.exited() {
+                        restrict_error!("failed to audit the exit of process {}: {}", child, err);
+                        if on_error != OnError::Continue {
+                            failure.get_or_insert(err.into());
+                        }
+                    }
+                }
                 break;
             }
 
-            if WIFSTOPPED(status) {
-                let sig = WSTOPSIG(status);
-
-                match sig {
-                    s if s == (SIGTRAP | 0x80) => {
-                        let regs = wrapper.get_registers()?;
-                        let caught_syscall =
-                            Syscall::try_from(regs.syscall_number() as i32).unwrap();
-                        if !in_syscall {
-                            in_syscall = true;
-                            // println!("ENTRY: syscall {:?}", caught_syscall);
-                            // If it's tracked by seccomp the second match arm will catch it
-                        } else {
-                            in_syscall = false;
-
-                            match post_intercept_map.find_by_syscall(caught_syscall).take() {
-                                Some(mapped_fn) => {
-                                    let interceptor = Interceptor::new(
-                                        caught_syscall,
-                                        regs,
-                                        wrapper.get_process().get_pid(),
-                                    );
-                                    match mapped_fn(interceptor) {
-                                        TraceAction::Kill => {
-                                            wrapper.kill_execution()?;
-                                            // if the child is killed the parent should be killed too
-                                            // todo(z0rw): exit gracefully
-                                            std::process::exit(SIGKILL);
-                                        }
-
-                                        _ => wrapper.syscall_trace()?,
-                                    }
-                                }
-                                None => wrapper.syscall_trace().unwrap(),
-                            };
-                            // println!("EXIT: return value {}", regs.return_value());
-                        }
-                        wrapper.syscall_trace()?;
-                    }
+            // a killed tracee still reports the stop it was in before its exit
+            if !WIFSTOPPED(status) || failure.is_some() {
+                continue;
+            }
 
-                    _ if sig == libc::SIGTRAP && (status >> 16) == libc::PTRACE_EVENT_SECCOMP => {
-                        let regs = wrapper.get_registers()?;
-
-                        // get Syscall from regs.orig_rax
-                        let caught_syscall = Syscall::try_from(regs.syscall_number() as i32)?;
-
-                        // Getting the syscall handler
-                        match trace_map.find_by_syscall(caught_syscall).take() {
-                            Some(mapped_fn) => {
-                                match mapped_fn(caught_syscall) {
-                                    TraceAction::Kill => {
-                                        wrapper.kill_execution()?;
-                                        // if the child is killed the parent should be killed too
-                                        // todo(z0rw): exit gracefully
-                                        std::process::exit(SIGKILL);
-                                    }
-                                    _ => wrapper.continue_execution()?,
-                                }
-                            }
-                            None => {}
-                        };
-
-                        match intercept_map.find_by_syscall(caught_syscall).take() {
-                            Some(mapped_fn) => {
-                                let interceptor = Interceptor::new(
-                                    caught_syscall,
-                                    regs,
-                                    wrapper.get_process().get_pid(),
-                                );
-                                match mapped_fn(interceptor) {
-                                    TraceAction::Continue => wrapper.syscall_trace()?,
-                                    TraceAction::Kill => {
-                                        wrapper.kill_execution()?;
-                                        // if the child is killed the parent should be killed too
-                                        // todo(z0rw): exit gracefully
-                                        std::process::exit(SIGKILL);
-                                    }
-
-                                    TraceAction::SkipExit => wrapper.continue_execution()?,
-                                }
-                            }
-                            None => wrapper.syscall_trace().unwrap(),
-                        };
-                    } // println!("else branche- syscall_trace()");
-
-                    _ => {
-                        wrapper.syscall_trace()?;
-                    }
+            let err = match tracer.handle_stop(status) {
+                Ok(()) => continue,
+                // the tracee died (eg. SIGKILL from outside) while its stop was handled,
+                // the next wait reports its exit
+                Err(err) if err.is_tracee_gone() => continue,
+                Err(err) => err,
+            };
+            restrict_error!("failed to handle a stop of process {}: {}", child, err);
+            let recovered = match on_error {
+                OnError::Kill => {
+                    failure = Some(err);
+                    tracer.wrapper.kill_execution()
+                }
+                OnError::Detach => {
+                    failure = Some(err);
+                    tracer.wrapper.detach()
                 }
+                OnError::Continue => tracer.wrapper.resume(tracer.trace_all),
+            };
+            match recovered {
+                Err(err) if !err.is_tracee_gone() => return Err(err),
+                _ => {}
             }
         }
-        Ok(())
+        if let Some(err) = failure {
+            return Err(err);
+        }
+
+        let killed = WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL;
+        Ok(if watchdog.is_some_and(Watchdog::fired) && killed {
+            Outcome::TimeLimit
+        } else if tracer.policy_kill && killed {
+            Outcome::PolicyKill
+        } else if WIFSIGNALED(status)
+            && (WTERMSIG(status) == libc::SIGXCPU || killed)
+            && limits.cpu_exceeded(&usage)
+        {
+            Outcome::CpuLimit
+        } else if WIFSIGNALED(status) {
+            Outcome::Signaled(WTERMSIG(status))
+        } else {
+            Outcome::Exited(libc::WEXITSTATUS(status))
+        })
     }
-    /// Set `PTRACE_O_TRACESECCOMP` option
-    /// to
-    pub fn set_traceseccomp_option(&self) -> Result<&Self, SeccompError> {
+    /// skip the syscall the tracee is stopped at (seccomp stop) by replacing its number with -1
+    ///
+    /// the return value is set right away too, but some kernels overwrite it with -ENOSYS
+    /// so the caller must write `ret` again at the syscall exit stop
+    pub(crate) fn skip_syscall(&self, mut regs: Registers, ret: i64) -> Result<(), SeccompError> {
+        regs.set_syscall_number(-1i64 as u64);
+        regs.set_return_value(ret as u64);
+        regs.commit_regs(self.process.get_pid())
+    }
+
+    /// start tracing the child with `PTRACE_SEIZE`, setting `PTRACE_O_TRACESECCOMP` and
+    /// `PTRACE_O_TRACEEXEC` (or an exec would send the tracee a `SIGTRAP`)
+    ///
+    /// the child must be in the group-stop of its sync `SIGSTOP`, that stop is ended with a
+    /// `SIGCONT` and the child is left in the signal-delivery stop of it, to be suppressed
+    /// when resuming. Unlike `PTRACE_TRACEME`, a seized tracee reports its group-stops so
+    /// they can last until a `SIGCONT` (see `listen`)
+    pub fn seize(&self) -> Result<&Self, SeccompError> {
+        let pid = self.process.get_pid();
         let ret = unsafe {
             ptrace(
-                PTRACE_SETOPTIONS,
-                self.process.get_pid(),
+                PTRACE_SEIZE,
+                pid,
                 std::ptr::null_mut::<c_void>(),
-                (PTRACE_O_TRACESECCOMP | PTRACE_O_TRACESYSGOOD) as *mut c_void,
+                (PTRACE_O_TRACESECCOMP | PTRACE_O_TRACESYSGOOD | PTRACE_O_TRACEEXEC) as *mut c_void,
             )
         };
         if ret == -1 {
             return Err(SeccompError::PtraceOptionsSet(
-                self.process.get_pid(),
+                pid,
                 std::io::Error::last_os_error(),
             ));
         }
 
+        let mut status = 0;
+        if unsafe { waitpid(pid, &mut status, 0) } == -1 {
+            return Err(SeccompError::Wait(pid, io::Error::last_os_error()));
+        }
+        if !WIFSTOPPED(status) || (status >> 16) != PTRACE_EVENT_STOP {
+            restrict_error!("Unexpected stop after seizing, raw status = {:#x}", status);
+            return Err(io::Error::other("Unexpected signal").into());
+        }
+
+        // a detached tracee would go back to the group-stop if it was never ended
+        unsafe { kill(pid, SIGCONT) };
+        let mut signals = Vec::new();
+        let result = self.resume_until(
+            |status| (status >> 16) == 0 && WSTOPSIG(status) == SIGCONT,
+            &mut signals,
+        );
+        self.reraise(signals);
+        result.map_err(|err| SeccompError::Wait(pid, err))?;
         Ok(self)
     }
 
-    /// enable ptrace tracing for the child process
-    pub fn enable_tracing(&self) -> Result<&Self, SeccompError> {
+    /// read the general purpose registers with `PTRACE_GETREGSET` (`NT_PRSTATUS`),
+    /// unlike `PTRACE_GETREGS` it exists on every architecture
+    pub fn get_registers(&self) -> Result<Registers, SeccompError> {
+        let mut regs = Registers::empty(abi::NATIVE);
+        let regset = regs.regset_mut();
+        let mut iov = libc::iovec {
+            iov_base: regset.as_mut_ptr() as *mut c_void,
+            iov_len: std::mem::size_of_val(regset),
+        };
+
+        // SAFETY: the kernel writes at most `iov_len` bytes to the regset buffer
         let ret = unsafe {
             ptrace(
-                PTRACE_TRACEME,
-                self.process.get_pid(),
-                std::ptr::null_mut::<c_void>(),
-                0 as *mut c_void,
+                PTRACE_GETREGSET,
+                self.get_process().get_pid(),
+                NT_PRSTATUS as *mut c_void,
+                ptr::addr_of_mut!(iov) as *mut c_void,
             )
         };
+
         if ret == -1 {
-            return Err(SeccompError::PtraceSyscall(
-                self.process.get_pid(),
-                std::io::Error::last_os_error(),
-            ));
+            return Err(io::Error::last_os_error().into());
         }
-        Ok(self)
+        Ok(regs)
     }
 
-    pub fn get_registers(&self) -> Result<Registers, SeccompError> {
-        let mut regs = MaybeUninit::<libc::user_regs_struct>::uninit();
+    /// write the registers back with `PTRACE_SETREGSET` (`NT_PRSTATUS`)
+    ///
+    /// on aarch64 a changed syscall number also goes through `NT_ARM_SYSTEM_CALL`, the
+    /// kernel doesn't read x8 again once the syscall started
+    pub fn set_registers(child_pid: pid_t, regs: &Registers) -> Result<(), SeccompError> {
+        let regset = regs.regset();
+        let mut iov = libc::iovec {
+            iov_base: regset.as_ptr() as *mut c_void,
+            iov_len: std::mem::size_of_val(regset),
+        };
+        Self::set_regset(child_pid, NT_PRSTATUS, &mut iov)?;
 
+        if let Some((note, nr)) = regs.syscall_number_regset() {
+            let mut nr = nr as libc::c_int;
+            let mut iov = libc::iovec {
+                iov_base: ptr::addr_of_mut!(nr) as *mut c_void,
+                iov_len: std::mem::size_of::<libc::c_int>(),
+            };
+            Self::set_regset(child_pid, note, &mut iov)?;
+        }
+        Ok(())
+    }
+
+    /// `PTRACE_SETREGSET` of the regset `note`
+    fn set_regset(
+        child_pid: pid_t,
+        note: libc::c_int,
+        iov: &mut libc::iovec,
+    ) -> Result<(), SeccompError> {
+        // SAFETY: the kernel reads at most `iov_len` bytes from the iovec buffer
         let ret = unsafe {
             ptrace(
-                PTRACE_GETREGS,
-                self.get_process().get_pid(),
-                std::ptr::null_mut::<c_void>(),
-                regs.as_mut_ptr() as *mut c_void,
+                PTRACE_SETREGSET,
+                child_pid,
+                note as usize as *mut c_void,
+                iov as *mut libc::iovec as *mut c_void,
             )
         };
 
         if ret == -1 {
             return Err(io::Error::last_os_error().into());
         }
+        Ok(())
+    }
 
-        let regs = unsafe { regs.assume_init() };
-        Ok(Registers::from_raw(regs))
+    /// resume the tracee with `PTRACE_SYSCALL` when it must also stop at the next syscall
+    /// exit (or entry), with `PTRACE_CONT` otherwise so only the seccomp `TRACE` rules stop it
+    pub fn resume(&self, stop_at_syscall: bool) -> Result<(), SeccompError> {
+        self.resume_with_signal(stop_at_syscall, 0)
     }
 
-    pub fn set_registers(
-        child_pid: pid_t,
-        mut user_regs: libc::user_regs_struct,
+    /// `resume` the tracee from a signal-delivery stop, delivering `signal` (0 for none)
+    pub fn resume_with_signal(
+        &self,
+        stop_at_syscall: bool,
+        signal: c_int,
     ) -> Result<(), SeccompError> {
-        let raw_ptr = ptr::from_mut(&mut user_regs);
+        let pid = self.process.get_pid();
+        let request = if stop_at_syscall {
+            PTRACE_SYSCALL
+        } else {
+            PTRACE_CONT
+        };
         let ret = unsafe {
             ptrace(
-                PTRACE_SETREGS,
-                child_pid,
+                request,
+                pid,
                 std::ptr::null_mut::<c_void>(),
-                raw_ptr as *mut c_void,
+                signal as usize as *mut c_void,
             )
         };
+        if ret == -1 {
+            let err = io::Error::last_os_error();
+            return Err(match request {
+                PTRACE_SYSCALL => SeccompError::PtraceSyscall(pid, err),
+                _ => SeccompError::PtraceCont(pid, err),
+            });
+        }
+        Ok(())
+    }
 
+    /// leave the tracee in its group-stop but let it report the next one, when a `SIGCONT`
+    /// wakes it up (only for seized tracees)
+    pub fn listen(&self) -> Result<(), SeccompError> {
+        let ret = unsafe {
+            ptrace(
+                PTRACE_LISTEN,
+                self.process.get_pid(),
+                std::ptr::null_mut::<c_void>(),
+                std::ptr::null_mut::<c_void>(),
+            )
+        };
         if ret == -1 {
-            return Err(io::Error::last_os_error().into());
+            return Err(SeccompError::PtraceListen(
+                self.process.get_pid(),
+                io::Error::last_os_error(),
+            ));
         }
         Ok(())
     }
 
-    /// cont
-    pub fn continue_execution(&self) -> Result<(), SeccompError> {
-        unsafe {
+    /// stop tracing the tracee and let it run on its own
+    pub fn detach(&self) -> Result<(), SeccompError> {
+        let ret = unsafe {
             ptrace(
-                PTRACE_CONT,
+                PTRACE_DETACH,
                 self.process.get_pid(),
                 std::ptr::null_mut::<c_void>(),
-                0 as *mut c_void,
-            );
+                std::ptr::null_mut::<c_void>(),
+            )
+        };
+        if ret == -1 {
+            return Err(SeccompError::PtraceDetach(
+                self.process.get_pid(),
+                io::Error::last_os_error(),
+            ));
         }
         Ok(())
     }
 
     /// killing after ptrace traps the syscall
-    // TODO(x0rw): instead of killing facilitate setting orig_rax to -1 (-EPREM)
+    /// (to only refuse the syscall, handlers return `TraceAction::Deny` instead)
     pub fn kill_execution(&self) -> Result<(), SeccompError> {
         // println!("[Child-process] killing {}", self.process.get_pid());
         let kill_res = unsafe { kill(self.process.get_pid(), SIGKILL) };
@@ -421,26 +562,742 @@ impl PtraceWrapper {
                     PTRACE_KILL,
                     self.process.get_pid(),
                     std::ptr::null_mut::<c_void>(),
-                    0 as *mut c_void,
+                    std::ptr::null_mut::<c_void>(),
                 )
             };
         }
 
         Ok(())
     }
+    /// read `buf.len()` bytes at `addr` from the tracee address space
+    ///
+    /// uses `process_vm_readv` and falls back to `PTRACE_PEEKDATA` when the former is not
+    /// available (ENOSYS) or not permitted (EPERM, eg. under some LSMs)
+    pub fn read_memory(&self, addr: u64, buf: &mut [u8]) -> Result<(), SeccompError> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        let pid = self.process.get_pid();
+        let local = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut c_void,
+            iov_len: buf.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut c_void,
+            iov_len: buf.len(),
+        };
+
+        // SAFETY: `local` points to `buf` which is valid for `buf.len()` bytes, the remote
+        // iovec is only dereferenced by the kernel in the tracee address space
+        let ret = unsafe { libc::process_vm_readv(pid, &local, 1, &remote, 1, 0) };
+        if ret == -1 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::EFAULT) => Err(SeccompError::MemoryFault(pid, addr)),
+                Some(libc::ENOSYS) | Some(libc::EPERM) => self.peek_memory(addr, buf),
+                _ => Err(SeccompError::MemoryAccess(pid, err)),
+            };
+        }
+        // partial reads stop right at the first inaccessible page
+        let read = ret as usize;
+        if read < buf.len() {
+            return Err(SeccompError::MemoryFault(pid, addr + read as u64));
+        }
+        Ok(())
+    }
+
+    /// `PTRACE_PEEKDATA` fallback of `read_memory`, one aligned word at a time
+    fn peek_memory(&self, addr: u64, buf: &mut [u8]) -> Result<(), SeccompError> {
+        let pid = self.process.get_pid();
+        let word = std::mem::size_of::<libc::c_long>() as u64;
+        let mut cursor = addr & !(word - 1);
+        let mut filled = 0;
+
+        while filled < buf.len() {
+            // PEEKDATA returns the data itself so -1 is only an error if errno is set
+            // SAFETY: errno is thread local
+            unsafe { *libc::__errno_location() = 0 };
+            // SAFETY: PEEKDATA only reads the tracee memory, aligned words never cross a page
+            let data = unsafe {
+                ptrace(
+                    PTRACE_PEEKDATA,
+                    pid,
+                    cursor as *mut c_void,
+                    std::ptr::null_mut::<c_void>(),
+                )
+            };
+            if data == -1 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(0) => {}
+                    Some(libc::EIO) | Some(libc::EFAULT) => {
+                        return Err(SeccompError::MemoryFault(pid, cursor.max(addr)));
+                    }
+                    _ => return Err(SeccompError::MemoryAccess(pid, err)),
+                }
+            }
+
+            let bytes = data.to_ne_bytes();
+            let skip = addr.saturating_sub(cursor) as usize;
+            let len = (bytes.len() - skip).min(buf.len() - filled);
+            buf[filled..filled + len].copy_from_slice(&bytes[skip..skip + len]);
+            filled += len;
+            cursor += word;
+        }
+        Ok(())
+    }
+
+    /// write `data` at `addr` in the tracee address space
+    ///
+    /// uses `process_vm_writev` and falls back to `PTRACE_POKEDATA`, which unlike the former
+    /// can also patch read-only mappings (eg. a string literal passed to `openat`)
+    pub fn write_memory(&self, addr: u64, data: &[u8]) -> Result<(), SeccompError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let pid = self.process.get_pid();
+        let local = libc::iovec {
+            iov_base: data.as_ptr() as *mut c_void,
+            iov_len: data.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut c_void,
+            iov_len: data.len(),
+        };
+
+        // SAFETY: `local` points to `data` which is valid for `data.len()` bytes, the remote
+        // iovec is only dereferenced by the kernel in the tracee address space
+        let ret = unsafe { libc::process_vm_writev(pid, &local, 1, &remote, 1, 0) };
+        if ret == -1 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::EFAULT) | Some(libc::ENOSYS) | Some(libc::EPERM) => {
+                    self.poke_memory(addr, data)
+                }
+                _ => Err(SeccompError::MemoryAccess(pid, err)),
+            };
+        }
+        let written = ret as usize;
+        if written < data.len() {
+            return self.poke_memory(addr + written as u64, &data[written..]);
+        }
+        Ok(())
+    }
+
+    /// `PTRACE_POKEDATA` fallback of `write_memory`, one aligned word at a time
+    fn poke_memory(&self, addr: u64, data: &[u8]) -> Result<(), SeccompError> {
+        let pid = self.process.get_pid();
+        let word = std::mem::size_of::<libc::c_long>();
+        let mut cursor = addr & !(word as u64 - 1);
+        let mut written = 0;
+
+        while written < data.len() {
+            let skip = addr.saturating_sub(cursor) as usize;
+            let len = (word - skip).min(data.len() - written);
+
+            // words only partially covered by `data` keep the rest of their bytes
+            let mut bytes = [0u8; std::mem::size_of::<libc::c_long>()];
+            if len < word {
+                self.peek_memory(cursor, &mut bytes)?;
+            }
+            bytes[skip..skip + len].copy_from_slice(&data[written..written + len]);
+
+            // SAFETY: POKEDATA only writes the tracee memory, aligned words never cross a page
+            let ret = unsafe {
+                ptrace(
+                    PTRACE_POKEDATA,
+                    pid,
+                    cursor as *mut c_void,
+                    libc::c_long::from_ne_bytes(bytes) as *mut c_void,
+                )
+            };
+            if ret == -1 {
+                let err = io::Error::last_os_error();
+                return match err.raw_os_error() {
+                    Some(libc::EIO) | Some(libc::EFAULT) => {
+                        Err(SeccompError::MemoryFault(pid, cursor.max(addr)))
+                    }
+                    _ => Err(SeccompError::MemoryAccess(pid, err)),
+                };
+            }
+            written += len;
+            cursor += word as u64;
+        }
+        Ok(())
+    }
+
+    /// run `syscall(args)` in the tracee and return its raw result
+    ///
+    /// must be called while the tracee sits in the seccomp stop of another syscall,
+    /// that syscall is then restarted so the tracee ends up in the exact same stop
+    pub(crate) fn inject_syscall(
+        &self,
+        regs: &Registers,
+        syscall: Syscall,
+        args: [u64; 6],
+    ) -> Result<i64, SeccompError> {
+        let pid = self.process.get_pid();
+        let injection_err = |err: io::Error| SeccompError::SyscallInjection(pid, syscall, err);
+
+        let mut injected = regs.clone();
+        injected.set_syscall_number(syscall as u64);
+        for (n, arg) in args.into_iter().enumerate() {
+            injected.set_arg(n, arg);
+        }
+        injected.commit_regs(pid)?;
+
+        let mut signals = Vec::new();
+        let result = self.run_injected(regs, &mut signals, injection_err);
+        self.reraise(signals);
+        let ret = result?;
+
+        if let Some(Errno(errno)) = Errno::from_return_value(ret) {
+            return Err(injection_err(io::Error::from_raw_os_error(errno)));
+        }
+        Ok(ret)
+    }
+
+    /// run the syscall committed by `inject_syscall` and restart the original one of `regs`,
+    /// return the raw result of the injected syscall
+    fn run_injected(
+        &self,
+        regs: &Registers,
+        signals: &mut Vec<c_int>,
+        injection_err: impl Fn(io::Error) -> SeccompError,
+    ) -> Result<i64, SeccompError> {
+        // the injected syscall may hit its own seccomp stop before its exit stop
+        self.resume_until(|status| WSTOPSIG(status) == SIGTRAP | 0x80, signals)
+            .map_err(&injection_err)?;
+        let ret = self.get_registers()?.return_value() as i64;
+
+        // put the original syscall back and run it up to its seccomp stop again
+        let mut restart = regs.clone();
+        restart.rewind_syscall();
+        restart.commit_regs(self.process.get_pid())?;
+        self.resume_until(
+            |status| WSTOPSIG(status) == SIGTRAP && (status >> 16) == libc::PTRACE_EVENT_SECCOMP,
+            signals,
+        )
+        .map_err(injection_err)?;
+        Ok(ret)
+    }
+
+    /// keep resuming the tracee with `PTRACE_SYSCALL` until it reaches the stop matched by `stop`
+    ///
+    /// the signals delivered on the way are held back in `signals`, hand them to `reraise`
+    /// once the tracee is where it should be
+    fn resume_until(
+        &self,
+        stop: impl Fn(i32) -> bool,
+        signals: &mut Vec<c_int>,
+    ) -> Result<(), io::Error> {
+        let pid = self.process.get_pid();
+        let mut status = 0;
+        loop {
+            // SAFETY: plain ptrace/waitpid calls on our own tracee
+            if unsafe {
+                ptrace(
+                    PTRACE_SYSCALL,
+                    pid,
+                    std::ptr::null_mut::<c_void>(),
+                    std::ptr::null_mut::<c_void>(),
+                )
+            } == -1
+            {
+                return Err(io::Error::last_os_error());
+            }
+            if unsafe { waitpid(pid, &mut status, 0) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            // the tracee exited
+            if !WIFSTOPPED(status) {
+                return Err(io::Error::from_raw_os_error(libc::ESRCH));
+            }
+            if stop(status) {
+                return Ok(());
+            }
+            // syscall stops are `SIGTRAP | 0x80` and the event stops have the event above
+            if (status >> 16) == 0 && WSTOPSIG(status) != SIGTRAP | 0x80 {
+                signals.push(WSTOPSIG(status));
+            }
+        }
+    }
+
+    /// send the `signals` held back by `resume_until` again, the tracee reports them in the
+    /// usual signal-delivery stops as soon as it resumes
+    ///
+    /// a tracer can't queue a signal with the siginfo of the kernel, the tracee sees them
+    /// sent by `tgkill`
+    fn reraise(&self, signals: Vec<c_int>) {
+        let pid = self.process.get_pid();
+        for signal in signals {
+            // SAFETY: only signals our own tracee
+            if unsafe { libc::syscall(libc::SYS_tgkill, pid, pid, signal) } == -1 {
+                restrict_error!(
+                    "Failed to raise signal {} again in process {}: {}",
+                    signal,
+                    pid,
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+
+    /// address of the `kind` entry (`AT_*`) of the auxiliary vector of the tracee, its value
+    /// is the next word
+    ///
+    /// only works at the `PTRACE_EVENT_EXEC` stop, the stack pointer is then at `argc`
+    /// followed by the NULL terminated argv and envp and the auxiliary vector
+    pub(crate) fn auxv_entry(&self, kind: u64) -> Result<Option<u64>, SeccompError> {
+        let mut addr = self.get_registers()?.stack_pointer();
+        addr += (self.read_word(addr)? + 2) * 8;
+        while self.read_word(addr)? != 0 {
+            addr += 8;
+        }
+        addr += 8;
+        loop {
+            match self.read_word(addr)? {
+                libc::AT_NULL => return Ok(None),
+                entry if entry == kind => return Ok(Some(addr)),
+                _ => addr += 16,
+            }
+        }
+    }
+
+    /// read the 64 bits word at `addr`
+    pub(crate) fn read_word(&self, addr: u64) -> Result<u64, SeccompError> {
+        let mut buf = [0; 8];
+        self.read_memory(addr, &mut buf)?;
+        Ok(u64::from_ne_bytes(buf))
+    }
+
+    /// turn the `AT_SYSINFO_EHDR` entry of the auxiliary vector into `AT_IGNORE`, so the libc
+    /// of the tracee doesn't find the vDSO and makes real syscalls to read the clocks
+    pub(crate) fn hide_vdso(&self) -> Result<(), SeccompError> {
+        match self.auxv_entry(libc::AT_SYSINFO_EHDR)? {
+            Some(entry) => self.write_memory(entry, &libc::AT_IGNORE.to_ne_bytes()),
+            None => Ok(()),
+        }
+    }
+
     /// syscall tracing
     pub fn syscall_trace(&self) -> Result<(), SeccompError> {
-        unsafe {
-            ptrace(
-                PTRACE_SYSCALL,
-                self.process.get_pid(),
-                std::ptr::null_mut::<c_void>(),
-                0 as *mut c_void,
-            );
+        self.resume_with_signal(true, 0)
+    }
+}
+/// handler run at the exec stops of the tracee, eg. to edit its auxiliary vector
+pub(crate) type ExecHandler = Box<dyn FnMut(&PtraceWrapper) -> Result<(), SeccompError>>;
+
+/// handler of `Policy::on_signal`
+pub(crate) type SignalHandler<C> = Box<dyn FnMut(c_int, &mut C) -> SignalAction>;
+
+/// The handlers of a policy, looked up by the event loop
+pub(crate) struct Handlers<C> {
+    /// `trace` handlers, run at the seccomp stop
+    pub(crate) tracers: TracerMap<C>,
+    /// `entry_intercept` handlers, run at the seccomp stop after the tracers
+    pub(crate) entry: InterceptorMap<C>,
+    /// `exit_intercept` handlers, run at the syscall exit stop
+    pub(crate) exit: InterceptorMap<C>,
+    /// `on_signal` handlers with their signal, run at the signal-delivery stop
+    pub(crate) signals: Vec<(c_int, SignalHandler<C>)>,
+    /// run at the exec stops, before the new program starts
+    pub(crate) exec: Vec<ExecHandler>,
+    /// fd table of `Policy::track_fds`, handed to every interceptor
+    pub(crate) fds: Option<Rc<RefCell<FdTable>>>,
+}
+
+/// What watches the syscalls of the tracee without deciding anything
+#[derive(Default)]
+pub(crate) struct Observers {
+    /// `Policy::trace_all_pretty`
+    pub(crate) pretty: Option<PrettyTracer>,
+    /// `Policy::audit`, only sees the syscalls reaching the handlers
+    pub(crate) audit: Option<Auditor>,
+    /// `Policy::event_stream`
+    pub(crate) events: Option<EventPublisher>,
+    /// `Policy::runtime_metrics`
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<RuntimeMetrics>,
+}
+
+impl Observers {
+    /// whether the tracee must stop at the entry and exit of every syscall, not only at the
+    /// seccomp `TRACE` rules
+    pub(crate) fn trace_all(&self) -> bool {
+        self.pretty.is_some() || self.events.is_some()
+    }
+
+    /// whether an observer needs the exit stop of the syscalls reaching the handlers
+    fn wait_exit(&self) -> bool {
+        #[cfg(feature = "metrics")]
+        if self.metrics.is_some() {
+            return true;
+        }
+        self.audit.is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.trace_all() && !self.wait_exit()
+    }
+
+    /// a syscall entry seen by every stop, the seccomp stop following the entry stop of the
+    /// same syscall is ignored
+    fn entry(&mut self, interceptor: &Interceptor) {
+        if let Some(pretty) = self.pretty.as_mut() {
+            pretty.entry(interceptor);
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.entry(interceptor);
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.entry(interceptor);
+        }
+    }
+
+    /// the handler registered by `rule` decided `verdict` for the syscall being handled
+    fn decided(&mut self, verdict: Verdict, rule: Option<usize>) {
+        if let Some(audit) = self.audit.as_mut() {
+            audit.decided(verdict, rule);
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.decided(verdict, rule);
+        }
+    }
+
+    /// the syscall being handled won't stop at its exit
+    fn no_exit(&mut self) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.no_exit();
+        }
+    }
+
+    /// a syscall exit, once its result is final
+    fn exit(&mut self, interceptor: &Interceptor) -> Result<(), SeccompError> {
+        if let Some(pretty) = self.pretty.as_mut() {
+            pretty.exit(interceptor)?;
+        }
+        if let Some(audit) = self.audit.as_mut() {
+            audit.exit(interceptor)?;
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.exit(interceptor);
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.exit();
         }
         Ok(())
     }
 }
+
+/// State of `PtraceWrapper::event_loop` between two stops of the tracee
+struct EventLoop<'a, C> {
+    wrapper: PtraceWrapper,
+    handlers: Handlers<C>,
+    observers: Observers,
+    trace_all: bool,
+    ctx: &'a mut C,
+    in_syscall: bool,
+    /// arguments of the syscall in progress, the exit handlers can't read them all from the
+    /// registers on every architecture (see `Registers::arg`)
+    entry_args: [u64; 6],
+    /// syscall skipped at entry by `Deny`/`Return`, and the value it returns at exit
+    skipped: Option<(Syscall, i64)>,
+    /// the exit interceptors of the syscall in progress were skipped by `SkipExit`, its exit
+    /// stop only happens for the observers of `trace_all`
+    skip_exit: bool,
+    /// the syscall in progress was resumed to stop at its exit, which an exec in between
+    /// must keep
+    wait_exit: bool,
+    /// a handler killed the tracee with `TraceAction::Kill`
+    policy_kill: bool,
+}
+
+impl<C> EventLoop<'_, C> {
+    /// run the handlers of a stop and resume the tracee
+    fn handle_stop(&mut self, status: libc::c_int) -> Result<(), SeccompError> {
+        let sig = WSTOPSIG(status);
+        if sig == SIGTRAP | 0x80 {
+            if self.in_syscall {
+                self.in_syscall = false;
+                self.wait_exit = false;
+                self.syscall_exit()?;
+            } else {
+                self.in_syscall = true;
+                // If it's tracked by seccomp the seccomp stop will catch it
+                let regs = self.wrapper.get_registers()?;
+                self.entry_args = regs.args();
+                if let Ok(syscall) = Syscall::try_from(regs.syscall_number() as i32) {
+                    let child = self.wrapper.get_process().get_pid();
+                    self.observers
+                        .entry(&Interceptor::new(syscall, regs, child));
+                }
+            }
+            // resume exactly once per stop, resuming twice races with the
+            // next stop of the tracee and desyncs `in_syscall`
+            self.wrapper.resume(self.trace_all)
+        } else if sig == SIGTRAP && (status >> 16) == libc::PTRACE_EVENT_SECCOMP {
+            // seccomp stops only happen at syscall entry, even if the entry
+            // stop was missed because the tracee was resumed with PTRACE_CONT
+            self.in_syscall = true;
+            self.seccomp_stop()
+        } else if sig == SIGTRAP && (status >> 16) == libc::PTRACE_EVENT_EXEC {
+            for handler in &mut self.handlers.exec {
+                handler(&self.wrapper)?;
+            }
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = self.observers.metrics.as_mut() {
+                metrics.exec(self.wrapper.get_process().get_pid());
+            }
+            // the exec stop comes between the seccomp stop of `execve` and its exit stop
+            self.wrapper.resume(self.trace_all || self.wait_exit)
+        } else if (status >> 16) == PTRACE_EVENT_STOP {
+            match sig {
+                // group-stop: keep the tracee stopped until a SIGCONT
+                SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => self.wrapper.listen(),
+                // the tracee was woken by a SIGCONT, it is delivered next
+                _ => self.wrapper.resume(self.trace_all),
+            }
+        } else {
+            self.signal_delivery(sig)
+        }
+    }
+
+    /// the signal-delivery stop of `signal`, runs its `on_signal` handlers then resumes the
+    /// tracee with the signal they chose
+    fn signal_delivery(&mut self, signal: c_int) -> Result<(), SeccompError> {
+        // the chain stops at the first handler that doesn't `Deliver`
+        let mut action = SignalAction::Deliver;
+        for (_, handler) in self
+            .handlers
+            .signals
+            .iter_mut()
+            .filter(|(sig, _)| *sig == signal)
+        {
+            action = handler(signal, self.ctx);
+            if !matches!(action, SignalAction::Deliver) {
+                break;
+            }
+        }
+        let signal = match action {
+            SignalAction::Deliver => signal,
+            SignalAction::Suppress => 0,
+            SignalAction::Replace(signal) => signal,
+        };
+        self.wrapper.resume_with_signal(self.trace_all, signal)
+    }
+
+    /// the exit stop of a syscall, runs the exit interceptors
+    fn syscall_exit(&mut self) -> Result<(), SeccompError> {
+        let child = self.wrapper.get_process().get_pid();
+        let mut regs = self.wrapper.get_registers()?;
+
+        // a skipped syscall reports -1 as its number at exit
+        let caught_syscall = match self.skipped.take() {
+            Some((syscall, ret)) => {
+                regs.set_return_value(ret as u64);
+                regs.commit_regs(child)?;
+                syscall
+            }
+            None => match Syscall::try_from(regs.syscall_number() as i32) {
+                Ok(syscall) => syscall,
+                // numbers unknown to the headers the crate was built with have no handler
+                Err(_) => return Ok(()),
+            },
+        };
+
+        // the chain stops at the first handler that doesn't `Continue`
+        let scratch = Rc::default();
+        let handlers = match std::mem::take(&mut self.skip_exit) {
+            true => &mut [],
+            false => self.handlers.exit.handlers(caught_syscall),
+        };
+        let mut action = TraceAction::Continue;
+        let mut decided_by = None;
+        for (n, handler) in handlers.iter_mut().enumerate() {
+            // the next handlers see the registers committed by the previous
+            if n > 0 {
+                regs = self.wrapper.get_registers()?;
+            }
+            let interceptor = Interceptor::new(caught_syscall, regs.clone(), child)
+                .sharing_scratch(&scratch)
+                .with_fds(&self.handlers.fds)
+                .with_entry_args(self.entry_args)
+                .at_exit();
+            action = handler(interceptor, self.ctx);
+            if !matches!(action, TraceAction::Continue) {
+                decided_by = Some(n);
+                break;
+            }
+        }
+        if let Some(n) = decided_by {
+            let rule = self.handlers.exit.rule(caught_syscall, n);
+            self.observers.decided(Verdict::of(&action), rule);
+        }
+        if let TraceAction::Kill = action {
+            // the next wait reports the tracee killed and ends the loop
+            self.policy_kill = true;
+            return self.wrapper.kill_execution();
+        }
+        // the syscall already ran, only its result can change
+        if let Some(ret) = action.forced_return() {
+            regs.set_return_value(ret as u64);
+            regs.commit_regs(child)?;
+        }
+        if !self.observers.is_empty() {
+            let interceptor = Interceptor::new(caught_syscall, regs, child)
+                .with_entry_args(self.entry_args)
+                .at_exit();
+            self.observers.exit(&interceptor)?;
+        }
+        Ok(())
+    }
+
+    /// the seccomp stop of a syscall with a `TRACE` rule, runs the tracers and the entry
+    /// interceptors then resumes the tracee
+    fn seccomp_stop(&mut self) -> Result<(), SeccompError> {
+        let child = self.wrapper.get_process().get_pid();
+        let regs = self.wrapper.get_registers()?;
+        self.entry_args = regs.args();
+        self.wait_exit = false;
+
+        let Ok(caught_syscall) = Syscall::try_from(regs.syscall_number() as i32) else {
+            // numbers unknown to the headers the crate was built with have no handler
+            return self.wrapper.resume(self.trace_all);
+        };
+        if !self.observers.is_empty() {
+            let interceptor = Interceptor::new(caught_syscall, regs.clone(), child);
+            self.observers.entry(&interceptor);
+            if let Some(audit) = self.observers.audit.as_mut() {
+                audit.entry(&interceptor)?;
+            }
+        }
+
+        // the tracers then the interceptors run in registration order, the
+        // chain stops at the first handler that doesn't `Continue`
+        let mut action = TraceAction::Continue;
+        let mut decided_by = None;
+        for (n, handler) in self
+            .handlers
+            .tracers
+            .handlers(caught_syscall)
+            .iter_mut()
+            .enumerate()
+        {
+            action = handler(caught_syscall, self.ctx);
+            if !matches!(action, TraceAction::Continue) {
+                decided_by = Some(n);
+                break;
+            }
+        }
+        let mut rule = decided_by.and_then(|n| self.handlers.tracers.rule(caught_syscall, n));
+        if let TraceAction::Continue = action {
+            let scratch = Rc::default();
+            let handlers = self.handlers.entry.handlers(caught_syscall);
+            for (n, handler) in handlers.iter_mut().enumerate() {
+                // the next handlers see the registers committed by the previous
+                let regs = match n {
+                    0 => regs.clone(),
+                    _ => self.wrapper.get_registers()?,
+                };
+                let interceptor = Interceptor::new(caught_syscall, regs, child)
+                    .sharing_scratch(&scratch)
+                    .with_fds(&self.handlers.fds);
+                action = handler(interceptor, self.ctx);
+                if !matches!(action, TraceAction::Continue) {
+                    decided_by = Some(n);
+                    break;
+                }
+            }
+            // the syscall runs with the arguments the interceptors committed
+            if !handlers.is_empty() {
+                self.entry_args = self.wrapper.get_registers()?.args();
+            }
+            rule = decided_by.and_then(|n| self.handlers.entry.rule(caught_syscall, n));
+        }
+        self.observers.decided(Verdict::of(&action), rule);
+
+        match action {
+            // only stop at the exit if an exit interceptor waits for it, or to observe the result
+            TraceAction::Continue => {
+                self.wait_exit =
+                    self.observers.wait_exit() || self.handlers.exit.contains(caught_syscall);
+                self.wrapper.resume(self.trace_all || self.wait_exit)
+            }
+            // the next wait reports the tracee killed and ends the loop
+            TraceAction::Kill => {
+                self.observers.no_exit();
+                self.policy_kill = true;
+                self.wrapper.kill_execution()
+            }
+            TraceAction::SkipExit => {
+                // the observers of every syscall still see the exit, only the exit
+                // interceptors are skipped
+                match self.trace_all {
+                    true => self.skip_exit = true,
+                    false => self.observers.no_exit(),
+                }
+                self.wrapper.resume(self.trace_all)
+            }
+            TraceAction::Deny(_) | TraceAction::Return(_) => {
+                let ret = action.forced_return().unwrap_or_default();
+                // re-read the registers, the interceptor may have committed
+                // changes to them
+                self.wrapper
+                    .skip_syscall(self.wrapper.get_registers()?, ret)?;
+                self.skipped = Some((caught_syscall, ret));
+                self.wait_exit = true;
+                // the result is written at the exit stop
+                self.wrapper.syscall_trace()
+            }
+        }
+    }
+}
+
+/// What the tracer does when handling a stop of the tracee fails (eg. a ptrace request
+/// or the writer of `trace_all_pretty` returned an error)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// kill the tracee, its syscalls can't be checked anymore
+    #[default]
+    Kill,
+    /// stop tracing and let the tracee run, the syscalls with a seccomp `TRACE` rule
+    /// then fail with `ENOSYS`
+    Detach,
+    /// ignore the error and resume the tracee as if the stop was handled
+    Continue,
+}
+
+/// How the traced process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// exited with this status code
+    Exited(i32),
+    /// killed by this signal, including the `SIGSYS` of a `deny` rule
+    Signaled(c_int),
+    /// killed by a handler returning `TraceAction::Kill`
+    PolicyKill,
+    /// killed for running longer than `Policy::time_limit`
+    TimeLimit,
+    /// killed for using more CPU time than `Policy::cpu_limit`
+    CpuLimit,
+}
+
+/// Action taken by an `on_signal` handler before the signal reaches the tracee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalAction {
+    /// deliver the signal as is
+    Deliver,
+    /// drop the signal, the tracee never sees it
+    Suppress,
+    /// deliver this signal instead
+    Replace(c_int),
+}
+
 /// Action taken by the handler function after a syscall is caught
 #[derive(Debug)]
 pub enum TraceAction {
@@ -448,8 +1305,25 @@ pub enum TraceAction {
     Continue,
     /// kill the target syscall process
     Kill,
-    /// Skip exit
+    /// continue without stopping at the syscall exit, even if an `exit_intercept` is registered
     SkipExit,
+    /// skip the syscall and make it fail with this errno, only this call is affected
+    /// (from `exit_intercept` the syscall already ran, only its return value is replaced)
+    Deny(Errno),
+    /// skip the syscall and make it return this value instead
+    /// (from `exit_intercept` the syscall already ran, only its return value is replaced)
+    Return(i64),
+}
+
+impl TraceAction {
+    /// the return value the syscall is replaced with, if any
+    fn forced_return(&self) -> Option<i64> {
+        match self {
+            TraceAction::Deny(errno) => Some(errno.as_return_value()),
+            TraceAction::Return(val) => Some(*val),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -476,4 +1350,84 @@ mod tests {
             Ok(_) => panic!("Expected an error for an invalid syscall, but got Ok"),
         }
     }
+
+    #[test]
+    fn tracee_gone_errors() {
+        let esrch = || io::Error::from_raw_os_error(libc::ESRCH);
+        assert!(SeccompError::IO(esrch()).is_tracee_gone());
+        assert!(SeccompError::PtraceCont(1, esrch()).is_tracee_gone());
+        assert!(SeccompError::SyscallInjection(1, Syscall::Mmap, esrch()).is_tracee_gone());
+        assert!(!SeccompError::IO(io::Error::from_raw_os_error(libc::EPERM)).is_tracee_gone());
+        assert!(!SeccompError::MemoryFault(1, 0).is_tracee_gone());
+        assert_eq!(OnError::default(), OnError::Kill);
+    }
+
+    #[test]
+    fn add_rule_errors_from_libseccomp() {
+        let ctx = SeccompWrapper::init_context(Action::Allow).unwrap();
+        // allowing in an allow-all filter
+        match ctx.add_rule(Action::Allow, Syscall::Read) {
+            Err(SeccompError::RuleMatchesDefault {
+                syscall: Syscall::Read,
+                action: Action::Allow,
+                errno,
+            }) => assert_eq!(errno, -libc::EACCES),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+        // the error carries the errno name
+        let err = ctx.add_rule(Action::Allow, Syscall::Write).unwrap_err();
+        assert_eq!(err.errno(), Some(Errno(libc::EACCES)));
+        assert!(err.to_string().ends_with("(-EACCES)"), "{err}");
+    }
+
+    #[test]
+    fn add_rule_errno_mapping() {
+        let err = |errno: i32| SeccompError::add_rule(Syscall::Openat, Action::Kill, -errno);
+        assert!(matches!(
+            err(libc::EEXIST),
+            SeccompError::DuplicateRule { .. }
+        ));
+        assert!(matches!(
+            err(libc::EACCES),
+            SeccompError::RuleMatchesDefault { .. }
+        ));
+        for errno in [libc::EINVAL, libc::EDOM, libc::EOPNOTSUPP] {
+            assert!(matches!(err(errno), SeccompError::InvalidRule { .. }));
+        }
+        for errno in [libc::ENOMEM, libc::EFAULT, libc::E2BIG] {
+            match err(errno) {
+                SeccompError::AddRuleFailed {
+                    syscall: Syscall::Openat,
+                    action: Action::Kill,
+                    errno: code,
+                    ..
+                } => assert_eq!(code, -errno),
+                other => panic!("Unexpected error: {other:?}"),
+            }
+        }
+        assert_eq!(
+            err(libc::EDOM).to_string(),
+            "Invalid Kill rule for Openat: the syscall does not exist on the architecture \
+             of the filter (-EDOM)"
+        );
+    }
+
+    #[test]
+    fn load_errno_mapping() {
+        let err = |errno: i32| SeccompError::load(-errno);
+        let denied = err(libc::EACCES);
+        assert!(
+            matches!(denied, SeccompError::LoadPermissionDenied { errno } if errno == -libc::EACCES)
+        );
+        assert!(denied.to_string().contains("NO_NEW_PRIVS"), "{denied}");
+        for errno in [libc::EFAULT, libc::ECANCELED] {
+            assert!(matches!(err(errno), SeccompError::LibSeccompError { .. }));
+        }
+        for errno in [libc::EINVAL, libc::ENOMEM, libc::ESRCH, libc::EPERM, 4000] {
+            assert!(matches!(err(errno), SeccompError::LoadFailed { .. }));
+            assert_eq!(err(errno).errno(), Some(Errno(errno)));
+        }
+        assert!(err(4000).to_string().ends_with("(-4000)"));
+        assert_eq!(SeccompError::Fork.errno(), None);
+    }
 }
@@ -58,3 +58,359 @@ fn test_custom_errno() {
 #[cfg(target_arch = "x86_64")]
 #[test]
 fn test_trace_interception() {}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn read_tracee_memory_test() {
+    let mut cmd = Command::cargo_bin("read_memory").unwrap();
+    cmd.assert().success().stdout(
+        "openat: /restrict/read_memory/test.txt\n\
+         openat: fault at 0x8\n\
+         openat: /dev/null\n\
+         writev: hello world\n\
+         nanosleep: 0s 1000ns\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn write_tracee_memory_test() {
+    let mut cmd = Command::cargo_bin("write_memory").unwrap();
+    cmd.assert().success().stdout(
+        "stack: true\n\
+         mmap: true\n\
+         mmap signaled: true\n\
+         patched: true\n\
+         signal delivered: true\n\
+         sysname: restrict\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn deny_and_return_test() {
+    let mut cmd = Command::cargo_bin("deny_return").unwrap();
+    cmd.assert().success().stdout(
+        "getppid: 4242\n\
+         open /dev/null: Err(Some(13))\n\
+         open /dev/zero: Ok(())\n\
+         mkdir: 0, created: false\n\
+         getpid: -1 Some(1)\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn pretty_trace_test() {
+    let mut cmd = Command::cargo_bin("pretty_trace").unwrap();
+    let output = cmd.output().expect("failed to run pretty_trace");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "pretty\n");
+
+    let trace = String::from_utf8_lossy(&output.stderr);
+    for line in [
+        "openat(AT_FDCWD, \"/dev/null\", O_RDONLY|O_CLOEXEC) = 3",
+        "openat(AT_FDCWD, \"/restrict/pretty_trace/missing.txt\", O_RDONLY|O_CLOEXEC) = -1 ENOENT (No such file or directory)",
+        "write(1, \"pretty\\n\", 7) = 7",
+        "exit_group(0) = ?",
+        "+++ exited with 0 +++",
+    ] {
+        assert!(trace.lines().any(|l| l == line), "missing `{line}` in:\n{trace}");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn context_returned_after_run_test() {
+    let mut cmd = Command::cargo_bin("context_stats").unwrap();
+    cmd.assert().success().stdout(
+        "one\n\
+         two\n\
+         close #1\n\
+         writes: 2\n\
+         opened: /dev/null, /restrict/context_stats/missing\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn chained_and_wildcard_handlers_test() {
+    let mut cmd = Command::cargo_bin("chained_handlers").unwrap();
+    cmd.assert().success().stdout(
+        "getppid: 4242\n\
+         getuid: -1 Some(1)\n\
+         getgid: 77\n\
+         getppid chain: first, second\n\
+         writes: [3, 3]\n\
+         ids: 2\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn unknown_syscall_passed_through_test() {
+    let mut cmd = Command::cargo_bin("on_error").unwrap();
+    cmd.assert()
+        .success()
+        .stdout("syscall 1000: -1 Some(38)\nrun: Ok(())\n");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn tracee_gone_during_stop_test() {
+    let mut cmd = Command::cargo_bin("on_error").unwrap();
+    cmd.arg("gone").assert().success().stdout("run: Ok(())\n");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn on_error_kill_test() {
+    let mut cmd = Command::cargo_bin("on_error").unwrap();
+    cmd.arg("kill").assert().success().stdout(
+        "run: Err(\"IO error occured: Custom { kind: Other, error: \\\"broken writer\\\" }\")\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn on_error_detach_test() {
+    let mut cmd = Command::cargo_bin("on_error").unwrap();
+    cmd.arg("detach").assert().success().stdout(
+        "tracee done\n\
+         run: Err(\"IO error occured: Custom { kind: Other, error: \\\"broken writer\\\" }\")\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn on_error_continue_test() {
+    let mut cmd = Command::cargo_bin("on_error").unwrap();
+    cmd.arg("continue")
+        .assert()
+        .success()
+        .stdout("tracee done\nrun: Ok(())\n");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn signal_delivery_test() {
+    let mut cmd = Command::cargo_bin("signals").unwrap();
+    cmd.assert().success().stdout(
+        "usr1 handled: 1\n\
+         usr2 suppressed\n\
+         hup replaced: 2\n\
+         stopped until SIGCONT: true\n\
+         usr1 seen: 1, cont seen: 1\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn time_and_cpu_limits_test() {
+    let mut cmd = Command::cargo_bin("limits").unwrap();
+    cmd.assert().success().stdout(
+        "sleep: TimeLimit, killed early: true\n\
+         spin: TimeLimit\n\
+         exit: Exited(3), waited: false\n\
+         busy loop: CpuLimit\n\
+         getppid: PolicyKill\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn quota_and_rate_limit_test() {
+    let mut cmd = Command::cargo_bin("quotas").unwrap();
+    cmd.assert().success().stdout(
+        "quota: ok, ok, Some(11)\n\
+         burst: ok, ok, ok, Some(16), Some(16)\n\
+         refilled: ok\n\
+         logged: ok\n\
+         outcome: PolicyKill\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn fault_injection_test() {
+    let mut cmd = Command::cargo_bin("faults").unwrap();
+    let tracee = "nth: ok, Some(4), ok\nsome failed: true\nshort: true\n";
+    cmd.assert().success().stdout(format!(
+        "{tracee}{tracee}\
+         getppid #2: EINTR (Interrupted system call)\n\
+         getpid #2: EIO (Input/output error)\n\
+         getpid #3: EIO (Input/output error)\n\
+         getpid #5: ENOMEM (Cannot allocate memory)\n\
+         getpid #6: ENOMEM (Cannot allocate memory)\n\
+         getpid #7: EIO (Input/output error)\n\
+         getpid #11: EIO (Input/output error)\n\
+         getpid #12: EIO (Input/output error)\n\
+         getpid #15: ENOMEM (Cannot allocate memory)\n\
+         getpid #17: EIO (Input/output error)\n\
+         pwrite64 #1: short 100 -> 79\n\
+         reproducible: true\n"
+    ));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn virtual_clock_test() {
+    let mut cmd = Command::cargo_bin("virtual_clock").unwrap();
+    cmd.assert().success().stdout(
+        "time: 1000000000\n\
+         clock_gettime: 1000000000.0\n\
+         gettimeofday: 1000000000\n\
+         date: 1000000000\n\
+         offset: -3600\n\
+         slept for real: true\n\
+         slept virtually: true\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn deterministic_random_test() {
+    let run = |seed: &str| {
+        let mut cmd = Command::cargo_bin("random").unwrap();
+        let output = cmd.arg(seed).assert().success();
+        String::from_utf8_lossy(&output.get_output().stdout).into_owned()
+    };
+    let (seeded, again, other) = (run("42"), run("42"), run("43"));
+    assert_eq!(seeded.lines().count(), 9);
+    // the same seed gives the same bytes, another seed other bytes
+    assert_eq!(seeded, again);
+    for (line, other) in seeded.lines().zip(other.lines()) {
+        assert_ne!(line, other);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn remap_path_test() {
+    let mut cmd = Command::cargo_bin("remap").unwrap();
+    cmd.assert().success().stdout(
+        "resolv.conf: nameserver 10.0.0.1\n\
+         is dir: true\n\
+         relative: hello\n\
+         nameserver 10.0.0.1\n\
+         written to: hello\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn network_policy_test() {
+    let mut cmd = Command::cargo_bin("network").unwrap();
+    cmd.assert().success().stdout(
+        "tcp allowed: ok\n\
+         tcp other port: Some(111)\n\
+         bind denied: Some(13)\n\
+         sendto allowed: ok\n\
+         sendto other: Some(111)\n\
+         sendmsg other: Some(111)\n\
+         sendmmsg other: Some(111)\n\
+         connect bad address: Some(13)\n\
+         unix allowed: ok\n\
+         unix other: Some(13)\n\
+         unix dotdot: Some(13)\n\
+         unix relative dotdot: Some(13)\n\
+         abstract allowed: ok\n\
+         abstract other: Some(13)\n\
+         received: hi\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn exec_policy_test() {
+    let mut cmd = Command::cargo_bin("exec_policy").unwrap();
+    cmd.assert()
+        .success()
+        .stdout("ls: Some(13)\nmissing: Some(13)\nexec LD_PRELOAD: None\nFOO=bar\n");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn fd_table_test() {
+    let mut cmd = Command::cargo_bin("fd_table").unwrap();
+    cmd.assert().success().stdout(
+        "file: ok\n\
+         dup: ok\n\
+         /dev/null: Some(13)\n\
+         reopened: ok\n\
+         socket: ok\n\
+         pipe: ok\n\
+         file out: 2\n\
+         file out: 2\n\
+         file /dev/null: -13\n\
+         file out: 2\n\
+         socket listener: 5\n\
+         pipe: 2\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn audit_test() {
+    let mut cmd = Command::cargo_bin("audit").unwrap();
+    cmd.assert().success().stdout(
+        "openat [\"AT_FDCWD\", \"\\\"/nonexistent/secret\\\"\", \"O_RDONLY\"] deny rule=Some(0) ret=Some(-13)\n\
+         getppid [] return rule=Some(1) ret=Some(4242)\n\
+         getpid [] allow rule=None ret=pid\n\
+         getuid [] return rule=Some(3) ret=Some(0)\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn event_stream_test() {
+    let mut cmd = Command::cargo_bin("event_stream").unwrap();
+    cmd.assert().success().stdout(
+        "blocking: entry ret=None\n\
+         blocking: exit parent=true\n\
+         blocking: entry ret=None\n\
+         blocking: exit parent=true\n\
+         lossy dropped some: true\n\
+         lossy received: 1\n\
+         skip exit: Getpid Entry\n\
+         skip exit: Getpid Exit\n\
+         skip exit: Getppid Entry\n\
+         skip exit: Getppid Exit\n",
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn exec_exit_test() {
+    let mut cmd = Command::cargo_bin("exec_exit").unwrap();
+    cmd.assert().success().stdout("execve exits: [0]\n");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn feature_rules_test() {
+    let mut cmd = Command::cargo_bin("feature_rules").unwrap();
+    cmd.assert().success().stdout(
+        "track_fds allowed: Exited(0)\n\
+         track_fds failed: Exited(1)\n\
+         track_fds default: Signaled(31)\n\
+         remap_path allowed: Exited(0)\n\
+         remap_path failed: Exited(1)\n\
+         remap_path default: Signaled(31)\n\
+         inject_faults allowed: Exited(5)\n\
+         inject_faults failed: Exited(1)\n\
+         inject_faults default: Signaled(31)\n\
+         virtual_clock allowed: Exited(0)\n\
+         virtual_clock failed: Exited(1)\n\
+         virtual_clock default: Signaled(31)\n\
+         deterministic_random allowed: true\n\
+         deterministic_random failed: Exited(1)\n\
+         deterministic_random default: Signaled(31)\n\
+         network_policy allowed: Exited(111)\n\
+         network_policy failed: Exited(1)\n\
+         network_policy default: Signaled(31)\n\
+         exec_policy allowed: Exited(13)\n\
+         exec_policy failed: Exited(1)\n\
+         exec_policy default: Signaled(31)\n",
+    );
+}
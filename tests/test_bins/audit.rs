@@ -0,0 +1,73 @@
+use restrict::{
+    audit::{AuditRecord, AuditSink},
+    policy::{Policy, Syscall},
+    Errno, TraceAction,
+};
+use std::{cell::RefCell, ffi::CString, io, rc::Rc, time::Duration};
+
+/// keeps the records in memory
+#[derive(Clone, Default)]
+struct Collect(Rc<RefCell<Vec<AuditRecord>>>);
+
+impl AuditSink for Collect {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        self.0.borrow_mut().push(record.clone());
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let records = Collect::default();
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        // rule 0
+        .entry_intercept(Syscall::Openat, |i| {
+            let path = i.read_c_string(i.registers.arg(1), 4096).unwrap();
+            if path.to_bytes().ends_with(b"secret") {
+                TraceAction::Deny(Errno(libc::EACCES))
+            } else {
+                TraceAction::Continue
+            }
+        })
+        // rule 1
+        .trace(Syscall::Getppid, |_| TraceAction::Return(4242))
+        // rule 2, only observes
+        .exit_intercept(Syscall::Getpid, |_| TraceAction::Continue)
+        // rule 3, replaces the result
+        .exit_intercept(Syscall::Getuid, |_| TraceAction::Return(0))
+        .audit(records.clone());
+
+    policy
+        .run(|| {
+            let path = CString::new("/nonexistent/secret").unwrap();
+            unsafe {
+                libc::open(path.as_ptr(), libc::O_RDONLY);
+                libc::getppid();
+                libc::getpid();
+                libc::getuid();
+            }
+        })
+        .unwrap();
+
+    for record in records.0.borrow().iter() {
+        assert_eq!(record.pid, record.tid);
+        assert!(record.duration > Duration::ZERO);
+        // the pid changes from one run to the other
+        let ret = match record.ret {
+            Some(ret) if record.syscall == Syscall::Getpid && ret == i64::from(record.pid) => {
+                "pid".to_string()
+            }
+            ret => format!("{ret:?}"),
+        };
+        println!(
+            "{} {:?} {} rule={:?} ret={ret}",
+            record.syscall.name(),
+            record.args,
+            record.verdict,
+            record.rule,
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+/// what the handlers saw, in the order they ran
+#[derive(Default)]
+struct Log {
+    getppid: Vec<&'static str>,
+    writes: [usize; 2],
+    ids: usize,
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut policy = Policy::allow_all().unwrap().with_context(Log::default());
+    policy
+        // both tracers see every write
+        .trace_with(Syscall::Write, |_, log: &mut Log| {
+            log.writes[0] += 1;
+            TraceAction::Continue
+        })
+        .trace_with(Syscall::Write, |_, log| {
+            log.writes[1] += 1;
+            TraceAction::Continue
+        })
+        // the second handler short-circuits the chain, the third never runs
+        .entry_intercept_with(Syscall::Getppid, |_, log| {
+            log.getppid.push("first");
+            TraceAction::Continue
+        })
+        .entry_intercept_with(Syscall::Getppid, |_, log| {
+            log.getppid.push("second");
+            TraceAction::Return(4242)
+        })
+        .entry_intercept_with(Syscall::Getppid, |_, log| {
+            log.getppid.push("third");
+            TraceAction::Continue
+        })
+        .trace_any_with(
+            |sc| matches!(sc, Syscall::Getpid | Syscall::Gettid),
+            |_, log| {
+                log.ids += 1;
+                TraceAction::Continue
+            },
+        )
+        // getuid keeps its explicit rule, only getgid is intercepted
+        .fail_with(Syscall::Getuid, libc::EPERM as u16)
+        .intercept_any(
+            |sc| matches!(sc, Syscall::Getuid | Syscall::Getgid),
+            |_| TraceAction::Return(77),
+        );
+
+    let log = policy
+        .run(|| unsafe {
+            println!("getppid: {}", libc::getppid());
+            libc::syscall(libc::SYS_getpid);
+            libc::syscall(libc::SYS_gettid);
+            let uid = libc::syscall(libc::SYS_getuid);
+            let errno = std::io::Error::last_os_error().raw_os_error();
+            println!("getuid: {uid} {errno:?}");
+            println!("getgid: {}", libc::syscall(libc::SYS_getgid));
+        })
+        .unwrap();
+
+    println!("getppid chain: {}", log.getppid.join(", "));
+    println!("writes: {:?}", log.writes);
+    println!("ids: {}", log.ids);
+}
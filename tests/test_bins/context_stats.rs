@@ -0,0 +1,48 @@
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+/// state collected by the handlers in the tracer process
+#[derive(Default)]
+struct Stats {
+    writes: usize,
+    opened: Vec<String>,
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut policy = Policy::allow_all().unwrap().with_context(Stats::default());
+
+    // a stateful handler without the context
+    let mut closes = 0;
+    policy
+        .trace_with(Syscall::Write, |_, stats: &mut Stats| {
+            stats.writes += 1;
+            TraceAction::Continue
+        })
+        .entry_intercept_with(Syscall::Openat, |i, stats| {
+            let path = i.read_c_string(i.registers.arg(1), 4096).unwrap();
+            stats.opened.push(path.to_string_lossy().into_owned());
+            TraceAction::Continue
+        })
+        .exit_intercept(Syscall::Close, move |_| {
+            closes += 1;
+            println!("close #{closes}");
+            TraceAction::Continue
+        });
+
+    let stats = policy
+        .run(|| {
+            println!("one");
+            println!("two");
+            let _ = std::fs::File::open("/dev/null");
+            let _ = std::fs::File::open("/restrict/context_stats/missing");
+        })
+        .unwrap();
+
+    println!("writes: {}", stats.writes);
+    println!("opened: {}", stats.opened.join(", "));
+}
@@ -0,0 +1,41 @@
+use std::{io::Error, path::Path};
+
+use restrict::policy::Policy;
+use restrict::{syscall::Syscall, Errno, TraceAction};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let dir = std::env::temp_dir().join(format!("restrict-deny-{}", std::process::id()));
+    let dir_str = format!("{}\0", dir.display());
+
+    let mut filter = Policy::allow_all().unwrap();
+    filter
+        .trace(Syscall::Getppid, |_| TraceAction::Return(4242))
+        .entry_intercept(Syscall::Openat, |i| {
+            let path = i.read_c_string(i.registers.arg(1), 4096).unwrap();
+            if path.to_bytes() == b"/dev/null" {
+                TraceAction::Deny(Errno(libc::EACCES))
+            } else {
+                TraceAction::Continue
+            }
+        })
+        // report success without creating anything
+        .entry_intercept(Syscall::Mkdir, |_| TraceAction::Return(0))
+        .exit_intercept(Syscall::Getpid, |_| TraceAction::Deny(Errno(libc::EPERM)));
+    filter.apply().unwrap();
+
+    println!("getppid: {}", unsafe { libc::getppid() });
+
+    let denied = std::fs::File::open("/dev/null").map_err(|e| e.raw_os_error());
+    println!("open /dev/null: {:?}", denied.map(|_| ()));
+    let allowed = std::fs::File::open("/dev/zero").map_err(|e| e.raw_os_error());
+    println!("open /dev/zero: {:?}", allowed.map(|_| ()));
+
+    let ret = unsafe { libc::syscall(libc::SYS_mkdir, dir_str.as_ptr(), 0o755) };
+    println!("mkdir: {ret}, created: {}", Path::new(&dir).exists());
+
+    let ret = unsafe { libc::syscall(libc::SYS_getpid) };
+    println!("getpid: {ret} {:?}", Error::last_os_error().raw_os_error());
+}
@@ -0,0 +1,69 @@
+use restrict::{
+    policy::{Policy, Syscall},
+    stream::Backpressure,
+    Phase, TraceAction,
+};
+use std::thread;
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let parent = std::process::id() as i64;
+
+    // every event reaches the consumer thread
+    let mut policy = Policy::allow_all().unwrap();
+    let stream = policy.event_stream(4, Backpressure::Block);
+    let consumer = thread::spawn(move || {
+        stream
+            .filter(|event| event.syscall == Syscall::Getppid)
+            .map(|event| match event.phase {
+                Phase::Entry => format!("entry ret={:?}", event.ret),
+                Phase::Exit => format!("exit parent={}", event.ret == Some(parent)),
+            })
+            .collect::<Vec<_>>()
+    });
+    policy
+        .run(|| unsafe {
+            libc::getppid();
+            libc::getppid();
+        })
+        .unwrap();
+    for event in consumer.join().unwrap() {
+        println!("blocking: {event}");
+    }
+
+    // nobody reads until the process exited, only the first event fits
+    let mut policy = Policy::allow_all().unwrap();
+    let stream = policy.event_stream(1, Backpressure::DropNewest);
+    policy
+        .run(|| unsafe {
+            libc::getppid();
+        })
+        .unwrap();
+    println!("lossy dropped some: {}", stream.dropped() > 0);
+    println!("lossy received: {}", stream.count());
+
+    // a handler skipping the exit doesn't hide the next syscalls, nor the skipped exit
+    let mut policy = Policy::allow_all().unwrap();
+    let stream = policy.event_stream(64, Backpressure::Block);
+    policy
+        .entry_intercept(Syscall::Getpid, |_| TraceAction::SkipExit)
+        .exit_intercept(Syscall::Getpid, |_| panic!("the exit was skipped"));
+    let consumer = thread::spawn(move || {
+        stream
+            .filter(|event| matches!(event.syscall, Syscall::Getpid | Syscall::Getppid))
+            .inspect(|event| assert_eq!(event.tid, event.pid))
+            .map(|event| format!("{:?} {:?}", event.syscall, event.phase))
+            .collect::<Vec<_>>()
+    });
+    policy
+        .run(|| unsafe {
+            libc::getpid();
+            libc::getppid();
+        })
+        .unwrap();
+    for event in consumer.join().unwrap() {
+        println!("skip exit: {event}");
+    }
+}
@@ -0,0 +1,23 @@
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    // the exec stop between the entry and the exit of `execve` doesn't lose its exit stop
+    let mut policy = Policy::allow_all().unwrap().with_context(Vec::new());
+    policy.exit_intercept_with(Syscall::Execve, |i, returns: &mut Vec<i64>| {
+        returns.push(i.registers.return_value() as i64);
+        TraceAction::Continue
+    });
+    let returns = policy
+        .run(|| unsafe {
+            let argv = [b"true\0".as_ptr().cast(), std::ptr::null()];
+            libc::execv(b"/bin/true\0".as_ptr().cast(), argv.as_ptr());
+        })
+        .unwrap();
+    println!("execve exits: {returns:?}");
+}
@@ -0,0 +1,58 @@
+use restrict::{exec::ExecPolicy, policy::Policy};
+use sha2::{Digest, Sha256};
+use std::{ffi::CString, io::Error, os::unix::process::CommandExt, process::Command};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    if std::env::args().nth(1).as_deref() == Some("exec") {
+        // allowed by its path, without the variables the policy strips
+        println!("exec LD_PRELOAD: {:?}", std::env::var("LD_PRELOAD").ok());
+        // allowed by its hash
+        let err = Command::new("/bin/env")
+            .env_clear()
+            .env("FOO", "bar")
+            .env("LD_LIBRARY_PATH", "/tmp")
+            .exec();
+        panic!("exec failed: {err}");
+    }
+
+    let env = std::fs::read(std::fs::canonicalize("/bin/env").unwrap()).unwrap();
+    let digest: String = Sha256::digest(env)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let exec = ExecPolicy::new()
+        .allow_path(std::env::current_exe().unwrap())
+        .unwrap()
+        .allow_sha256(&digest)
+        .unwrap()
+        .strip_dangerous_env();
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.exec_policy(exec);
+    policy
+        .run(|| {
+            let ls = CString::new("/bin/ls").unwrap();
+            unsafe { libc::execv(ls.as_ptr(), [ls.as_ptr(), std::ptr::null()].as_ptr()) };
+            println!("ls: {:?}", Error::last_os_error().raw_os_error());
+            // a binary that can't be resolved is denied too
+            let missing = CString::new("/nonexistent/binary").unwrap();
+            unsafe {
+                libc::execv(
+                    missing.as_ptr(),
+                    [missing.as_ptr(), std::ptr::null()].as_ptr(),
+                )
+            };
+            println!("missing: {:?}", Error::last_os_error().raw_os_error());
+
+            // through a symlink to the binary
+            let err = Command::new("/proc/self/exe")
+                .arg("exec")
+                .env("LD_PRELOAD", "/nonexistent.so")
+                .exec();
+            panic!("exec failed: {err}");
+        })
+        .unwrap();
+}
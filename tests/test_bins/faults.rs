@@ -0,0 +1,56 @@
+use restrict::{
+    fault::FaultInjector,
+    policy::{Policy, Syscall},
+    Errno,
+};
+use std::{fs::File, io::Error, os::fd::AsRawFd};
+
+/// the result of `syscall(nr)`, with its errno when it failed
+fn call(nr: libc::c_long) -> String {
+    match unsafe { libc::syscall(nr) } {
+        -1 => format!("{:?}", Error::last_os_error().raw_os_error()),
+        _ => "ok".to_string(),
+    }
+}
+
+/// run the same tracee under a fresh injector seeded with `seed`, return its report
+fn run(seed: u64) -> String {
+    let faults = FaultInjector::new(seed)
+        .fail_nth(Syscall::Getppid, 2, Errno(libc::EINTR))
+        .fail(
+            Syscall::Getpid,
+            0.5,
+            &[Errno(libc::EIO), Errno(libc::ENOMEM)],
+        )
+        .short_io(Syscall::Pwrite64, 1.0);
+    let report = faults.report();
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.inject_faults(faults);
+    policy
+        .run(|| {
+            let nth: Vec<_> = (0..3).map(|_| call(libc::SYS_getppid)).collect();
+            println!("nth: {}", nth.join(", "));
+
+            let failures = (0..20).filter(|_| call(libc::SYS_getpid) != "ok").count();
+            println!("some failed: {}", failures > 0 && failures < 20);
+
+            let null = File::options().write(true).open("/dev/null").unwrap();
+            let buf = [0u8; 100];
+            let written =
+                unsafe { libc::pwrite(null.as_raw_fd(), buf.as_ptr().cast(), buf.len(), 0) };
+            println!("short: {}", (1..100).contains(&written));
+        })
+        .unwrap();
+    report.to_string()
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let first = run(42);
+    let again = run(42);
+    print!("{first}");
+    println!("reproducible: {}", first == again);
+}
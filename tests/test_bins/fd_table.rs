@@ -0,0 +1,103 @@
+use restrict::{
+    fd::FdKind,
+    interceptor::Interceptor,
+    net::SocketAddress,
+    policy::{Policy, Syscall},
+    Errno, TraceAction,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Error, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    os::fd::FromRawFd,
+};
+
+/// `ok`, or the errno the call failed with
+fn result<T>(result: Result<T, Error>) -> String {
+    match result {
+        Ok(_) => "ok".to_string(),
+        Err(err) => format!("{:?}", err.raw_os_error()),
+    }
+}
+
+/// what the write of the tracee went to, stdout and stderr left out
+fn log_write(i: &Interceptor, listener: SocketAddr, writes: &mut Vec<String>) -> TraceAction {
+    let fd = i.registers.arg(0) as i32;
+    if fd > 2 {
+        let kind = match i.fd_info(fd) {
+            Some(FdKind::File(path)) => format!("file {}", path.display()),
+            Some(FdKind::Socket(Some(SocketAddress::Ip(peer)))) if peer == listener => {
+                "socket listener".to_string()
+            }
+            Some(FdKind::Pipe) => "pipe".to_string(),
+            other => format!("{other:?}"),
+        };
+        writes.push(format!("{kind}: {}", i.registers.return_value() as i64));
+    }
+    TraceAction::Continue
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let dir = std::env::temp_dir().join(format!("restrict-fd-table-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+
+    // files can only be written under `dir`, pipes and sockets always
+    let allowed = dir.clone();
+    let mut policy = Policy::allow_all().unwrap().with_context(Vec::new());
+    policy
+        .track_fds()
+        .entry_intercept(Syscall::Write, move |i| {
+            match i.fd_info(i.registers.arg(0) as i32) {
+                Some(FdKind::File(path)) if !path.starts_with(&allowed) => {
+                    TraceAction::Deny(Errno(libc::EACCES))
+                }
+                _ => TraceAction::Continue,
+            }
+        })
+        .exit_intercept_with(Syscall::Write, move |i, writes| {
+            log_write(&i, address, writes)
+        })
+        // `TcpStream` writes with `sendto`
+        .exit_intercept_with(Syscall::Sendto, move |i, writes| {
+            log_write(&i, address, writes)
+        });
+
+    let target = dir.join("out");
+    let writes = policy
+        .run(|| {
+            let mut file = File::create(&target).unwrap();
+            println!("file: {}", result(file.write_all(b"hi")));
+            // try_clone is fcntl(F_DUPFD_CLOEXEC)
+            let mut copy = file.try_clone().unwrap();
+            println!("dup: {}", result(copy.write_all(b"hi")));
+            drop((file, copy));
+
+            let mut null = OpenOptions::new().write(true).open("/dev/null").unwrap();
+            println!("/dev/null: {}", result(null.write_all(b"hi")));
+            drop(null);
+            // gets the number of the closed file back
+            let mut reopened = OpenOptions::new().append(true).open(&target).unwrap();
+            println!("reopened: {}", result(reopened.write_all(b"hi")));
+
+            let mut stream = TcpStream::connect(address).unwrap();
+            println!("socket: {}", result(stream.write_all(b"hello")));
+
+            let mut ends = [0; 2];
+            assert_eq!(unsafe { libc::pipe(ends.as_mut_ptr()) }, 0);
+            let mut write_end = unsafe { File::from_raw_fd(ends[1]) };
+            println!("pipe: {}", result(write_end.write_all(b"hi")));
+        })
+        .unwrap();
+
+    let prefix = format!("{}/", dir.display());
+    for write in writes {
+        println!("{}", write.replace(&prefix, ""));
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
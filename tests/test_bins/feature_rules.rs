@@ -0,0 +1,216 @@
+use restrict::{
+    clock::VirtualClock,
+    exec::ExecPolicy,
+    fault::FaultInjector,
+    net::NetworkPolicy,
+    policy::{Policy, Syscall},
+    Errno, Outcome,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// a `deny_all` policy only letting the tracee exit
+fn deny_all() -> Policy {
+    let mut policy = Policy::deny_all().unwrap();
+    policy.allow(Syscall::ExitGroup);
+    policy
+}
+
+/// how the tracee ended after running `tracee` under `policy`
+fn outcome(policy: Policy, tracee: impl FnOnce() -> i32) -> Outcome {
+    let (_, outcome) = policy
+        .run_with_outcome(|| unsafe { libc::_exit(tracee()) })
+        .unwrap();
+    outcome
+}
+
+/// 0 when a libc call returning `ret` succeeded, its errno otherwise
+fn errno(ret: i32) -> i32 {
+    match ret {
+        -1 => std::io::Error::last_os_error().raw_os_error().unwrap(),
+        _ => 0,
+    }
+}
+
+/// 0 when `/dev/null` opens, the errno otherwise
+fn open_null() -> i32 {
+    errno(unsafe { libc::open(b"/dev/null\0".as_ptr().cast(), libc::O_RDONLY) })
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    // the features of a policy only see the syscalls seccomp allows, an explicit rule
+    // decides for its syscall and the others keep the default action
+    let mut policy = deny_all();
+    policy.allow(Syscall::Openat).track_fds();
+    println!("track_fds allowed: {:?}", outcome(policy, open_null));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Openat, libc::EPERM as u16)
+        .track_fds();
+    println!("track_fds failed: {:?}", outcome(policy, open_null));
+
+    let mut policy = deny_all();
+    policy.track_fds();
+    println!(
+        "track_fds default: {:?}",
+        outcome(policy, || unsafe { libc::close(100) })
+    );
+
+    let mut policy = deny_all();
+    policy
+        .allow(Syscall::Openat)
+        .remap_path("/restrict/remapped", "/dev/null");
+    println!(
+        "remap_path allowed: {:?}",
+        outcome(policy, || errno(unsafe {
+            libc::open(b"/restrict/remapped\0".as_ptr().cast(), libc::O_RDONLY)
+        }))
+    );
+
+    // only ever remapped to paths the syscalls can't harm
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Faccessat, libc::EPERM as u16)
+        .remap_path("/restrict/remapped", "/dev/null");
+    println!(
+        "remap_path failed: {:?}",
+        outcome(policy, || errno(unsafe {
+            libc::syscall(
+                libc::SYS_faccessat,
+                libc::AT_FDCWD,
+                b"/restrict/remapped\0".as_ptr(),
+                libc::F_OK,
+            ) as i32
+        }))
+    );
+
+    let mut policy = deny_all();
+    policy.remap_path("/restrict/remapped", "/restrict/missing");
+    println!(
+        "remap_path default: {:?}",
+        outcome(policy, || unsafe {
+            libc::mkdir(b"/restrict/remapped\0".as_ptr().cast(), 0o700)
+        })
+    );
+
+    let faults = || FaultInjector::new(1).fail_nth(Syscall::Getpid, 1, Errno(libc::EIO));
+    let getpid = || errno(unsafe { libc::syscall(libc::SYS_getpid) } as i32);
+    let mut policy = deny_all();
+    policy.allow(Syscall::Getpid).inject_faults(faults());
+    println!("inject_faults allowed: {:?}", outcome(policy, getpid));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Getpid, libc::EPERM as u16)
+        .inject_faults(faults());
+    println!("inject_faults failed: {:?}", outcome(policy, getpid));
+
+    let mut policy = deny_all();
+    policy.inject_faults(faults());
+    println!("inject_faults default: {:?}", outcome(policy, getpid));
+
+    let clock = || VirtualClock::frozen(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+    let time = || match unsafe { libc::syscall(libc::SYS_time, 0) } {
+        1_000_000_000 => 0,
+        -1 => errno(-1),
+        _ => 2,
+    };
+    let mut policy = deny_all();
+    policy.allow(Syscall::Time).virtual_clock(clock());
+    println!("virtual_clock allowed: {:?}", outcome(policy, time));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Time, libc::EPERM as u16)
+        .virtual_clock(clock());
+    println!("virtual_clock failed: {:?}", outcome(policy, time));
+
+    let mut policy = deny_all();
+    policy.virtual_clock(clock());
+    println!("virtual_clock default: {:?}", outcome(policy, time));
+
+    // the random byte, the same in every run with the same seed, or the errno
+    let getrandom = || {
+        let mut byte = 0u8;
+        match unsafe { libc::syscall(libc::SYS_getrandom, &mut byte, 1, 0) } {
+            1 => i32::from(byte),
+            _ => errno(-1),
+        }
+    };
+    let allowed = || {
+        let mut policy = deny_all();
+        policy.allow(Syscall::Getrandom).deterministic_random(7);
+        outcome(policy, getrandom)
+    };
+    let first = allowed();
+    println!(
+        "deterministic_random allowed: {}",
+        matches!(first, Outcome::Exited(_)) && first == allowed()
+    );
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Getrandom, libc::EPERM as u16)
+        .deterministic_random(7);
+    println!(
+        "deterministic_random failed: {:?}",
+        outcome(policy, getrandom)
+    );
+
+    let mut policy = deny_all();
+    policy.deterministic_random(7);
+    println!(
+        "deterministic_random default: {:?}",
+        outcome(policy, getrandom)
+    );
+
+    // the address is checked before the kernel sees the bad fd
+    let connect = || {
+        let addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: 1u16.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let len = std::mem::size_of_val(&addr) as libc::socklen_t;
+        errno(unsafe { libc::connect(-1, (&addr as *const libc::sockaddr_in).cast(), len) })
+    };
+    let mut policy = deny_all();
+    policy
+        .allow(Syscall::Connect)
+        .network_policy(NetworkPolicy::deny_all());
+    println!("network_policy allowed: {:?}", outcome(policy, connect));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Connect, libc::EPERM as u16)
+        .network_policy(NetworkPolicy::allow_all());
+    println!("network_policy failed: {:?}", outcome(policy, connect));
+
+    let mut policy = deny_all();
+    policy.network_policy(NetworkPolicy::allow_all());
+    println!("network_policy default: {:?}", outcome(policy, connect));
+
+    let exec = || {
+        let argv = [b"true\0".as_ptr().cast(), std::ptr::null()];
+        errno(unsafe { libc::execv(b"/bin/true\0".as_ptr().cast(), argv.as_ptr()) })
+    };
+    let mut policy = deny_all();
+    policy.allow(Syscall::Execve).exec_policy(ExecPolicy::new());
+    println!("exec_policy allowed: {:?}", outcome(policy, exec));
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .fail_with(Syscall::Execve, libc::EPERM as u16)
+        .exec_policy(ExecPolicy::new());
+    println!("exec_policy failed: {:?}", outcome(policy, exec));
+
+    let mut policy = deny_all();
+    policy.exec_policy(ExecPolicy::new());
+    println!("exec_policy default: {:?}", outcome(policy, exec));
+}
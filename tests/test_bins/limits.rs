@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use restrict::{
+    policy::{Policy, Syscall},
+    TraceAction,
+};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let start = Instant::now();
+    let mut policy = Policy::allow_all().unwrap();
+    policy.time_limit(Duration::from_millis(200));
+    let (_, outcome) = policy
+        .run_with_outcome(|| std::thread::sleep(Duration::from_secs(10)))
+        .unwrap();
+    println!(
+        "sleep: {outcome:?}, killed early: {}",
+        start.elapsed() < Duration::from_secs(5)
+    );
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.time_limit(Duration::from_millis(200));
+    let (_, outcome) = policy
+        .run_with_outcome(|| loop {
+            std::hint::black_box(0);
+        })
+        .unwrap();
+    println!("spin: {outcome:?}");
+
+    // the watchdog stops with a tracee that exits in time
+    let start = Instant::now();
+    let mut policy = Policy::allow_all().unwrap();
+    policy.time_limit(Duration::from_secs(10));
+    let (_, outcome) = policy
+        .run_with_outcome(|| unsafe { libc::_exit(3) })
+        .unwrap();
+    println!(
+        "exit: {outcome:?}, waited: {}",
+        start.elapsed() >= Duration::from_secs(5)
+    );
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.cpu_limit(Duration::from_millis(500));
+    let (_, outcome) = policy
+        .run_with_outcome(|| loop {
+            std::hint::black_box(0);
+        })
+        .unwrap();
+    println!("busy loop: {outcome:?}");
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .time_limit(Duration::from_secs(10))
+        .trace(Syscall::Getppid, |_| TraceAction::Kill);
+    let (_, outcome) = policy
+        .run_with_outcome(|| unsafe {
+            libc::getppid();
+        })
+        .unwrap();
+    println!("getppid: {outcome:?}");
+}
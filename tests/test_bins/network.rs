@@ -0,0 +1,135 @@
+use restrict::{net::NetworkPolicy, policy::Policy};
+use std::{
+    fs,
+    io::Error,
+    net::{TcpListener, TcpStream, UdpSocket},
+    os::{
+        fd::AsRawFd,
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixListener, UnixStream},
+    },
+    time::Duration,
+};
+
+/// `ok`, or the errno the call failed with
+fn result<T>(result: Result<T, Error>) -> String {
+    match result {
+        Ok(_) => "ok".to_string(),
+        Err(err) => format!("{:?}", err.raw_os_error()),
+    }
+}
+
+/// the result of a libc call returning -1 on error
+fn libc_result(ret: isize) -> String {
+    result(if ret == -1 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    })
+}
+
+fn sockaddr_in(port: u16) -> libc::sockaddr_in {
+    libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+        },
+        sin_zero: [0; 8],
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let allowed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let other = TcpListener::bind("127.0.0.1:0").unwrap();
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let (allowed, other, receiver_addr) = (
+        allowed.local_addr().unwrap(),
+        other.local_addr().unwrap(),
+        receiver.local_addr().unwrap(),
+    );
+
+    let dir = std::env::temp_dir().join(format!("restrict-network-{}", std::process::id()));
+    fs::create_dir_all(dir.join("allowed")).unwrap();
+    let _unix_allowed = UnixListener::bind(dir.join("allowed/app.sock")).unwrap();
+    let _unix_other = UnixListener::bind(dir.join("other.sock")).unwrap();
+    let abstract_name = format!("restrict-network-{}", std::process::id());
+    let abstract_addr = SocketAddr::from_abstract_name(&abstract_name).unwrap();
+    let _abstract = UnixListener::bind_addr(&abstract_addr).unwrap();
+
+    let network = NetworkPolicy::deny_all()
+        .allow_ip("127.0.0.0/8".parse().unwrap(), Some(allowed.port()))
+        .allow_ip("127.0.0.1".parse().unwrap(), Some(receiver_addr.port()))
+        // binding an ephemeral port
+        .allow_ip("127.0.0.1".parse().unwrap(), Some(0))
+        .allow_unix(dir.join("allowed"))
+        .allow_abstract(abstract_name.as_str());
+    let mut policy = Policy::allow_all().unwrap();
+    policy.network_policy(network);
+    policy
+        .run(|| {
+            println!("tcp allowed: {}", result(TcpStream::connect(allowed)));
+            println!("tcp other port: {}", result(TcpStream::connect(other)));
+
+            println!("bind denied: {}", result(UdpSocket::bind("127.0.0.1:1")));
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            println!(
+                "sendto allowed: {}",
+                result(socket.send_to(b"hi", receiver_addr))
+            );
+            println!("sendto other: {}", result(socket.send_to(b"hi", other)));
+
+            // sendmsg carries its address in the msghdr
+            let mut name = sockaddr_in(other.port());
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_name = std::ptr::addr_of_mut!(name).cast();
+            msg.msg_namelen = std::mem::size_of_val(&name) as u32;
+            let sent = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+            println!("sendmsg other: {}", libc_result(sent));
+
+            // every message of sendmmsg is checked, the allowed first one isn't sent either
+            let mut names = [sockaddr_in(receiver_addr.port()), sockaddr_in(other.port())];
+            let mut msgs: [libc::mmsghdr; 2] = unsafe { std::mem::zeroed() };
+            for (msg, name) in msgs.iter_mut().zip(&mut names) {
+                msg.msg_hdr.msg_name = std::ptr::addr_of_mut!(*name).cast();
+                msg.msg_hdr.msg_namelen = std::mem::size_of_val(name) as u32;
+            }
+            let sent = unsafe { libc::sendmmsg(socket.as_raw_fd(), msgs.as_mut_ptr(), 2, 0) };
+            println!("sendmmsg other: {}", libc_result(sent as isize));
+
+            // an address the tracer can't read is denied
+            let bad = unsafe { libc::connect(socket.as_raw_fd(), 8 as *const _, 16) };
+            println!("connect bad address: {}", libc_result(bad as isize));
+
+            let unix = UnixStream::connect(dir.join("allowed/app.sock"));
+            println!("unix allowed: {}", result(unix));
+            std::env::set_current_dir(&dir).unwrap();
+            println!("unix other: {}", result(UnixStream::connect("other.sock")));
+            // `..` doesn't climb out of the allowed directory
+            let dotdot = UnixStream::connect(dir.join("allowed/../other.sock"));
+            println!("unix dotdot: {}", result(dotdot));
+            let dotdot = UnixStream::connect("allowed/../other.sock");
+            println!("unix relative dotdot: {}", result(dotdot));
+            let abstract_other = SocketAddr::from_abstract_name("restrict-other").unwrap();
+            println!(
+                "abstract allowed: {}",
+                result(UnixStream::connect_addr(&abstract_addr))
+            );
+            println!(
+                "abstract other: {}",
+                result(UnixStream::connect_addr(&abstract_other))
+            );
+        })
+        .unwrap();
+
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut buf = [0; 16];
+    let (len, _) = receiver.recv_from(&mut buf).unwrap();
+    println!("received: {}", String::from_utf8_lossy(&buf[..len]));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+use restrict::{
+    policy::{Policy, Syscall},
+    OnError, TraceAction,
+};
+
+/// a `trace_all_pretty` writer that always fails, so the tracer fails at the first
+/// syscall exit of the tracee
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("broken writer"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let on_error = match std::env::args().nth(1).as_deref() {
+        Some("kill") => OnError::Kill,
+        Some("detach") => OnError::Detach,
+        Some("continue") => OnError::Continue,
+        // ptrace requests on a tracee killed during its stop fail with ESRCH, that is
+        // not an error of the tracer
+        Some("gone") => {
+            let mut policy = Policy::allow_all().unwrap();
+            policy.entry_intercept(Syscall::Getppid, |i| {
+                unsafe { libc::kill(i.child_pid, libc::SIGKILL) };
+                TraceAction::Return(0)
+            });
+            let result = policy.run(|| println!("getppid: {}", unsafe { libc::getppid() }));
+            println!("run: {:?}", result.map_err(|e| e.to_string()));
+            return;
+        }
+        // syscall numbers unknown to the crate are passed through
+        _ => {
+            let mut policy = Policy::allow_all().unwrap();
+            policy.trace_all_pretty(io::sink());
+            let result = policy.run(|| {
+                let ret = unsafe { libc::syscall(1000) };
+                let errno = io::Error::last_os_error().raw_os_error();
+                println!("syscall 1000: {ret} {errno:?}");
+            });
+            println!("run: {:?}", result.map_err(|e| e.to_string()));
+            return;
+        }
+    };
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.trace_all_pretty(Broken).on_error(on_error);
+    let result = policy.run(|| println!("tracee done"));
+    println!("run: {:?}", result.map_err(|e| e.to_string()));
+}
@@ -0,0 +1,14 @@
+use restrict::policy::Policy;
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut filter = Policy::allow_all().unwrap();
+    filter.trace_all_pretty(std::io::stderr());
+    filter.apply().unwrap();
+
+    let _ = std::fs::File::open("/dev/null");
+    let _ = std::fs::File::open("/restrict/pretty_trace/missing.txt");
+    println!("pretty");
+}
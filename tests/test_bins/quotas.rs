@@ -0,0 +1,49 @@
+use std::{io::Error, time::Duration};
+
+use restrict::{
+    policy::{Policy, Syscall},
+    Errno, OnExceeded,
+};
+
+/// the result of `syscall(nr)`, with its errno when it failed
+fn call(nr: libc::c_long) -> String {
+    match unsafe { libc::syscall(nr) } {
+        -1 => format!("{:?}", Error::last_os_error().raw_os_error()),
+        _ => "ok".to_string(),
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .quota(Syscall::Getppid, 2, OnExceeded::Fail(Errno(libc::EAGAIN)))
+        .rate_limit(
+            Syscall::Getpid,
+            3,
+            Duration::from_millis(300),
+            OnExceeded::Fail(Errno(libc::EBUSY)),
+        )
+        .quota(Syscall::Getgid, 0, OnExceeded::Log)
+        .quota(Syscall::Getuid, 0, OnExceeded::Kill);
+
+    let (_, outcome) = policy
+        .run_with_outcome(|| {
+            let quota: Vec<_> = (0..3).map(|_| call(libc::SYS_getppid)).collect();
+            println!("quota: {}", quota.join(", "));
+
+            let burst: Vec<_> = (0..5).map(|_| call(libc::SYS_getpid)).collect();
+            println!("burst: {}", burst.join(", "));
+            // a token is back every 100ms
+            std::thread::sleep(Duration::from_millis(150));
+            println!("refilled: {}", call(libc::SYS_getpid));
+
+            println!("logged: {}", call(libc::SYS_getgid));
+            call(libc::SYS_getuid);
+            println!("not reached");
+        })
+        .unwrap();
+    println!("outcome: {outcome:?}");
+}
@@ -0,0 +1,61 @@
+use restrict::policy::Policy;
+use std::{
+    fs::File,
+    io::Read,
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::process::CommandExt,
+    },
+    process::Command,
+};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// the bytes of `/dev/random`, then of `getrandom` and `AT_RANDOM` in a program exec'd by the
+/// tracee
+fn print_random(program: &str) {
+    let mut buf = [0u8; 8];
+    let mut random = File::open("/dev/random").unwrap();
+    random.read_exact(&mut buf).unwrap();
+    println!("{program} /dev/random: {}", hex(&buf));
+    // the copies of the fd are followed too
+    let dup = unsafe { libc::dup(random.as_raw_fd()) };
+    unsafe { File::from_raw_fd(dup) }
+        .read_exact(&mut buf)
+        .unwrap();
+    println!("{program} dup: {}", hex(&buf));
+    let dup = unsafe { libc::fcntl(random.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 100) };
+    unsafe { File::from_raw_fd(dup) }
+        .read_exact(&mut buf)
+        .unwrap();
+    println!("{program} F_DUPFD: {}", hex(&buf));
+    unsafe { libc::syscall(libc::SYS_getrandom, buf.as_mut_ptr(), buf.len(), 0) };
+    println!("{program} getrandom: {}", hex(&buf));
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let arg = std::env::args().nth(1);
+    if arg.as_deref() == Some("exec") {
+        let at_random = unsafe { libc::getauxval(libc::AT_RANDOM) } as *const [u8; 16];
+        println!("exec AT_RANDOM: {}", hex(unsafe { &*at_random }));
+        print_random("exec");
+        return;
+    }
+
+    let seed = arg.map_or(42, |seed| seed.parse().unwrap());
+    let mut policy = Policy::allow_all().unwrap();
+    policy.deterministic_random(seed);
+    policy
+        .run(|| {
+            print_random("tracee");
+            // a program exec'd by the tracee gets the same bytes on every run too
+            let err = Command::new("/proc/self/exe").arg("exec").exec();
+            panic!("exec failed: {err}");
+        })
+        .unwrap();
+}
@@ -0,0 +1,67 @@
+use restrict::policy::Policy;
+use restrict::{syscall::Syscall, SeccompError, TraceAction};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut filter = Policy::allow_all().unwrap();
+    filter
+        .entry_intercept(Syscall::Openat, |i| {
+            let path_addr = i.registers.arg(1);
+            match i.read_c_string(path_addr, 4096) {
+                Ok(path) => println!("openat: {}", path.to_string_lossy()),
+                Err(SeccompError::MemoryFault(_, addr)) => println!("openat: fault at {addr:#x}"),
+                Err(e) => println!("openat: {e}"),
+            }
+            TraceAction::Continue
+        })
+        .entry_intercept(Syscall::Writev, |i| {
+            let iov = i.registers.arg(1);
+            let iovcnt = i.registers.arg(2) as usize;
+            let data = i.read_iovec(iov, iovcnt, 64).unwrap();
+            println!("writev: {}", String::from_utf8_lossy(&data));
+            TraceAction::Continue
+        })
+        .entry_intercept(Syscall::Nanosleep, |i| {
+            let ts: libc::timespec = i.read_struct(i.registers.arg(0)).unwrap();
+            println!("nanosleep: {}s {}ns", ts.tv_sec, ts.tv_nsec);
+            TraceAction::Continue
+        });
+    filter.apply().unwrap();
+
+    let _ = std::fs::File::open("/restrict/read_memory/test.txt");
+    // a bogus path pointer, the handler must get an error instead of crashing the tracer
+    unsafe { libc::syscall(libc::SYS_openat, libc::AT_FDCWD, 8usize, libc::O_RDONLY) };
+
+    let (hello, world) = (b"hello ", b"world");
+    let iov = [
+        libc::iovec {
+            iov_base: hello.as_ptr() as *mut _,
+            iov_len: hello.len(),
+        },
+        libc::iovec {
+            iov_base: world.as_ptr() as *mut _,
+            iov_len: world.len(),
+        },
+    ];
+    unsafe {
+        let null = libc::open(
+            b"/dev/null\0".as_ptr() as *const libc::c_char,
+            libc::O_WRONLY,
+        );
+        libc::writev(null, iov.as_ptr(), 2);
+    }
+
+    let ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 1000,
+    };
+    unsafe {
+        libc::syscall(
+            libc::SYS_nanosleep,
+            &ts,
+            std::ptr::null_mut::<libc::timespec>(),
+        )
+    };
+}
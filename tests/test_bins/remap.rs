@@ -0,0 +1,57 @@
+use restrict::policy::Policy;
+use std::{
+    ffi::CString,
+    fs,
+    io::Read,
+    os::fd::{AsRawFd, FromRawFd},
+    os::unix::process::CommandExt,
+    process::Command,
+};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let dir = std::env::temp_dir().join(format!("restrict-remap-{}", std::process::id()));
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::write(dir.join("resolv.conf"), "nameserver 10.0.0.1\n").unwrap();
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy
+        .remap_path("/etc/resolv.conf", dir.join("resolv.conf"))
+        .remap_path("/var/lib/restrict-test", dir.join("data"))
+        .remap_path("/usr/bin/restrict-cat", "/bin/cat");
+    policy
+        .run(|| {
+            let resolv = fs::read_to_string("/etc/resolv.conf").unwrap();
+            print!("resolv.conf: {resolv}");
+
+            fs::write("/var/lib/restrict-test/out.txt", "hello").unwrap();
+            let is_dir = fs::metadata("/var/lib/restrict-test").unwrap().is_dir();
+            println!("is dir: {is_dir}");
+
+            // a relative path from a directory fd
+            let var = fs::File::open("/var").unwrap();
+            let path = CString::new("lib/restrict-test/../restrict-test/out.txt").unwrap();
+            let fd = unsafe { libc::openat(var.as_raw_fd(), path.as_ptr(), libc::O_RDONLY) };
+            assert!(fd >= 0, "openat failed");
+            let mut out = String::new();
+            unsafe { fs::File::from_raw_fd(fd) }
+                .read_to_string(&mut out)
+                .unwrap();
+            println!("relative: {out}");
+
+            // the remapped binary reads the remapped file too
+            let err = Command::new("/usr/bin/restrict-cat")
+                .arg("/etc/resolv.conf")
+                .exec();
+            panic!("exec failed: {err}");
+        })
+        .unwrap();
+
+    println!(
+        "written to: {}",
+        fs::read_to_string(dir.join("data/out.txt")).unwrap()
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,68 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use restrict::{policy::Policy, SignalAction};
+
+/// SIGUSR1 received by the tracee
+static USR1: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_usr1(_: libc::c_int) {
+    USR1.fetch_add(1, Ordering::SeqCst);
+}
+
+/// signals seen by the tracer
+#[derive(Default)]
+struct Seen {
+    usr1: usize,
+    cont: usize,
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut policy = Policy::allow_all().unwrap().with_context(Seen::default());
+    policy
+        .on_signal_with(libc::SIGUSR1, |_, seen: &mut Seen| {
+            seen.usr1 += 1;
+            SignalAction::Deliver
+        })
+        .on_signal(libc::SIGUSR2, |_| SignalAction::Suppress)
+        .on_signal(libc::SIGHUP, |_| SignalAction::Replace(libc::SIGUSR1))
+        .on_signal_with(libc::SIGCONT, |_, seen| {
+            seen.cont += 1;
+            SignalAction::Deliver
+        });
+
+    let seen = policy
+        .run(|| unsafe {
+            libc::signal(libc::SIGUSR1, on_usr1 as *const () as libc::sighandler_t);
+            libc::raise(libc::SIGUSR1);
+            println!("usr1 handled: {}", USR1.load(Ordering::SeqCst));
+
+            // would terminate the tracee if it was delivered
+            libc::raise(libc::SIGUSR2);
+            println!("usr2 suppressed");
+
+            libc::raise(libc::SIGHUP);
+            println!("hup replaced: {}", USR1.load(Ordering::SeqCst));
+
+            // the tracee stays stopped until a helper process sends SIGCONT
+            let tracee = libc::getpid();
+            if libc::fork() == 0 {
+                std::thread::sleep(Duration::from_millis(300));
+                libc::kill(tracee, libc::SIGCONT);
+                libc::_exit(0);
+            }
+            let start = Instant::now();
+            libc::raise(libc::SIGSTOP);
+            let stopped = start.elapsed() >= Duration::from_millis(200);
+            println!("stopped until SIGCONT: {stopped}");
+            libc::wait(std::ptr::null_mut());
+        })
+        .unwrap();
+
+    println!("usr1 seen: {}, cont seen: {}", seen.usr1, seen.cont);
+}
@@ -0,0 +1,68 @@
+use restrict::{clock::VirtualClock, policy::Policy};
+use std::{
+    os::unix::process::CommandExt,
+    process::Command,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// `clock` read with a raw syscall, the vDSO of the tracee still has the real time
+fn raw_clock_gettime(clock: libc::clockid_t) -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::syscall(libc::SYS_clock_gettime, clock, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut policy = Policy::allow_all().unwrap();
+    policy.virtual_clock(VirtualClock::frozen(
+        UNIX_EPOCH + Duration::from_secs(1_000_000_000),
+    ));
+    policy
+        .run(|| {
+            let time = unsafe { libc::syscall(libc::SYS_time, std::ptr::null_mut::<i64>()) };
+            println!("time: {time}");
+            let now = raw_clock_gettime(libc::CLOCK_REALTIME);
+            println!("clock_gettime: {}.{}", now.as_secs(), now.subsec_nanos());
+            let mut tv = libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            };
+            unsafe { libc::syscall(libc::SYS_gettimeofday, &mut tv, 0) };
+            println!("gettimeofday: {}", tv.tv_sec);
+            // without the vDSO the libc of `date` reads the clock with a syscall
+            let err = Command::new("date").args(["-u", "+date: %s"]).exec();
+            panic!("exec failed: {err}");
+        })
+        .unwrap();
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.virtual_clock(VirtualClock::offset(-3600));
+    policy
+        .run(|| {
+            let real = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            let virt = raw_clock_gettime(libc::CLOCK_REALTIME);
+            println!("offset: -{}", (real - virt).as_secs_f64().round());
+        })
+        .unwrap();
+
+    let mut policy = Policy::allow_all().unwrap();
+    policy.virtual_clock(VirtualClock::scaled(10.0));
+    policy
+        .run(|| {
+            let (real, virt) = (Instant::now(), raw_clock_gettime(libc::CLOCK_MONOTONIC));
+            std::thread::sleep(Duration::from_secs(2));
+            let virt = raw_clock_gettime(libc::CLOCK_MONOTONIC) - virt;
+            println!(
+                "slept for real: {}",
+                real.elapsed() < Duration::from_secs(1)
+            );
+            println!("slept virtually: {}", virt >= Duration::from_secs(2));
+        })
+        .unwrap();
+}
@@ -0,0 +1,82 @@
+use std::{
+    ffi::CStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use restrict::policy::Policy;
+use restrict::{syscall::Syscall, TraceAction};
+
+#[cfg(not(target_arch = "x86_64"))]
+fn main() {}
+#[cfg(target_arch = "x86_64")]
+static SIGNALED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_arch = "x86_64")]
+extern "C" fn on_signal(_: libc::c_int) {
+    SIGNALED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(target_arch = "x86_64")]
+fn main() {
+    let mut filter = Policy::allow_all().unwrap();
+    filter
+        .entry_intercept(Syscall::Openat, |mut i| {
+            let path_addr = i.registers.arg(1);
+            let path = i.read_c_string(path_addr, 4096).unwrap();
+            match path.to_bytes() {
+                // longer than the original, goes to the stack scratch space
+                b"/a" => {
+                    let addr = i.write_scratch(b"/dev/null\0").unwrap();
+                    i.registers.set_arg(1, addr);
+                    i.commit_regs().unwrap();
+                }
+                // forced into a fresh mapping
+                b"/b" => {
+                    let addr = i.map_scratch(4096).unwrap();
+                    i.write_bytes(addr, b"/dev/zero\0").unwrap();
+                    i.registers.set_arg(1, addr);
+                    i.commit_regs().unwrap();
+                }
+                // a signal arriving while the mmap is injected
+                b"/c" => {
+                    unsafe { libc::kill(i.child_pid, libc::SIGUSR1) };
+                    let addr = i.map_scratch(4096).unwrap();
+                    i.write_bytes(addr, b"/dev/null\0").unwrap();
+                    i.registers.set_arg(1, addr);
+                    i.commit_regs().unwrap();
+                }
+                // a read-only string literal patched in place
+                b"/restrict/patch-me" => i.write_bytes(path_addr, b"/dev/null\0").unwrap(),
+                _ => {}
+            }
+            TraceAction::Continue
+        })
+        .exit_intercept(Syscall::Uname, |i| {
+            let addr = i.registers.arg(0);
+            let mut uts: libc::utsname = i.read_struct(addr).unwrap();
+            uts.sysname = [0; 65];
+            for (dst, src) in uts.sysname.iter_mut().zip(b"restrict") {
+                *dst = *src as libc::c_char;
+            }
+            i.write_struct(addr, &uts).unwrap();
+            TraceAction::Continue
+        });
+    filter.apply().unwrap();
+    unsafe { libc::signal(libc::SIGUSR1, on_signal as *const () as libc::sighandler_t) };
+
+    for (name, path) in [
+        ("stack", "/a\0"),
+        ("mmap", "/b\0"),
+        ("mmap signaled", "/c\0"),
+        ("patched", "/restrict/patch-me\0"),
+    ] {
+        let fd = unsafe { libc::open(path.as_ptr() as *const libc::c_char, libc::O_RDONLY) };
+        println!("{name}: {}", fd >= 0);
+    }
+
+    let mut uts = unsafe { std::mem::zeroed::<libc::utsname>() };
+    unsafe { libc::uname(&mut uts) };
+    let sysname = unsafe { CStr::from_ptr(uts.sysname.as_ptr()) };
+    println!("signal delivered: {}", SIGNALED.load(Ordering::Relaxed));
+    println!("sysname: {}", sysname.to_string_lossy());
+}